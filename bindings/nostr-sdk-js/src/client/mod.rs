@@ -38,8 +38,8 @@ impl JsClient {
 
     /// Get current `Keys`
     #[wasm_bindgen(getter)]
-    pub fn keys(&self) -> JsKeys {
-        self.inner.keys().into()
+    pub fn keys(&self) -> Option<JsKeys> {
+        self.inner.keys().map(|k| k.into())
     }
 
     /// Completly shutdown `Client`
@@ -109,7 +109,7 @@ impl JsClient {
     /// Unsubscribe
     #[wasm_bindgen]
     pub async fn unsubscribe(&self) {
-        self.inner.unsubscribe().await;
+        self.inner.unsubscribe_all().await;
     }
 
     /// Get events of filters
@@ -247,7 +247,7 @@ export class Client {
   /** Update default difficulty for new `Event` */
   updateDifficulty(difficulty: number): void
   /** Get current `Keys` */
-  keys(): JsKeys
+  keys(): JsKeys | null
   /** Completly shutdown `Client` */
   shutdown(): Promise<void>
   /** Get relays */
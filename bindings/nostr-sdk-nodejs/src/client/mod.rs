@@ -48,8 +48,8 @@ impl JsClient {
 
     /// Get current `Keys`
     #[napi]
-    pub fn keys(&self) -> JsKeys {
-        self.inner.keys().into()
+    pub fn keys(&self) -> Option<JsKeys> {
+        self.inner.keys().map(|k| k.into())
     }
 
     /// Completly shutdown `Client`
@@ -123,7 +123,7 @@ impl JsClient {
     /// Unsubscribe
     #[napi]
     pub async fn unsubscribe(&self) {
-        self.inner.unsubscribe().await;
+        self.inner.unsubscribe_all().await;
     }
 
     /// Get events of filters
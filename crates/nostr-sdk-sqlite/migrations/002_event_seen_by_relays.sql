@@ -0,0 +1,11 @@
+PRAGMA user_version = 2; -- Schema version
+
+-- Event seen by relays Table
+CREATE TABLE IF NOT EXISTS event_seen_by_relays (
+id INTEGER PRIMARY KEY AUTOINCREMENT,
+event_id BLOB NOT NULL,
+relay_url TEXT NOT NULL
+);
+
+-- Event seen by relays Indexes
+CREATE UNIQUE INDEX IF NOT EXISTS event_seen_by_relays_index ON event_seen_by_relays(event_id,relay_url);
@@ -0,0 +1,23 @@
+PRAGMA user_version = 3; -- Schema version
+
+-- Deleted event IDs Table (NIP-09)
+CREATE TABLE IF NOT EXISTS deleted_ids (
+id INTEGER PRIMARY KEY AUTOINCREMENT,
+event_id BLOB NOT NULL,
+pubkey BLOB NOT NULL
+);
+
+-- Deleted event IDs Indexes
+CREATE UNIQUE INDEX IF NOT EXISTS deleted_ids_index ON deleted_ids(event_id,pubkey);
+
+-- Deleted coordinates Table (NIP-09)
+CREATE TABLE IF NOT EXISTS deleted_coordinates (
+id INTEGER PRIMARY KEY AUTOINCREMENT,
+kind INTEGER NOT NULL,
+pubkey BLOB NOT NULL,
+identifier TEXT NOT NULL, -- empty for replaceable events
+created_at INTEGER NOT NULL -- created_at of the deletion event
+);
+
+-- Deleted coordinates Indexes
+CREATE UNIQUE INDEX IF NOT EXISTS deleted_coordinates_index ON deleted_coordinates(kind,pubkey,identifier);
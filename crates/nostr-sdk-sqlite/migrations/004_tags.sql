@@ -0,0 +1,11 @@
+PRAGMA user_version = 4; -- Schema version
+
+-- Tags event IDs are stored as hex, like the events IDs
+DELETE FROM tags WHERE typeof(event_id) = 'blob' AND lower(hex(event_id)) NOT IN (SELECT id FROM events);
+UPDATE tags SET event_id = lower(hex(event_id)) WHERE typeof(event_id) = 'blob';
+
+-- Allow duplicated tags
+DROP INDEX IF EXISTS tag_composite_index;
+DROP INDEX IF EXISTS tag_kind_eid_index;
+CREATE INDEX IF NOT EXISTS tag_composite_index ON tags(event_id,kind,value);
+CREATE INDEX IF NOT EXISTS tag_kind_eid_index ON tags(kind,event_id,value);
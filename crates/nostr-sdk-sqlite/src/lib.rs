@@ -9,4 +9,4 @@
 mod migration;
 pub mod store;
 
-pub use self::store::{Error, Store};
+pub use self::store::{Error, EventInsertStatus, Store};
@@ -8,7 +8,7 @@ use rusqlite::Connection;
 use crate::store::{Error, PooledConnection};
 
 /// Latest database version
-pub const DB_VERSION: usize = 1;
+pub const DB_VERSION: usize = 4;
 
 /// Startup DB Pragmas
 pub const STARTUP_SQL: &str = r##"
@@ -51,12 +51,15 @@ pub fn run(conn: &mut PooledConnection) -> Result<(), Error> {
 
             // for initialized but out-of-date schemas, proceed to
             // upgrade sequentially until we are current.
-            /* if curr_version == 1 {
+            if curr_version == 1 {
                 curr_version = mig_1_to_2(conn)?;
-            } */
-            /* if curr_version == 2 {
+            }
+            if curr_version == 2 {
                 curr_version = mig_2_to_3(conn)?;
-            } */
+            }
+            if curr_version == 3 {
+                curr_version = mig_3_to_4(conn)?;
+            }
 
             if curr_version == DB_VERSION {
                 log::info!("All migration scripts completed successfully (v{DB_VERSION})");
@@ -86,8 +89,20 @@ fn mig_init(conn: &mut PooledConnection) -> Result<usize, Error> {
     Ok(1)
 }
 
-/* fn mig_1_to_2(conn: &mut PooledConnection) -> Result<usize, Error> {
-    conn.execute_batch(include_str!("../migrations/002.sql"))?;
+fn mig_1_to_2(conn: &mut PooledConnection) -> Result<usize, Error> {
+    conn.execute_batch(include_str!("../migrations/002_event_seen_by_relays.sql"))?;
     log::info!("database schema upgraded v1 -> v2");
     Ok(2)
-} */
+}
+
+fn mig_2_to_3(conn: &mut PooledConnection) -> Result<usize, Error> {
+    conn.execute_batch(include_str!("../migrations/003_event_deletion.sql"))?;
+    log::info!("database schema upgraded v2 -> v3");
+    Ok(3)
+}
+
+fn mig_3_to_4(conn: &mut PooledConnection) -> Result<usize, Error> {
+    conn.execute_batch(include_str!("../migrations/004_tags.sql"))?;
+    log::info!("database schema upgraded v3 -> v4");
+    Ok(4)
+}
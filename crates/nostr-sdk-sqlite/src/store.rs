@@ -3,12 +3,17 @@
 
 //! Store
 
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashSet};
 use std::net::SocketAddr;
 use std::path::Path;
+use std::str::FromStr;
 
-use nostr::{Event, Url};
+use nostr::secp256k1::XOnlyPublicKey;
+use nostr::{Event, EventId, Filter, Kind, Timestamp, Url};
 use r2d2_sqlite::SqliteConnectionManager;
-use rusqlite::OpenFlags;
+use rusqlite::types::Value;
+use rusqlite::{Connection, OpenFlags, Row};
 
 use crate::migration::{self, MigrationError, STARTUP_SQL};
 
@@ -27,6 +32,32 @@ pub enum Error {
     /// Migration error
     #[error(transparent)]
     Migration(#[from] MigrationError),
+    /// JSON error
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    /// Event not found
+    #[error("event not found")]
+    NotFound,
+}
+
+/// Result of [`Store::insert_event`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventInsertStatus {
+    /// Event inserted
+    Inserted,
+    /// Event inserted, replacing an older version
+    Replaced,
+    /// Event already stored
+    Duplicate,
+    /// Event rejected since a newer version is already stored
+    Rejected,
+}
+
+impl EventInsertStatus {
+    /// Check if the event has been inserted
+    pub fn is_inserted(&self) -> bool {
+        matches!(self, Self::Inserted | Self::Replaced)
+    }
 }
 
 /// Store
@@ -111,22 +142,845 @@ impl Store {
     }
 
     /// Insert new event
-    pub fn insert_event(&self, event: Event) -> Result<(), Error> {
-        let conn = self.pool.get()?;
+    ///
+    /// For replaceable and parameterized replaceable events only the newest version is kept:
+    /// older versions are deleted, while an event older than the stored one is rejected.
+    ///
+    /// Expired events (NIP-40) and events deleted by their author (NIP-09) are rejected.
+    /// Event deletions (kind `5`) remove the referenced events.
+    pub fn insert_event(&self, event: Event) -> Result<EventInsertStatus, Error> {
+        let mut conn = self.pool.get()?;
+        let tx = conn.transaction()?;
+        let event_id: String = event.id.to_hex();
+        let pubkey: String = event.pubkey.to_string();
+
+        // Check if already stored
+        let exists: bool = tx.query_row(
+            "SELECT EXISTS(SELECT 1 FROM events WHERE id = ?);",
+            [&event_id],
+            |row| row.get(0),
+        )?;
+        if exists {
+            return Ok(EventInsertStatus::Duplicate);
+        }
+
+        // Check if expired or deleted
+        if event.is_expired() || is_deleted(&tx, &event)? {
+            return Ok(EventInsertStatus::Rejected);
+        }
+
+        // Remove older versions of replaceable events
+        let mut replaced: bool = false;
+        if event.kind.is_replaceable() || event.kind.is_parameterized_replaceable() {
+            let identifier: Option<&str> = if event.kind.is_parameterized_replaceable() {
+                Some(event.identifier().unwrap_or_default())
+            } else {
+                None
+            };
+
+            let mut to_delete: Vec<String> = Vec::new();
+            {
+                let mut stmt =
+                    tx.prepare("SELECT id, created_at FROM events WHERE pubkey = ? AND kind = ?;")?;
+                let mut rows = stmt.query((&pubkey, event.kind.as_u64()))?;
+                while let Some(row) = rows.next()? {
+                    let id: String = row.get(0)?;
+                    let created_at: u64 = row.get(1)?;
+
+                    if let Some(identifier) = identifier {
+                        if stored_identifier(&tx, &id)? != identifier {
+                            continue;
+                        }
+                    }
+
+                    // On same timestamp, keep the event with the lowest id
+                    let created_at_new: u64 = event.created_at.as_u64();
+                    if created_at < created_at_new
+                        || (created_at == created_at_new && id > event_id)
+                    {
+                        to_delete.push(id);
+                    } else {
+                        return Ok(EventInsertStatus::Rejected);
+                    }
+                }
+            }
+
+            for id in to_delete.into_iter() {
+                delete_event_by_id(&tx, &id)?;
+                replaced = true;
+            }
+        }
+
+        // Remove events referenced by deletion
+        if event.kind == Kind::EventDeletion {
+            apply_deletion(&tx, &event)?;
+        }
+
         // Insert event
-        conn.execute(
-            "INSERT OR IGNORE INTO events (id, pubkey, created_at, kind, content, sig) VALUES (?, ?, ?, ?, ?, ?);",
-            (event.id.to_hex(), &event.pubkey.to_string(), event.created_at.as_u64(), event.kind.as_u64(), event.content, event.sig.to_string()),
+        tx.execute(
+            "INSERT INTO events (id, pubkey, created_at, kind, content, sig) VALUES (?, ?, ?, ?, ?, ?);",
+            (&event_id, &pubkey, event.created_at.as_u64(), event.kind.as_u64(), event.content, event.sig.to_string()),
         )?;
+
         // Insert tags
+        {
+            let mut stmt =
+                tx.prepare("INSERT INTO tags (event_id, kind, value) VALUES (?, ?, ?)")?;
+            for tag in event.tags.into_iter() {
+                let tag: Vec<String> = tag.as_vec();
+                let kind = &tag[0];
+                let value = tag.get(1..);
+                stmt.execute((&event_id, kind, serde_json::json!(value)))?;
+            }
+        }
+        tx.commit()?;
+
+        if replaced {
+            Ok(EventInsertStatus::Replaced)
+        } else {
+            Ok(EventInsertStatus::Inserted)
+        }
+    }
+
+    /// Delete expired events (NIP-40)
+    ///
+    /// Return the number of deleted events.
+    pub fn purge_expired(&self) -> Result<usize, Error> {
+        self.purge_expired_at(Timestamp::now())
+    }
+
+    fn purge_expired_at(&self, now: Timestamp) -> Result<usize, Error> {
+        let mut conn = self.pool.get()?;
+        let tx = conn.transaction()?;
+        let ids: Vec<String> = {
+            let mut stmt = tx.prepare(
+                "SELECT event_id FROM tags WHERE kind = 'expiration' AND CAST(json_extract(value, '$[0]') AS INTEGER) < ?;",
+            )?;
+            let rows = stmt.query_map([now.as_u64()], |row| row.get(0))?;
+            rows.collect::<Result<_, _>>()?
+        };
+        for id in ids.iter() {
+            delete_event_by_id(&tx, id)?;
+        }
+        tx.commit()?;
+        Ok(ids.len())
+    }
+
+    /// Mark [`EventId`] as seen by relay
+    pub fn event_seen_on_relay(&self, event_id: EventId, relay_url: Url) -> Result<(), Error> {
+        let conn = self.pool.get()?;
+        conn.execute(
+            "INSERT OR IGNORE INTO event_seen_by_relays (event_id, relay_url) VALUES (?, ?);",
+            (event_id.to_hex(), relay_url),
+        )?;
+        Ok(())
+    }
+
+    /// Get list of relays that have seen the [`EventId`]
+    pub fn event_seen_on_relays(&self, event_id: EventId) -> Result<Vec<Url>, Error> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            "SELECT relay_url FROM event_seen_by_relays WHERE event_id = ? ORDER BY id ASC;",
+        )?;
+        let mut rows = stmt.query([event_id.to_hex()])?;
+        let mut relays: Vec<Url> = Vec::new();
+        while let Some(row) = rows.next()? {
+            relays.push(row.get(0)?);
+        }
+        Ok(relays)
+    }
+
+    /// Get [`Event`] by [`EventId`]
+    pub fn event_by_id(&self, event_id: EventId) -> Result<Event, Error> {
+        let conn = self.pool.get()?;
         let mut stmt =
-            conn.prepare("INSERT OR IGNORE INTO tags (event_id, kind, value) VALUES (?, ?, ?)")?;
-        for tag in event.tags.into_iter() {
-            let tag: Vec<String> = tag.as_vec();
-            let kind = &tag[0];
-            let value = tag.get(1..);
-            stmt.execute((event.id.as_bytes(), kind, serde_json::json!(value)))?;
+            conn.prepare(&format!("SELECT {EVENT_COLUMNS} FROM events WHERE id = ?;"))?;
+        let mut rows = stmt.query([event_id.to_hex()])?;
+        match rows.next()? {
+            Some(row) => event_from_row(row),
+            None => Err(Error::NotFound),
+        }
+    }
+
+    /// Query stored events
+    ///
+    /// Events matching at least one of the [`Filter`]s are returned, ordered by `created_at` (newest first).
+    /// The `limit` of each [`Filter`] is honoured.
+    pub fn query(&self, filters: Vec<Filter>) -> Result<Vec<Event>, Error> {
+        let conn = self.pool.get()?;
+        let mut ids: HashSet<EventId> = HashSet::new();
+        let mut events: Vec<Event> = Vec::new();
+
+        for filter in filters.into_iter() {
+            for event in query_filter(&conn, &filter)?.into_iter() {
+                if ids.insert(event.id) {
+                    events.push(event);
+                }
+            }
+        }
+
+        events.sort_by_key(|e| Reverse(e.created_at));
+        Ok(events)
+    }
+
+    /// Count stored events matching the [`Filter`]s
+    pub fn count(&self, filters: Vec<Filter>) -> Result<usize, Error> {
+        // Custom tag queries can only be evaluated on the events
+        if filters.iter().any(has_custom_tags) {
+            return Ok(self.query(filters)?.len());
+        }
+
+        if filters.is_empty() {
+            return Ok(0);
+        }
+
+        let mut queries: Vec<String> = Vec::with_capacity(filters.len());
+        let mut params: Vec<Value> = Vec::new();
+        for filter in filters.iter() {
+            let (sql, p) = build_query(filter, "id");
+            queries.push(format!("SELECT id FROM ({sql})"));
+            params.extend(p);
+        }
+        let sql: String = format!("SELECT COUNT(*) FROM ({});", queries.join(" UNION "));
+
+        let conn = self.pool.get()?;
+        let count: usize =
+            conn.query_row(&sql, rusqlite::params_from_iter(params), |row| row.get(0))?;
+        Ok(count)
+    }
+
+    /// Delete all stored events matching the [`Filter`]
+    pub fn delete(&self, filter: Filter) -> Result<(), Error> {
+        let events: Vec<Event> = self.query(vec![filter])?;
+        let mut conn = self.pool.get()?;
+        let tx = conn.transaction()?;
+        {
+            for event in events.into_iter() {
+                delete_event_by_id(&tx, &event.id.to_hex())?;
+            }
         }
+        tx.commit()?;
         Ok(())
     }
 }
+
+/// Query the events matching a single [`Filter`]
+fn query_filter(conn: &Connection, filter: &Filter) -> Result<Vec<Event>, Error> {
+    let (sql, params) = build_query(filter, EVENT_COLUMNS);
+    let mut stmt = conn.prepare(&sql)?;
+    let mut rows = stmt.query(rusqlite::params_from_iter(params))?;
+
+    let mut events: Vec<Event> = Vec::new();
+    while let Some(row) = rows.next()? {
+        // Events are ordered by `created_at`, so the first matching ones are kept
+        if filter.limit.map_or(false, |limit| events.len() >= limit) {
+            break;
+        }
+
+        let event: Event = event_from_row(row)?;
+        // Apply exactly the same semantic of relays
+        if filter.match_event(&event) && !event.is_expired() {
+            events.push(event);
+        }
+    }
+
+    Ok(events)
+}
+
+/// Build an [`Event`] from a row selected with [`EVENT_COLUMNS`]
+fn event_from_row(row: &Row) -> Result<Event, Error> {
+    let id: String = row.get(0)?;
+    let pubkey: String = row.get(1)?;
+    let created_at: u64 = row.get(2)?;
+    let kind: u64 = row.get(3)?;
+    let content: String = row.get(4)?;
+    let sig: String = row.get(5)?;
+    let tags: serde_json::Value = row.get(6)?;
+
+    let tags: Vec<(String, Option<Vec<String>>)> = serde_json::from_value(tags)?;
+    let tags: Vec<Vec<String>> = tags
+        .into_iter()
+        .map(|(kind, values)| {
+            let mut tag: Vec<String> = vec![kind];
+            tag.extend(values.unwrap_or_default());
+            tag
+        })
+        .collect();
+
+    Ok(serde_json::from_value(serde_json::json!({
+        "id": id,
+        "pubkey": pubkey,
+        "created_at": created_at,
+        "kind": kind,
+        "tags": tags,
+        "content": content,
+        "sig": sig,
+    }))?)
+}
+
+/// Columns of an event row, with the tags (ordered as in the event) as JSON array of `[kind, values]`
+const EVENT_COLUMNS: &str = "id, pubkey, created_at, kind, content, sig, (SELECT json_group_array(json_array(t.kind, json(t.value))) FROM (SELECT kind, value FROM tags WHERE tags.event_id = events.id ORDER BY tags.id) AS t)";
+
+/// Delete a stored event (and its relays bookkeeping) by hex id
+fn delete_event_by_id(conn: &Connection, event_id: &str) -> Result<(), Error> {
+    conn.execute("DELETE FROM events WHERE id = ?;", [event_id])?;
+    conn.execute(
+        "DELETE FROM event_seen_by_relays WHERE event_id = ?;",
+        [event_id],
+    )?;
+    Ok(())
+}
+
+/// Check if the event has been deleted by its author (NIP-09)
+fn is_deleted(conn: &Connection, event: &Event) -> Result<bool, Error> {
+    let deleted: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM deleted_ids WHERE event_id = ? AND pubkey = ?);",
+        (event.id.to_hex(), event.pubkey.to_string()),
+        |row| row.get(0),
+    )?;
+    if deleted {
+        return Ok(true);
+    }
+
+    if event.kind.is_replaceable() || event.kind.is_parameterized_replaceable() {
+        let deleted: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM deleted_coordinates WHERE kind = ? AND pubkey = ? AND identifier = ? AND created_at >= ?);",
+            (
+                event.kind.as_u64(),
+                event.pubkey.to_string(),
+                coordinate_identifier(event.kind, event.identifier()),
+                event.created_at.as_u64(),
+            ),
+            |row| row.get(0),
+        )?;
+        return Ok(deleted);
+    }
+
+    Ok(false)
+}
+
+/// Remove the events referenced by an event deletion (NIP-09)
+///
+/// Only the events of the same author are removed. Deletions are recorded,
+/// so the referenced events can't be inserted again.
+fn apply_deletion(conn: &Connection, deletion: &Event) -> Result<(), Error> {
+    let pubkey: String = deletion.pubkey.to_string();
+    for tag in deletion.tags.iter() {
+        let tag: Vec<String> = tag.as_vec();
+        match (tag[0].as_str(), tag.get(1)) {
+            ("e", Some(id)) => {
+                conn.execute(
+                    "INSERT OR IGNORE INTO deleted_ids (event_id, pubkey) VALUES (?, ?);",
+                    (id, &pubkey),
+                )?;
+                let ids: Vec<String> = {
+                    let mut stmt = conn.prepare(
+                        "SELECT id FROM events WHERE id = ? AND pubkey = ? AND kind != ?;",
+                    )?;
+                    let rows = stmt
+                        .query_map((id, &pubkey, Kind::EventDeletion.as_u64()), |row| {
+                            row.get(0)
+                        })?;
+                    rows.collect::<Result<_, _>>()?
+                };
+                for id in ids.iter() {
+                    delete_event_by_id(conn, id)?;
+                }
+            }
+            ("a", Some(coordinate)) => {
+                let (kind, author, identifier) = match parse_coordinate(coordinate) {
+                    Some(coordinate) => coordinate,
+                    None => continue,
+                };
+                if author != deletion.pubkey {
+                    continue;
+                }
+
+                conn.execute(
+                    "INSERT INTO deleted_coordinates (kind, pubkey, identifier, created_at) VALUES (?, ?, ?, ?) ON CONFLICT(kind, pubkey, identifier) DO UPDATE SET created_at = MAX(created_at, excluded.created_at);",
+                    (kind.as_u64(), &pubkey, &identifier, deletion.created_at.as_u64()),
+                )?;
+
+                let ids: Vec<String> = {
+                    let mut stmt = conn.prepare(
+                        "SELECT id FROM events WHERE pubkey = ? AND kind = ? AND created_at <= ?;",
+                    )?;
+                    let rows = stmt.query_map(
+                        (&pubkey, kind.as_u64(), deletion.created_at.as_u64()),
+                        |row| row.get(0),
+                    )?;
+                    rows.collect::<Result<_, _>>()?
+                };
+                for id in ids.iter() {
+                    if !kind.is_parameterized_replaceable()
+                        || stored_identifier(conn, id)? == identifier
+                    {
+                        delete_event_by_id(conn, id)?;
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
+/// Parse an `a` tag coordinate (`<kind>:<pubkey>:<d-identifier>`)
+fn parse_coordinate(coordinate: &str) -> Option<(Kind, XOnlyPublicKey, String)> {
+    let mut split = coordinate.splitn(3, ':');
+    let kind: Kind = split.next()?.parse().ok()?;
+    let public_key = XOnlyPublicKey::from_str(split.next()?).ok()?;
+    if !kind.is_replaceable() && !kind.is_parameterized_replaceable() {
+        return None;
+    }
+    let identifier: String = coordinate_identifier(kind, split.next());
+    Some((kind, public_key, identifier))
+}
+
+/// Identifier of a coordinate: the `d` tag for parameterized replaceable events, empty otherwise
+fn coordinate_identifier(kind: Kind, identifier: Option<&str>) -> String {
+    if kind.is_parameterized_replaceable() {
+        identifier.unwrap_or_default().to_string()
+    } else {
+        String::new()
+    }
+}
+
+/// Get the identifier (`d` tag) of a stored event (empty string if missing)
+fn stored_identifier(conn: &Connection, event_id: &str) -> Result<String, Error> {
+    let mut stmt = conn.prepare(
+        "SELECT value FROM tags WHERE event_id = ? AND kind = 'd' ORDER BY id ASC LIMIT 1;",
+    )?;
+    let mut rows = stmt.query([event_id])?;
+    match rows.next()? {
+        Some(row) => {
+            let value: serde_json::Value = row.get(0)?;
+            let values: Option<Vec<String>> = serde_json::from_value(value)?;
+            Ok(values
+                .unwrap_or_default()
+                .into_iter()
+                .next()
+                .unwrap_or_default())
+        }
+        None => Ok(String::new()),
+    }
+}
+
+/// Build the SQL query (and its params) selecting the `columns` of the events matching a single [`Filter`]
+///
+/// The custom tag queries are not translated: in that case the events must be matched
+/// with [`Filter::match_event`] and the limit is not applied.
+fn build_query(filter: &Filter, columns: &str) -> (String, Vec<Value>) {
+    let mut conditions: Vec<String> = Vec::new();
+    let mut params: Vec<Value> = Vec::new();
+
+    // Ids and authors are prefixes
+    for (column, prefixes) in [("id", &filter.ids), ("pubkey", &filter.authors)] {
+        if let Some(prefixes) = prefixes {
+            let mut or: Vec<String> = Vec::new();
+            for prefix in prefixes.iter() {
+                or.push(format!("substr({column}, 1, ?) = ?"));
+                params.push(Value::Integer(prefix.len() as i64));
+                params.push(Value::Text(prefix.clone()));
+            }
+            conditions.push(format!("({})", or_or_false(or)));
+        }
+    }
+
+    if let Some(kinds) = &filter.kinds {
+        let placeholders: Vec<&str> = kinds.iter().map(|_| "?").collect();
+        conditions.push(format!("kind IN ({})", placeholders.join(",")));
+        params.extend(kinds.iter().map(|k| Value::Integer(k.as_u64() as i64)));
+    }
+
+    if let Some(since) = filter.since {
+        conditions.push(String::from("created_at >= ?"));
+        params.push(Value::Integer(since.as_i64()));
+    }
+
+    if let Some(until) = filter.until {
+        conditions.push(String::from("created_at <= ?"));
+        params.push(Value::Integer(until.as_i64()));
+    }
+
+    // Tags
+    for (kind, values) in tag_queries(filter).into_iter() {
+        let placeholders: Vec<&str> = values.iter().map(|_| "?").collect();
+        conditions.push(format!(
+            "EXISTS (SELECT 1 FROM tags WHERE tags.event_id = events.id AND tags.kind = ? AND json_extract(tags.value, '$[0]') IN ({}))",
+            placeholders.join(",")
+        ));
+        params.push(Value::Text(kind));
+        params.extend(values.into_iter().map(Value::Text));
+    }
+
+    // Skip expired events (NIP-40)
+    conditions.push(String::from(
+        "NOT EXISTS (SELECT 1 FROM tags WHERE tags.event_id = events.id AND tags.kind = 'expiration' AND CAST(json_extract(tags.value, '$[0]') AS INTEGER) < ?)",
+    ));
+    params.push(Value::Integer(Timestamp::now().as_i64()));
+
+    let mut sql = format!(
+        "SELECT {columns} FROM events WHERE {} ORDER BY created_at DESC",
+        conditions.join(" AND ")
+    );
+
+    // The limit can be applied directly only if the whole filter is translated to SQL
+    if let (Some(limit), false) = (filter.limit, has_custom_tags(filter)) {
+        sql.push_str(" LIMIT ?");
+        params.push(Value::Integer(limit as i64));
+    }
+
+    (sql, params)
+}
+
+/// Check if the [`Filter`] has custom tag queries, not translated to SQL
+fn has_custom_tags(filter: &Filter) -> bool {
+    filter.custom.keys().any(|k| k.starts_with('#'))
+}
+
+/// Collect the tag queries of a [`Filter`] (`#e`, `#p`, `#t`, `#r` and generic tags)
+fn tag_queries(filter: &Filter) -> BTreeMap<String, Vec<String>> {
+    let mut queries: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+    if let Some(events) = &filter.events {
+        queries.insert(
+            String::from("e"),
+            events.iter().map(|id| id.to_hex()).collect(),
+        );
+    }
+
+    if let Some(pubkeys) = &filter.pubkeys {
+        queries.insert(
+            String::from("p"),
+            pubkeys.iter().map(|p| p.to_string()).collect(),
+        );
+    }
+
+    if let Some(hashtags) = &filter.hashtags {
+        queries.insert(String::from("t"), hashtags.clone());
+    }
+
+    if let Some(references) = &filter.references {
+        queries.insert(String::from("r"), references.clone());
+    }
+
+    for (tag, values) in filter.generic_tags.iter() {
+        let entry = queries.entry(tag.to_string()).or_default();
+        for value in values.iter() {
+            if !entry.contains(value) {
+                entry.push(value.clone());
+            }
+        }
+    }
+
+    queries
+}
+
+fn or_or_false(or: Vec<String>) -> String {
+    if or.is_empty() {
+        String::from("0")
+    } else {
+        or.join(" OR ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::str::FromStr;
+    use std::time::Duration;
+
+    use nostr::event::tag::TagKind;
+    use nostr::event::tag::UncheckedUrl;
+    use nostr::{EventBuilder, Keys, SubscriptionId, Tag};
+
+    use super::*;
+
+    fn temp_store() -> (Store, PathBuf) {
+        let path = std::env::temp_dir().join(format!("{}.db", SubscriptionId::generate()));
+        (Store::open(&path).unwrap(), path)
+    }
+
+    fn remove_store(store: Store, path: PathBuf) {
+        store.close();
+        for ext in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{ext}", path.display()));
+        }
+    }
+
+    fn event_at(keys: &Keys, builder: EventBuilder, created_at: u64) -> Event {
+        let mut unsigned = builder.to_unsigned_event(keys.public_key());
+        unsigned.created_at = Timestamp::from(created_at);
+        unsigned.id = EventId::new(
+            &unsigned.pubkey,
+            unsigned.created_at,
+            &unsigned.kind,
+            &unsigned.tags,
+            &unsigned.content,
+        );
+        unsigned.sign(keys).unwrap()
+    }
+
+    #[test]
+    fn test_query_events() {
+        let (store, path) = temp_store();
+        let keys = Keys::generate();
+
+        // Duplicated tags are kept
+        let note = EventBuilder::new_text_note(
+            "hello",
+            &[
+                Tag::Hashtag("nostr".to_string()),
+                Tag::Hashtag("nostr".to_string()),
+            ],
+        )
+        .to_event(&keys)
+        .unwrap();
+        let article = EventBuilder::long_form_text_note(
+            "article",
+            &[Tag::Identifier("my-article".to_string())],
+        )
+        .to_event(&keys)
+        .unwrap();
+        assert_eq!(
+            store.insert_event(note.clone()).unwrap(),
+            EventInsertStatus::Inserted
+        );
+        assert_eq!(
+            store.insert_event(note.clone()).unwrap(),
+            EventInsertStatus::Duplicate
+        );
+        assert!(store.insert_event(article.clone()).unwrap().is_inserted());
+
+        assert_eq!(store.event_by_id(note.id).unwrap(), note);
+        assert_eq!(store.count(vec![Filter::new()]).unwrap(), 2);
+        assert_eq!(
+            store
+                .query(vec![Filter::new().kind(Kind::TextNote)])
+                .unwrap(),
+            vec![note.clone()]
+        );
+        assert_eq!(
+            store.query(vec![Filter::new().hashtag("nostr")]).unwrap(),
+            vec![note.clone()]
+        );
+        assert_eq!(
+            store
+                .query(vec![Filter::new().identifier("my-article")])
+                .unwrap(),
+            vec![article.clone()]
+        );
+        assert_eq!(
+            store
+                .count(vec![
+                    Filter::new().author(&keys.public_key().to_string()[..8])
+                ])
+                .unwrap(),
+            2
+        );
+        assert_eq!(
+            store
+                .count(vec![
+                    Filter::new().kind(Kind::TextNote),
+                    Filter::new().hashtag("nostr"),
+                ])
+                .unwrap(),
+            1
+        );
+        assert_eq!(store.count(vec![Filter::new().limit(1)]).unwrap(), 1);
+
+        let relay_url = Url::parse("wss://relay.example.com").unwrap();
+        store
+            .event_seen_on_relay(note.id, relay_url.clone())
+            .unwrap();
+        store
+            .event_seen_on_relay(note.id, relay_url.clone())
+            .unwrap();
+        assert_eq!(
+            store.event_seen_on_relays(note.id).unwrap(),
+            vec![relay_url]
+        );
+
+        store.delete(Filter::new().kind(Kind::TextNote)).unwrap();
+        assert!(store.event_seen_on_relays(note.id).unwrap().is_empty());
+        assert!(matches!(store.event_by_id(note.id), Err(Error::NotFound)));
+        assert_eq!(store.count(vec![Filter::new()]).unwrap(), 1);
+
+        remove_store(store, path);
+    }
+
+    #[test]
+    fn test_replaceable_events() {
+        let (store, path) = temp_store();
+        let keys = Keys::generate();
+
+        let metadata_1 = event_at(&keys, EventBuilder::new(Kind::Metadata, "{}", &[]), 10);
+        let metadata_2 = event_at(
+            &keys,
+            EventBuilder::new(Kind::Metadata, "{\"name\":\"a\"}", &[]),
+            20,
+        );
+        assert_eq!(
+            store.insert_event(metadata_1.clone()).unwrap(),
+            EventInsertStatus::Inserted
+        );
+        assert_eq!(
+            store.insert_event(metadata_2.clone()).unwrap(),
+            EventInsertStatus::Replaced
+        );
+        assert_eq!(
+            store.insert_event(metadata_1.clone()).unwrap(),
+            EventInsertStatus::Rejected
+        );
+        assert_eq!(
+            store
+                .query(vec![Filter::new().kind(Kind::Metadata)])
+                .unwrap(),
+            vec![metadata_2]
+        );
+
+        let article = |d: &str, created_at: u64| {
+            event_at(
+                &keys,
+                EventBuilder::long_form_text_note(d, &[Tag::Identifier(d.to_string())]),
+                created_at,
+            )
+        };
+        let article_a_1 = article("a", 10);
+        let article_a_2 = article("a", 20);
+        let article_b = article("b", 5);
+        assert!(store
+            .insert_event(article_a_2.clone())
+            .unwrap()
+            .is_inserted());
+        assert_eq!(
+            store.insert_event(article_b.clone()).unwrap(),
+            EventInsertStatus::Inserted
+        );
+        assert_eq!(
+            store.insert_event(article_a_1).unwrap(),
+            EventInsertStatus::Rejected
+        );
+        assert_eq!(
+            store
+                .query(vec![Filter::new().kind(Kind::LongFormTextNote)])
+                .unwrap(),
+            vec![article_a_2, article_b]
+        );
+
+        remove_store(store, path);
+    }
+
+    #[test]
+    fn test_deletion_and_expiration() {
+        let (store, path) = temp_store();
+        let keys = Keys::generate();
+        let other_keys = Keys::generate();
+
+        // Delete by id
+        let note = EventBuilder::new_text_note("hello", &[])
+            .to_event(&keys)
+            .unwrap();
+        store.insert_event(note.clone()).unwrap();
+        let other_deletion = EventBuilder::delete::<String>(vec![note.id], None)
+            .to_event(&other_keys)
+            .unwrap();
+        store.insert_event(other_deletion).unwrap();
+        assert_eq!(store.event_by_id(note.id).unwrap(), note);
+        let deletion = EventBuilder::delete::<String>(vec![note.id], None)
+            .to_event(&keys)
+            .unwrap();
+        store.insert_event(deletion).unwrap();
+        assert!(matches!(store.event_by_id(note.id), Err(Error::NotFound)));
+        assert_eq!(
+            store.insert_event(note).unwrap(),
+            EventInsertStatus::Rejected
+        );
+
+        // Delete by coordinate
+        let article = event_at(
+            &keys,
+            EventBuilder::long_form_text_note("article", &[Tag::Identifier("a".to_string())]),
+            10,
+        );
+        store.insert_event(article.clone()).unwrap();
+        let deletion = EventBuilder::new(
+            Kind::EventDeletion,
+            "",
+            &[Tag::A {
+                kind: Kind::LongFormTextNote,
+                public_key: keys.public_key(),
+                identifier: String::from("a"),
+                relay_url: UncheckedUrl::from_str("").unwrap(),
+            }],
+        )
+        .to_event(&keys)
+        .unwrap();
+        store.insert_event(deletion).unwrap();
+        assert!(matches!(
+            store.event_by_id(article.id),
+            Err(Error::NotFound)
+        ));
+        assert_eq!(
+            store.insert_event(article).unwrap(),
+            EventInsertStatus::Rejected
+        );
+
+        // Coordinates are normalized
+        let article = event_at(
+            &keys,
+            EventBuilder::long_form_text_note("article", &[Tag::Identifier("b".to_string())]),
+            10,
+        );
+        store.insert_event(article.clone()).unwrap();
+        let coordinate = format!(
+            "{}:{}:b",
+            Kind::LongFormTextNote.as_u64(),
+            keys.public_key().to_string().to_uppercase()
+        );
+        let deletion = EventBuilder::new(
+            Kind::EventDeletion,
+            "",
+            &[Tag::Generic(TagKind::A, vec![coordinate])],
+        )
+        .to_event(&keys)
+        .unwrap();
+        store.insert_event(deletion).unwrap();
+        assert_eq!(
+            store.insert_event(article).unwrap(),
+            EventInsertStatus::Rejected
+        );
+
+        // Expiration
+        let expired = EventBuilder::new_text_note(
+            "expired",
+            &[Tag::Expiration(Timestamp::now() - Duration::from_secs(10))],
+        )
+        .to_event(&keys)
+        .unwrap();
+        assert_eq!(
+            store.insert_event(expired).unwrap(),
+            EventInsertStatus::Rejected
+        );
+        let expiration = Timestamp::now() + Duration::from_secs(60);
+        let expiring = EventBuilder::new_text_note("expiring", &[Tag::Expiration(expiration)])
+            .to_event(&keys)
+            .unwrap();
+        assert!(store.insert_event(expiring).unwrap().is_inserted());
+        assert_eq!(store.purge_expired().unwrap(), 0);
+        assert_eq!(store.purge_expired_at(expiration).unwrap(), 0);
+        assert_eq!(
+            store
+                .purge_expired_at(expiration + Duration::from_secs(1))
+                .unwrap(),
+            1
+        );
+        assert!(store
+            .query(vec![Filter::new().kind(Kind::TextNote)])
+            .unwrap()
+            .is_empty());
+
+        remove_store(store, path);
+    }
+}
@@ -0,0 +1,38 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+use std::str::FromStr;
+
+use nostr_sdk::prelude::*;
+
+const SIGNER_SECRET_KEY: &str = "nsec1ufnus6pju578ste3v90xd5m2decpuzpql2295m3sknqcjzyys9ls0qlc85";
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    env_logger::init();
+
+    let secret_key = SecretKey::from_bech32(SIGNER_SECRET_KEY)?;
+    let keys = Keys::new(secret_key);
+
+    // Allow the app to sign only text notes and reactions
+    let uri =
+        NostrConnectURI::from_str(&std::env::args().nth(1).expect("missing nostrconnect uri"))?;
+    let policy = AppPermissionsPolicy::new();
+    policy
+        .grant(
+            uri.public_key,
+            AppPermissions::new()
+                .allow_method("sign_event")
+                .allow_kinds([Kind::TextNote, Kind::Reaction]),
+        )
+        .await;
+
+    let signer = NostrConnectSigner::new(keys, policy);
+    signer.add_relay("wss://relay.damus.io", None).await?;
+    signer.connect_app(&uri).await?;
+
+    println!("Serving {}", signer.public_key().to_bech32()?);
+    signer.serve().await?;
+
+    Ok(())
+}
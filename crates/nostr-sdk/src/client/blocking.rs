@@ -5,19 +5,26 @@
 
 use std::collections::HashMap;
 use std::net::SocketAddr;
+use std::sync::Arc;
 use std::time::Duration;
 
 use nostr::key::XOnlyPublicKey;
+use nostr::nips::nip10::ThreadNode;
+#[cfg(feature = "nip17")]
+use nostr::nips::nip17::Conversation;
 use nostr::url::Url;
 use nostr::{
-    ChannelId, ClientMessage, Contact, Event, EventId, Filter, Keys, Metadata, Result, Tag,
+    ChannelId, ClientMessage, Contact, Event, EventId, Filter, Keys, Metadata, NostrSigner, Result,
+    SubscriptionId, Tag,
 };
 use tokio::sync::broadcast;
 
 #[cfg(feature = "nip46")]
 use super::signer::remote::RemoteSigner;
 use super::{Entity, Error, Options};
-use crate::relay::{Relay, RelayOptions, RelayPoolNotification};
+use crate::relay::{
+    CountOutput, PublishOutput, PublishStatus, Relay, RelayOptions, RelayPoolNotification,
+};
 use crate::RUNTIME;
 
 #[derive(Debug, Clone)]
@@ -69,11 +76,33 @@ impl Client {
         }
     }
 
+    pub fn with_signer<T>(signer: T) -> Self
+    where
+        T: NostrSigner + 'static,
+    {
+        Self {
+            client: super::Client::with_signer(signer),
+        }
+    }
+
+    pub fn with_signer_and_opts<T>(signer: T, opts: Options) -> Self
+    where
+        T: NostrSigner + 'static,
+    {
+        Self {
+            client: super::Client::with_signer_and_opts(signer, opts),
+        }
+    }
+
     /// Get current [`Keys`]
-    pub fn keys(&self) -> Keys {
+    pub fn keys(&self) -> Option<Keys> {
         self.client.keys()
     }
 
+    pub fn signer(&self) -> Arc<dyn NostrSigner> {
+        self.client.signer()
+    }
+
     pub fn notifications(&self) -> broadcast::Receiver<RelayPoolNotification> {
         self.client.notifications()
     }
@@ -141,15 +170,32 @@ impl Client {
         RUNTIME.block_on(async { self.client.disconnect().await })
     }
 
-    pub fn subscribe(&self, filters: Vec<Filter>) {
+    pub fn auth<S>(&self, url: Url, challenge: S) -> Result<(), Error>
+    where
+        S: Into<String>,
+    {
+        RUNTIME.block_on(async { self.client.auth(url, challenge).await })
+    }
+
+    pub fn subscribe(&self, filters: Vec<Filter>) -> SubscriptionId {
+        RUNTIME.block_on(async { self.client.subscribe(filters).await })
+    }
+
+    pub fn subscribe_with_id(&self, id: SubscriptionId, filters: Vec<Filter>) {
         RUNTIME.block_on(async {
-            self.client.subscribe(filters).await;
+            self.client.subscribe_with_id(id, filters).await;
         })
     }
 
-    pub fn unsubscribe(&self) {
+    pub fn unsubscribe(&self, id: SubscriptionId) {
         RUNTIME.block_on(async {
-            self.client.unsubscribe().await;
+            self.client.unsubscribe(id).await;
+        })
+    }
+
+    pub fn unsubscribe_all(&self) {
+        RUNTIME.block_on(async {
+            self.client.unsubscribe_all().await;
         })
     }
 
@@ -161,6 +207,22 @@ impl Client {
         RUNTIME.block_on(async { self.client.get_events_of(filters, timeout).await })
     }
 
+    pub fn get_thread(
+        &self,
+        event_id: EventId,
+        timeout: Option<Duration>,
+    ) -> Result<Option<ThreadNode>, Error> {
+        RUNTIME.block_on(async { self.client.get_thread(event_id, timeout).await })
+    }
+
+    pub fn count_events_of(
+        &self,
+        filters: Vec<Filter>,
+        timeout: Option<Duration>,
+    ) -> Result<CountOutput, Error> {
+        RUNTIME.block_on(async { self.client.count_events_of(filters, timeout).await })
+    }
+
     pub fn req_events_of(&self, filters: Vec<Filter>, timeout: Option<Duration>) {
         RUNTIME.block_on(async {
             self.client.req_events_of(filters, timeout).await;
@@ -190,6 +252,21 @@ impl Client {
         RUNTIME.block_on(async { self.client.send_event_to(url, event).await })
     }
 
+    pub fn publish_event(
+        &self,
+        event: Event,
+        min_accepted: Option<usize>,
+    ) -> Result<PublishOutput, Error> {
+        RUNTIME.block_on(async { self.client.publish_event(event, min_accepted).await })
+    }
+
+    pub fn publish_event_to<S>(&self, url: S, event: Event) -> Result<PublishStatus, Error>
+    where
+        S: Into<String>,
+    {
+        RUNTIME.block_on(async { self.client.publish_event_to(url, event).await })
+    }
+
     pub fn set_metadata(&self, metadata: Metadata) -> Result<EventId, Error> {
         RUNTIME.block_on(async { self.client.set_metadata(metadata).await })
     }
@@ -238,6 +315,28 @@ impl Client {
         RUNTIME.block_on(async { self.client.send_direct_msg(receiver, msg).await })
     }
 
+    #[cfg(feature = "nip17")]
+    pub fn send_private_msg<S>(
+        &self,
+        receivers: Vec<XOnlyPublicKey>,
+        message: S,
+        reply_to: Option<EventId>,
+    ) -> Result<Vec<EventId>, Error>
+    where
+        S: Into<String>,
+    {
+        RUNTIME.block_on(async {
+            self.client
+                .send_private_msg(receivers, message, reply_to)
+                .await
+        })
+    }
+
+    #[cfg(feature = "nip17")]
+    pub fn get_private_msgs(&self, timeout: Option<Duration>) -> Result<Vec<Conversation>, Error> {
+        RUNTIME.block_on(async { self.client.get_private_msgs(timeout).await })
+    }
+
     pub fn repost_event(
         &self,
         event_id: EventId,
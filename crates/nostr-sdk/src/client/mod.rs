@@ -9,22 +9,31 @@ use std::net::SocketAddr;
 #[cfg(feature = "sqlite")]
 use std::path::Path;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::time::Duration;
 
 use nostr::event::builder::Error as EventBuilderError;
 use nostr::key::XOnlyPublicKey;
+use nostr::nips::nip10::ThreadNode;
+#[cfg(feature = "nip17")]
+use nostr::nips::nip17::{self, Conversation, PrivateDirectMessage};
 #[cfg(feature = "nip46")]
-use nostr::nips::nip46::{NostrConnectMetadata, NostrConnectURI, Request, Response};
+use nostr::nips::nip46::{NostrConnectMetadata, NostrConnectURI};
+#[cfg(feature = "nip59")]
+use nostr::nips::nip59::{self, UnwrappedGift};
 use nostr::types::metadata::Error as MetadataError;
 use nostr::url::Url;
 use nostr::{
     ChannelId, ClientMessage, Contact, Entity, Event, EventBuilder, EventId, Filter, Keys, Kind,
-    Metadata, Result, Tag,
+    Metadata, NostrSigner, RelayMessage, Result, SignerError, SubscriptionId, Tag,
 };
 use nostr_sdk_net::futures_util::Future;
 #[cfg(feature = "sqlite")]
 use nostr_sdk_sqlite::Store;
-use tokio::sync::broadcast;
+use tokio::sync::broadcast::{self, error::RecvError};
+#[cfg(feature = "nip17")]
+use tokio::sync::mpsc;
 
 #[cfg(feature = "blocking")]
 pub mod blocking;
@@ -34,9 +43,20 @@ pub mod signer;
 
 pub use self::options::Options;
 #[cfg(feature = "nip46")]
+pub use self::signer::connect::{
+    AppPermissions, AppPermissionsPolicy, NostrConnectSigner, NostrConnectSignerPolicy,
+};
+#[cfg(feature = "nip46")]
+use self::signer::remote::Nip46Signer;
+#[cfg(feature = "nip46")]
 pub use self::signer::remote::RemoteSigner;
+use crate::database::NostrDatabase;
 use crate::relay::pool::{Error as RelayPoolError, RelayPool};
-use crate::relay::{Relay, RelayOptions, RelayPoolNotification};
+use crate::relay::{
+    CountOutput, EventStream, PublishOutput, PublishStatus, Relay, RelayOptions,
+    RelayPoolNotification,
+};
+use crate::thread;
 
 /// [`Client`] error
 #[derive(Debug, thiserror::Error)]
@@ -68,6 +88,9 @@ pub enum Error {
     /// Metadata error
     #[error(transparent)]
     Metadata(#[from] MetadataError),
+    /// Signer error
+    #[error(transparent)]
+    Signer(#[from] SignerError),
     /// Notification Handler error
     #[error("notification handler error: {0}")]
     Handler(String),
@@ -83,6 +106,10 @@ pub enum Error {
     #[cfg(feature = "nip46")]
     #[error(transparent)]
     NIP46(#[from] nostr::nips::nip46::Error),
+    /// NIP59 error
+    #[cfg(feature = "nip59")]
+    #[error(transparent)]
+    NIP59(#[from] nip59::Error),
     /// JSON error
     #[cfg(feature = "nip46")]
     #[error(transparent)]
@@ -107,14 +134,20 @@ pub enum Error {
     #[cfg(feature = "nip46")]
     #[error("response not match to the request")]
     ResponseNotMatchRequest,
+    /// Event not accepted by any relay
+    #[cfg(feature = "nip17")]
+    #[error("event {} not accepted by any relay", .0.event_id)]
+    EventNotPublished(PublishOutput),
 }
 
 /// Nostr client
 #[derive(Debug, Clone)]
 pub struct Client {
     pool: RelayPool,
-    keys: Keys,
+    keys: Option<Keys>,
+    signer: Arc<dyn NostrSigner>,
     opts: Options,
+    auth_handler: Arc<AtomicBool>,
     #[cfg(feature = "nip46")]
     remote_signer: Option<RemoteSigner>,
 }
@@ -146,8 +179,10 @@ impl Client {
     pub fn with_opts(keys: &Keys, opts: Options) -> Self {
         Self {
             pool: RelayPool::new(),
-            keys: keys.clone(),
+            keys: Some(keys.clone()),
+            signer: Arc::new(keys.clone()),
             opts,
+            auth_handler: Arc::new(AtomicBool::new(false)),
             #[cfg(feature = "nip46")]
             remote_signer: None,
         }
@@ -172,14 +207,71 @@ impl Client {
         remote_signer: RemoteSigner,
         opts: Options,
     ) -> Self {
+        let pool = RelayPool::new();
+        let signer = Nip46Signer::new(
+            remote_signer.clone(),
+            app_keys.clone(),
+            pool.clone(),
+            opts.clone(),
+        );
         Self {
-            pool: RelayPool::new(),
-            keys: app_keys.clone(),
+            pool,
+            keys: Some(app_keys.clone()),
+            signer: Arc::new(signer),
             opts,
+            auth_handler: Arc::new(AtomicBool::new(false)),
             remote_signer: Some(remote_signer),
         }
     }
 
+    /// Create a new [`Client`] with a custom [`NostrSigner`]
+    ///
+    /// All the events are signed, and the direct messages encrypted, by the signer.
+    pub fn with_signer<T>(signer: T) -> Self
+    where
+        T: NostrSigner + 'static,
+    {
+        Self::with_signer_and_opts(signer, Options::default())
+    }
+
+    /// Create a new [`Client`] with a custom [`NostrSigner`] and [`Options`]
+    pub fn with_signer_and_opts<T>(signer: T, opts: Options) -> Self
+    where
+        T: NostrSigner + 'static,
+    {
+        Self {
+            pool: RelayPool::new(),
+            keys: None,
+            signer: Arc::new(signer),
+            opts,
+            auth_handler: Arc::new(AtomicBool::new(false)),
+            #[cfg(feature = "nip46")]
+            remote_signer: None,
+        }
+    }
+
+    /// New [`Client`] with custom [`NostrDatabase`]
+    pub fn with_database(keys: &Keys, database: Arc<dyn NostrDatabase>) -> Self {
+        Self::with_database_and_opts(keys, database, Options::default())
+    }
+
+    /// New [`Client`] with custom [`NostrDatabase`] and [`Options`]
+    pub fn with_database_and_opts(
+        keys: &Keys,
+        database: Arc<dyn NostrDatabase>,
+        opts: Options,
+    ) -> Self {
+        Self {
+            pool: RelayPool::with_database(database),
+            keys: Some(keys.clone()),
+            signer: Arc::new(keys.clone()),
+            opts,
+            auth_handler: Arc::new(AtomicBool::new(false)),
+            #[cfg(feature = "nip46")]
+            remote_signer: None,
+        }
+    }
+
     /// New [`Client`] with [`Store`]
     #[cfg(feature = "sqlite")]
     pub fn with_store<P>(keys: &Keys, path: P) -> Result<Self, Error>
@@ -207,8 +299,10 @@ impl Client {
     {
         Ok(Self {
             pool: RelayPool::with_store(path)?,
-            keys: keys.clone(),
+            keys: Some(keys.clone()),
+            signer: Arc::new(keys.clone()),
             opts,
+            auth_handler: Arc::new(AtomicBool::new(false)),
             #[cfg(feature = "nip46")]
             remote_signer: None,
         })
@@ -230,10 +324,18 @@ impl Client {
     }
 
     /// Get current [`Keys`]
-    pub fn keys(&self) -> Keys {
+    ///
+    /// When the client uses a remote signer, these are the app keys, while no keys are
+    /// available with a custom signer: use [`Client::signer`] to get the user public key.
+    pub fn keys(&self) -> Option<Keys> {
         self.keys.clone()
     }
 
+    /// Get [`NostrSigner`]
+    pub fn signer(&self) -> Arc<dyn NostrSigner> {
+        self.signer.clone()
+    }
+
     /// Get NIP46 uri
     #[cfg(feature = "nip46")]
     pub fn nostr_connect_uri(
@@ -244,8 +346,9 @@ impl Client {
             .remote_signer
             .as_ref()
             .ok_or(Error::SignerNotConfigured)?;
+        let keys: &Keys = self.keys.as_ref().ok_or(Error::SignerNotConfigured)?;
         Ok(NostrConnectURI::new(
-            self.keys.public_key(),
+            keys.public_key(),
             signer.relay_url(),
             metadata.name,
         ))
@@ -257,6 +360,11 @@ impl Client {
         self.remote_signer.clone().ok_or(Error::SignerNotConfigured)
     }
 
+    /// Get [`NostrDatabase`]
+    pub fn database(&self) -> Option<Arc<dyn NostrDatabase>> {
+        self.pool.database()
+    }
+
     /// Get [`Store`]
     #[cfg(feature = "sqlite")]
     pub fn store(&self) -> Option<Store> {
@@ -435,6 +543,7 @@ impl Client {
     {
         let url = Url::parse(&url.into())?;
         if let Some(relay) = self.pool.relays().await.get(&url) {
+            self.spawn_auth_handler();
             self.pool
                 .connect_relay(relay, self.opts.get_wait_for_connection())
                 .await;
@@ -485,9 +594,60 @@ impl Client {
     /// # }
     /// ```
     pub async fn connect(&self) {
+        self.spawn_auth_handler();
         self.pool.connect(self.opts.get_wait_for_connection()).await;
     }
 
+    /// Authenticate to relay with the NIP-42 `challenge`
+    ///
+    /// Called automatically when a relay sends an `AUTH` challenge, if `automatic_authentication` is enabled in [`Options`].
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/42.md>
+    pub async fn auth<S>(&self, url: Url, challenge: S) -> Result<(), Error>
+    where
+        S: Into<String>,
+    {
+        let relay: Relay = self.pool.relay(&url).await?;
+        let builder = EventBuilder::auth(challenge, url);
+        let event: Event = self.sign_event_builder(builder).await?;
+        relay
+            .auth(event, self.opts.get_publish_timeout())
+            .await
+            .map_err(RelayPoolError::Relay)?;
+        Ok(())
+    }
+
+    /// Spawn the task that answers to the relays `AUTH` challenges (only once)
+    fn spawn_auth_handler(&self) {
+        if self.auth_handler.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let client = self.clone();
+        let mut notifications = self.notifications();
+        thread::spawn(async move {
+            loop {
+                match notifications.recv().await {
+                    Ok(RelayPoolNotification::Message(url, RelayMessage::Auth { challenge })) => {
+                        if !client.opts.get_automatic_authentication() {
+                            continue;
+                        }
+                        let client = client.clone();
+                        thread::spawn(async move {
+                            match client.auth(url.clone(), challenge).await {
+                                Ok(()) => log::debug!("Authenticated to {url}"),
+                                Err(e) => log::error!("Impossible to authenticate to {url}: {e}"),
+                            }
+                        });
+                    }
+                    Ok(RelayPoolNotification::Shutdown) | Err(RecvError::Closed) => break,
+                    Ok(_) | Err(RecvError::Lagged(_)) => (),
+                }
+            }
+            log::debug!("Auth handler terminated");
+        });
+    }
+
     /// Disconnect from all relays
     ///
     /// # Example
@@ -507,6 +667,9 @@ impl Client {
 
     /// Subscribe to filters
     ///
+    /// Every call replaces the filters of the default subscription:
+    /// use [`Client::subscribe_with_id`] to keep other subscriptions open.
+    ///
     /// # Example
     /// ```rust,no_run
     /// use nostr_sdk::prelude::*;
@@ -522,15 +685,62 @@ impl Client {
     /// client.subscribe(vec![subscription]).await;
     /// # }
     /// ```
-    pub async fn subscribe(&self, filters: Vec<Filter>) {
+    pub async fn subscribe(&self, filters: Vec<Filter>) -> SubscriptionId {
         self.pool
             .subscribe(filters, self.opts.get_wait_for_send())
+            .await
+    }
+
+    /// Subscribe to filters with custom [`SubscriptionId`]
+    ///
+    /// If a subscription with the same [`SubscriptionId`] already exists, its filters are replaced.
+    pub async fn subscribe_with_id(&self, id: SubscriptionId, filters: Vec<Filter>) {
+        self.pool
+            .subscribe_with_id(id, filters, self.opts.get_wait_for_send())
             .await;
     }
 
     /// Unsubscribe
-    pub async fn unsubscribe(&self) {
-        self.pool.unsubscribe(self.opts.get_wait_for_send()).await;
+    pub async fn unsubscribe(&self, id: SubscriptionId) {
+        self.pool
+            .unsubscribe(id, self.opts.get_wait_for_send())
+            .await;
+    }
+
+    /// Unsubscribe from all subscriptions
+    pub async fn unsubscribe_all(&self) {
+        self.pool
+            .unsubscribe_all(self.opts.get_wait_for_send())
+            .await;
+    }
+
+    /// Subscribe to filters and stream the received events
+    ///
+    /// Events are deduplicated across relays, and the `EOSE` and `CLOSED` messages of each relay are yielded too.
+    /// The subscription is closed when the stream is dropped.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use nostr_sdk::prelude::*;
+    /// use nostr_sdk::futures_util::StreamExt;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let my_keys = Keys::generate();
+    /// #   let client = Client::new(&my_keys);
+    /// let filter = Filter::new().kind(Kind::TextNote).limit(10);
+    /// let mut stream = client.stream_events_of(vec![filter]).await;
+    /// while let Some(item) = stream.next().await {
+    ///     if let EventStreamItem::Event(event) = item {
+    ///         println!("{:?}", event);
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub async fn stream_events_of(&self, filters: Vec<Filter>) -> EventStream {
+        self.pool
+            .stream_events_of(filters, self.opts.get_wait_for_send())
+            .await
     }
 
     /// Get events of filters
@@ -568,6 +778,103 @@ impl Client {
         Ok(self.pool.get_events_of(filters, timeout).await?)
     }
 
+    /// Get the reply tree of a text note (NIP10)
+    ///
+    /// Fetch the root of the thread and walk the replies (`#e` filters) in rounds, until no new events
+    /// are found. Events that only mention the thread are skipped.
+    /// If the root is not found, the tree starts from the requested event.
+    /// Return `None` if the event is not found.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/10.md>
+    pub async fn get_thread(
+        &self,
+        event_id: EventId,
+        timeout: Option<Duration>,
+    ) -> Result<Option<ThreadNode>, Error> {
+        let filter = Filter::new().id(event_id.to_hex());
+        let event: Event = match self.get_events_of(vec![filter], timeout).await?.pop() {
+            Some(event) => event,
+            None => return Ok(None),
+        };
+
+        // Fetch the root, if the event is a reply
+        let root: Event = match event.thread_refs().root {
+            Some(root_id) if root_id != event.id => {
+                let filter = Filter::new().id(root_id.to_hex());
+                self.get_events_of(vec![filter], timeout)
+                    .await?
+                    .pop()
+                    .unwrap_or(event)
+            }
+            _ => event,
+        };
+
+        let chunk_size: usize = self.opts.get_req_filters_chunk_size();
+        let mut events: HashMap<EventId, Event> = HashMap::new();
+        let mut frontier: Vec<EventId> = vec![root.id];
+        while !frontier.is_empty() {
+            let mut next: Vec<EventId> = Vec::new();
+            for chunk in frontier.chunks(chunk_size) {
+                let filter = Filter::new().kind(Kind::TextNote).events(chunk.to_vec());
+                for event in self.get_events_of(vec![filter], timeout).await?.into_iter() {
+                    if event.id == root.id || events.contains_key(&event.id) {
+                        continue;
+                    }
+
+                    // Skip the events that only mention the thread
+                    let refs = event.thread_refs();
+                    let in_thread: bool = refs.root == Some(root.id)
+                        || refs
+                            .parent()
+                            .map_or(false, |p| p == root.id || events.contains_key(&p));
+                    if in_thread {
+                        next.push(event.id);
+                        events.insert(event.id, event);
+                    }
+                }
+            }
+            frontier = next;
+        }
+
+        Ok(Some(ThreadNode::from_events(root, events.into_values())))
+    }
+
+    /// Count events of filters (NIP-45)
+    ///
+    /// Query the read relays that support NIP-45, falling back to the local database.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/45.md>
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use std::time::Duration;
+    ///
+    /// use nostr_sdk::prelude::*;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let my_keys = Keys::generate();
+    /// #   let client = Client::new(&my_keys);
+    /// let filter = Filter::new().pubkey(my_keys.public_key()).kind(Kind::Reaction);
+    /// let output = client
+    ///     .count_events_of(vec![filter], Some(Duration::from_secs(10)))
+    ///     .await
+    ///     .unwrap();
+    /// println!("Reactions: {}", output.count);
+    /// # }
+    /// ```
+    pub async fn count_events_of(
+        &self,
+        filters: Vec<Filter>,
+        timeout: Option<Duration>,
+    ) -> Result<CountOutput, Error> {
+        let timeout: Option<Duration> = match timeout {
+            Some(t) => Some(t),
+            None => self.opts.get_timeout(),
+        };
+        Ok(self.pool.count_events_of(filters, timeout).await?)
+    }
+
     /// Request events of filters
     /// All events will be received on notification listener (`client.notifications()`)
     pub async fn req_events_of(&self, filters: Vec<Filter>, timeout: Option<Duration>) {
@@ -616,54 +923,59 @@ impl Client {
         Ok(event_id)
     }
 
-    async fn send_event_builder(&self, builder: EventBuilder) -> Result<EventId, Error> {
-        #[cfg(feature = "nip46")]
-        let event: Event = if let Some(signer) = self.remote_signer.as_ref() {
-            let signer_public_key = signer
-                .signer_public_key()
-                .await
-                .ok_or(Error::SignerPublicKeyNotFound)?;
-            let unsigned_event = {
-                let difficulty: u8 = self.opts.get_difficulty();
-                if difficulty > 0 {
-                    builder.to_unsigned_pow_event(signer_public_key, difficulty)
-                } else {
-                    builder.to_unsigned_event(signer_public_key)
-                }
-            };
-            let res: Response = self
-                .send_req_to_signer(
-                    Request::SignEvent(unsigned_event.clone()),
-                    self.opts.get_nip46_timeout(),
-                )
-                .await?;
-            if let Response::SignEvent(sig) = res {
-                unsigned_event.add_signature(sig)?
-            } else {
-                return Err(Error::ResponseNotMatchRequest);
-            }
-        } else {
-            let difficulty: u8 = self.opts.get_difficulty();
-            if difficulty > 0 {
-                builder.to_pow_event(&self.keys, difficulty)?
-            } else {
-                builder.to_event(&self.keys)?
-            }
-        };
+    /// Send event and wait for the relays `OK` responses
+    ///
+    /// If `min_accepted` is set, return as soon as that number of relays have accepted the event.
+    /// The timeout for each relay can be set with [`Options::publish_timeout`].
+    pub async fn publish_event(
+        &self,
+        event: Event,
+        min_accepted: Option<usize>,
+    ) -> Result<PublishOutput, Error> {
+        Ok(self
+            .pool
+            .publish_event(
+                event,
+                self.opts.get_publish_timeout(),
+                min_accepted,
+                self.opts.get_wait_for_send(),
+            )
+            .await?)
+    }
 
-        #[cfg(not(feature = "nip46"))]
-        let event: Event = {
-            let difficulty: u8 = self.opts.get_difficulty();
-            if difficulty > 0 {
-                builder.to_pow_event(&self.keys, difficulty)?
-            } else {
-                builder.to_event(&self.keys)?
-            }
-        };
+    /// Send event to specific relay and wait for its `OK` response
+    pub async fn publish_event_to<S>(&self, url: S, event: Event) -> Result<PublishStatus, Error>
+    where
+        S: Into<String>,
+    {
+        let url = Url::parse(&url.into())?;
+        let relay = self.pool.relay(&url).await?;
+        Ok(relay
+            .publish_event(
+                event,
+                self.opts.get_publish_timeout(),
+                self.opts.get_wait_for_send(),
+            )
+            .await)
+    }
 
+    async fn send_event_builder(&self, builder: EventBuilder) -> Result<EventId, Error> {
+        let event: Event = self.sign_event_builder(builder).await?;
         self.send_event(event).await
     }
 
+    /// Sign [`EventBuilder`] with the [`NostrSigner`]
+    async fn sign_event_builder(&self, builder: EventBuilder) -> Result<Event, Error> {
+        let public_key: XOnlyPublicKey = self.signer.public_key().await?;
+        let difficulty: u8 = self.opts.get_difficulty();
+        let unsigned = if difficulty > 0 {
+            builder.to_unsigned_pow_event(public_key, difficulty)
+        } else {
+            builder.to_unsigned_event(public_key)
+        };
+        Ok(self.signer.sign_event(unsigned).await?)
+    }
+
     /// Update metadata
     ///
     /// <https://github.com/nostr-protocol/nips/blob/master/01.md>
@@ -753,30 +1065,11 @@ impl Client {
     }
 
     async fn get_contact_list_filters(&self) -> Result<Vec<Filter>, Error> {
-        #[cfg(feature = "nip46")]
-        let filter = {
-            let mut filter = Filter::new().kind(Kind::ContactList).limit(1);
-
-            if let Some(signer) = self.remote_signer.as_ref() {
-                let signer_public_key = signer
-                    .signer_public_key()
-                    .await
-                    .ok_or(Error::SignerPublicKeyNotFound)?;
-
-                filter = filter.author(signer_public_key.to_string());
-            } else {
-                filter = filter.author(self.keys.public_key().to_string());
-            }
-
-            filter
-        };
-
-        #[cfg(not(feature = "nip46"))]
+        let public_key: XOnlyPublicKey = self.signer.public_key().await?;
         let filter = Filter::new()
-            .author(self.keys.public_key().to_string())
+            .author(public_key.to_string())
             .kind(Kind::ContactList)
             .limit(1);
-
         Ok(vec![filter])
     }
 
@@ -912,32 +1205,216 @@ impl Client {
     where
         S: Into<String>,
     {
-        #[cfg(feature = "nip46")]
-        let builder: EventBuilder = if self.remote_signer.is_some() {
-            let req = Request::Nip04Encrypt {
-                public_key: receiver,
-                text: msg.into(),
+        let content: String = self.signer.nip04_encrypt(receiver, msg.into()).await?;
+        let builder = EventBuilder::new(
+            Kind::EncryptedDirectMessage,
+            content,
+            &[Tag::PubKey(receiver, None)],
+        );
+        self.send_event_builder(builder).await
+    }
+
+    /// Get the inbox relays of a public key, where to send its private direct messages (NIP17)
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/17.md>
+    #[cfg(feature = "nip17")]
+    pub async fn get_inbox_relays(
+        &self,
+        public_key: XOnlyPublicKey,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<Url>, Error> {
+        let filter = Filter::new()
+            .author(public_key.to_string())
+            .kind(Kind::InboxRelays)
+            .limit(1);
+        let events: Vec<Event> = self.get_events_of(vec![filter], timeout).await?;
+        Ok(events
+            .into_iter()
+            .max_by_key(|e| e.created_at)
+            .map(|e| nip17::extract_relay_list(&e))
+            .unwrap_or_default())
+    }
+
+    /// Publish the relays where to receive private direct messages (NIP17)
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/17.md>
+    #[cfg(feature = "nip17")]
+    pub async fn set_inbox_relays(&self, relays: Vec<Url>) -> Result<EventId, Error> {
+        let builder = EventBuilder::inbox_relays(relays);
+        self.send_event_builder(builder).await
+    }
+
+    /// Send private direct message (NIP17)
+    ///
+    /// The message is sealed and gift wrapped to each receiver and to ourselves, and sent to the
+    /// inbox relays of each of them (falling back to the client relays if they have not published any).
+    /// The inbox relays not already in the pool are added as write-only relays and removed
+    /// once the gift wrap has been published.
+    ///
+    /// Return the ids of the gift wraps, or [`Error::EventNotPublished`] if no relay accepted one of them.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/17.md>
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use nostr_sdk::prelude::*;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let my_keys = Keys::generate();
+    /// #   let client = Client::new(&my_keys);
+    /// let alice_pubkey = XOnlyPublicKey::from_bech32(
+    ///     "npub14f8usejl26twx0dhuxjh9cas7keav9vr0v8nvtwtrjqx3vycc76qqh9nsy",
+    /// )
+    /// .unwrap();
+    ///
+    /// client
+    ///     .send_private_msg(vec![alice_pubkey], "My first private DM from Nostr SDK!", None)
+    ///     .await
+    ///     .unwrap();
+    /// # }
+    /// ```
+    #[cfg(feature = "nip17")]
+    pub async fn send_private_msg<S>(
+        &self,
+        receivers: Vec<XOnlyPublicKey>,
+        message: S,
+        reply_to: Option<EventId>,
+    ) -> Result<Vec<EventId>, Error>
+    where
+        S: Into<String>,
+    {
+        let public_key: XOnlyPublicKey = self.signer.public_key().await?;
+        let rumor = EventBuilder::private_msg_rumor(&receivers, message, reply_to)
+            .to_unsigned_event(public_key);
+
+        let mut public_keys: Vec<XOnlyPublicKey> = receivers;
+        if !public_keys.contains(&public_key) {
+            public_keys.push(public_key);
+        }
+
+        let mut ids: Vec<EventId> = Vec::with_capacity(public_keys.len());
+        for receiver in public_keys.into_iter() {
+            let gift_wrap: Event = EventBuilder::gift_wrap_with_signer(
+                self.signer.as_ref(),
+                &receiver,
+                rumor.clone(),
+                None,
+            )
+            .await?;
+            let relays: Vec<Url> = self.get_inbox_relays(receiver, None).await?;
+            ids.push(self.send_event_to_inbox(relays, gift_wrap).await?);
+        }
+        Ok(ids)
+    }
+
+    /// Publish event to the inbox relays, or to all relays if empty
+    ///
+    /// The inbox relays not already in the pool are added only for the time of the publishing.
+    /// Return [`Error::EventNotPublished`] if no relay accepted the event.
+    #[cfg(feature = "nip17")]
+    async fn send_event_to_inbox(&self, relays: Vec<Url>, event: Event) -> Result<EventId, Error> {
+        let output: PublishOutput = if relays.is_empty() {
+            self.publish_event(event, None).await?
+        } else {
+            let timeout: Duration = self.opts.get_publish_timeout();
+            let wait: bool = self.opts.get_wait_for_send();
+            let mut output = PublishOutput {
+                event_id: event.id,
+                relays: HashMap::new(),
             };
-            let res: Response = self
-                .send_req_to_signer(req, self.opts.get_nip46_timeout())
-                .await?;
-            if let Response::Nip04Encrypt(content) = res {
-                EventBuilder::new(
-                    Kind::EncryptedDirectMessage,
-                    content,
-                    &[Tag::PubKey(receiver, None)],
-                )
-            } else {
-                return Err(Error::ResponseNotMatchRequest);
+            let mut added: Vec<Url> = Vec::new();
+            let (tx, mut rx) = mpsc::channel(relays.len());
+            for url in relays.into_iter() {
+                let relay: Relay = match self.pool.relay(&url).await {
+                    Ok(relay) => relay,
+                    Err(_) => match self.add_inbox_relay(&url).await {
+                        Ok(relay) => {
+                            added.push(url.clone());
+                            relay
+                        }
+                        Err(e) => {
+                            output
+                                .relays
+                                .insert(url, PublishStatus::NotSent(e.to_string()));
+                            continue;
+                        }
+                    },
+                };
+
+                let event = event.clone();
+                let tx = tx.clone();
+                thread::spawn(async move {
+                    let status = relay.publish_event(event, timeout, wait).await;
+                    let _ = tx.send((url, status)).await;
+                });
             }
-        } else {
-            EventBuilder::new_encrypted_direct_msg(&self.keys, receiver, msg)?
+            drop(tx);
+
+            while let Some((url, status)) = rx.recv().await {
+                if let PublishStatus::Rejected(reason) = &status {
+                    log::warn!("Gift wrap rejected by {url}: {reason}");
+                }
+                output.relays.insert(url, status);
+            }
+
+            for url in added.into_iter() {
+                self.pool.remove_relay(url).await?;
+            }
+
+            output
         };
 
-        #[cfg(not(feature = "nip46"))]
-        let builder = EventBuilder::new_encrypted_direct_msg(&self.keys, receiver, msg)?;
+        if output.accepted().is_empty() {
+            return Err(Error::EventNotPublished(output));
+        }
 
-        self.send_event_builder(builder).await
+        Ok(output.event_id)
+    }
+
+    /// Add an inbox relay to the pool as write-only and connect to it
+    #[cfg(feature = "nip17")]
+    async fn add_inbox_relay(&self, url: &Url) -> Result<Relay, Error> {
+        let opts = RelayOptions::new(false, true);
+        #[cfg(not(target_arch = "wasm32"))]
+        self.pool.add_relay(url.clone(), None, opts).await?;
+        #[cfg(target_arch = "wasm32")]
+        self.pool.add_relay(url.clone(), opts).await?;
+        let relay: Relay = self.pool.relay(url).await?;
+        self.pool.connect_relay(&relay, true).await;
+        Ok(relay)
+    }
+
+    /// Unwrap a gift wrap received from relays (NIP59)
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/59.md>
+    #[cfg(feature = "nip59")]
+    pub async fn unwrap_gift_wrap(&self, gift_wrap: &Event) -> Result<UnwrappedGift, Error> {
+        Ok(UnwrappedGift::from_gift_wrap_with_signer(self.signer.as_ref(), gift_wrap).await?)
+    }
+
+    /// Get private direct messages (NIP17), grouped by conversation
+    ///
+    /// Gift wraps that can't be unwrapped or that don't carry a private direct message are skipped.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/17.md>
+    #[cfg(feature = "nip17")]
+    pub async fn get_private_msgs(
+        &self,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<Conversation>, Error> {
+        let public_key: XOnlyPublicKey = self.signer.public_key().await?;
+        let filter = Filter::new().pubkey(public_key).kind(Kind::GiftWrap);
+        let events: Vec<Event> = self.get_events_of(vec![filter], timeout).await?;
+        let mut messages: Vec<PrivateDirectMessage> = Vec::with_capacity(events.len());
+        for event in events.iter() {
+            if let Ok(gift) = self.unwrap_gift_wrap(event).await {
+                if let Ok(msg) = PrivateDirectMessage::try_from(gift) {
+                    messages.push(msg);
+                }
+            }
+        }
+        Ok(nip17::group_conversations(messages))
     }
 
     /// Repost event
@@ -1196,3 +1673,138 @@ impl Client {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+
+    use async_trait::async_trait;
+    use nostr::UnsignedEvent;
+
+    use super::*;
+
+    /// Signer that keeps its [`Keys`] private and counts the signatures
+    #[derive(Debug)]
+    struct CountingSigner {
+        keys: Keys,
+        signatures: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl NostrSigner for CountingSigner {
+        async fn public_key(&self) -> Result<XOnlyPublicKey, SignerError> {
+            Ok(self.keys.public_key())
+        }
+
+        async fn sign_event(&self, unsigned: UnsignedEvent) -> Result<Event, SignerError> {
+            self.signatures.fetch_add(1, Ordering::SeqCst);
+            self.keys.sign_event(unsigned).await
+        }
+
+        #[cfg(feature = "nip04")]
+        async fn nip04_encrypt(
+            &self,
+            public_key: XOnlyPublicKey,
+            content: String,
+        ) -> Result<String, SignerError> {
+            self.keys.nip04_encrypt(public_key, content).await
+        }
+
+        #[cfg(feature = "nip04")]
+        async fn nip04_decrypt(
+            &self,
+            public_key: XOnlyPublicKey,
+            payload: String,
+        ) -> Result<String, SignerError> {
+            self.keys.nip04_decrypt(public_key, payload).await
+        }
+
+        #[cfg(feature = "nip44")]
+        async fn nip44_encrypt(
+            &self,
+            public_key: XOnlyPublicKey,
+            content: String,
+        ) -> Result<String, SignerError> {
+            self.keys.nip44_encrypt(public_key, content).await
+        }
+
+        #[cfg(feature = "nip44")]
+        async fn nip44_decrypt(
+            &self,
+            public_key: XOnlyPublicKey,
+            payload: String,
+        ) -> Result<String, SignerError> {
+            self.keys.nip44_decrypt(public_key, payload).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_client_with_custom_signer() {
+        let user_keys = Keys::generate();
+        let signatures = Arc::new(AtomicUsize::new(0));
+        let client = Client::with_signer(CountingSigner {
+            keys: user_keys.clone(),
+            signatures: signatures.clone(),
+        });
+        assert!(client.keys().is_none());
+
+        let signer = client.signer();
+        assert_eq!(signer.public_key().await.unwrap(), user_keys.public_key());
+
+        let event = client
+            .sign_event_builder(EventBuilder::new_text_note("hello", &[]))
+            .await
+            .unwrap();
+        event.verify().unwrap();
+        assert_eq!(event.pubkey, user_keys.public_key());
+        assert_eq!(signatures.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_keys_signer_checks_event() {
+        let keys = Keys::generate();
+        let unsigned =
+            EventBuilder::new_text_note("hello", &[]).to_unsigned_event(keys.public_key());
+        let event = keys.sign_event(unsigned.clone()).await.unwrap();
+        event.verify().unwrap();
+
+        let mut forged = unsigned.clone();
+        forged.content = String::from("forged");
+        assert!(matches!(
+            keys.sign_event(forged).await,
+            Err(SignerError::InvalidEventId)
+        ));
+
+        let other = Keys::generate();
+        assert!(matches!(
+            other.sign_event(unsigned).await,
+            Err(SignerError::PublicKeyMismatch)
+        ));
+    }
+
+    #[cfg(feature = "nip59")]
+    #[tokio::test]
+    async fn test_gift_wrap_with_custom_signer() {
+        let user_keys = Keys::generate();
+        let signer = CountingSigner {
+            keys: user_keys.clone(),
+            signatures: Arc::new(AtomicUsize::new(0)),
+        };
+
+        let receiver = Keys::generate();
+        let rumor = EventBuilder::private_msg_rumor(&[receiver.public_key()], "hi", None)
+            .to_unsigned_event(user_keys.public_key());
+        let gift_wrap = EventBuilder::gift_wrap_with_signer(
+            &signer,
+            &receiver.public_key(),
+            rumor.clone(),
+            None,
+        )
+        .await
+        .unwrap();
+        let unwrapped = nip59::extract_rumor(&receiver, &gift_wrap).unwrap();
+        assert_eq!(unwrapped.sender, user_keys.public_key());
+        assert_eq!(unwrapped.rumor, rumor);
+        assert_eq!(signer.signatures.load(Ordering::SeqCst), 1);
+    }
+}
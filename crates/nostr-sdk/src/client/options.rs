@@ -20,6 +20,10 @@ pub struct Options {
     req_filters_chunk_size: Arc<AtomicU8>,
     /// Timeout (default: none)
     timeout: Option<Duration>,
+    /// Timeout for the relays `OK` on publish (default: 20 secs)
+    publish_timeout: Duration,
+    /// Automatically authenticate to relays (NIP-42) (default: true)
+    automatic_authentication: Arc<AtomicBool>,
     /// NIP46 timeout (default: 180 secs)
     #[cfg(feature = "nip46")]
     nip46_timeout: Option<Duration>,
@@ -33,6 +37,8 @@ impl Default for Options {
             difficulty: Arc::new(AtomicU8::new(0)),
             req_filters_chunk_size: Arc::new(AtomicU8::new(10)),
             timeout: None,
+            publish_timeout: Duration::from_secs(20),
+            automatic_authentication: Arc::new(AtomicBool::new(true)),
             #[cfg(feature = "nip46")]
             nip46_timeout: Some(Duration::from_secs(180)),
         }
@@ -108,6 +114,30 @@ impl Options {
         self.timeout
     }
 
+    /// Set timeout for the relays `OK` on publish
+    pub fn publish_timeout(self, timeout: Duration) -> Self {
+        Self {
+            publish_timeout: timeout,
+            ..self
+        }
+    }
+
+    pub(crate) fn get_publish_timeout(&self) -> Duration {
+        self.publish_timeout
+    }
+
+    /// If set to `true`, `Client` answers to the relays `AUTH` challenges (NIP-42)
+    pub fn automatic_authentication(self, enabled: bool) -> Self {
+        Self {
+            automatic_authentication: Arc::new(AtomicBool::new(enabled)),
+            ..self
+        }
+    }
+
+    pub(crate) fn get_automatic_authentication(&self) -> bool {
+        self.automatic_authentication.load(Ordering::SeqCst)
+    }
+
     /// Set NIP46 timeout
     #[cfg(feature = "nip46")]
     pub fn nip46_timeout(self, timeout: Option<Duration>) -> Self {
@@ -0,0 +1,578 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! Nostr Connect Signer (NIP46)
+//!
+//! Signer side of NIP46: serve the requests of the apps with local [`Keys`].
+//!
+//! <https://github.com/nostr-protocol/nips/blob/master/46.md>
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
+#[cfg(not(target_arch = "wasm32"))]
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use nostr::hashes::sha256::Hash as Sha256Hash;
+use nostr::hashes::Hash;
+use nostr::nips::nip04;
+use nostr::nips::nip46::{
+    BunkerURI, Message, NostrConnectURI, RelayPermissions, Request, Response,
+};
+use nostr::secp256k1::XOnlyPublicKey;
+use nostr::{ClientMessage, Event, EventBuilder, EventId, Filter, Keys, Kind, Timestamp, Url};
+use tokio::sync::RwLock;
+
+use crate::client::Error;
+use crate::relay::pool::RelayPool;
+use crate::relay::{RelayOptions, RelayPoolNotification};
+
+/// Approval policy of a [`NostrConnectSigner`]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub trait NostrConnectSignerPolicy: fmt::Debug + Send + Sync {
+    /// Check if the `app` is allowed to execute the [`Request`]
+    async fn approve(&self, app: XOnlyPublicKey, req: &Request) -> bool;
+
+    /// Called when the `app` connects with a valid [`BunkerURI`] secret
+    ///
+    /// Do nothing by default.
+    async fn connect(&self, _app: XOnlyPublicKey) {}
+}
+
+/// Permissions granted to an app
+///
+/// `describe`, `get_public_key`, `get_relays`, `ping`, `connect` and `disconnect` are always allowed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AppPermissions {
+    methods: HashSet<String>,
+    kinds: Option<BTreeSet<Kind>>,
+}
+
+impl AppPermissions {
+    /// New empty permissions
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow a NIP46 method (ex. `sign_event`, `nip04_encrypt`)
+    pub fn allow_method<S>(self, method: S) -> Self
+    where
+        S: Into<String>,
+    {
+        let mut methods = self.methods;
+        methods.insert(method.into());
+        Self { methods, ..self }
+    }
+
+    /// Restrict `sign_event` to a list of [`Kind`]
+    ///
+    /// If not set, every kind can be signed.
+    pub fn allow_kinds<I>(self, kinds: I) -> Self
+    where
+        I: IntoIterator<Item = Kind>,
+    {
+        let mut allowed = self.kinds.unwrap_or_default();
+        allowed.extend(kinds);
+        Self {
+            kinds: Some(allowed),
+            ..self
+        }
+    }
+
+    /// Check if the [`Request`] is allowed
+    pub fn is_allowed(&self, req: &Request) -> bool {
+        match req {
+            Request::Describe
+            | Request::GetPublicKey
+            | Request::GetRelays
+            | Request::Ping
+            | Request::Connect { .. }
+            | Request::Disconnect => true,
+            Request::SignEvent(unsigned) => {
+                self.methods.contains(&req.method())
+                    && match &self.kinds {
+                        Some(kinds) => kinds.contains(&unsigned.kind),
+                        None => true,
+                    }
+            }
+            req => self.methods.contains(&req.method()),
+        }
+    }
+}
+
+/// Per-app [`AppPermissions`] policy
+///
+/// The requests of unknown apps are rejected. An app is known once granted, or after
+/// a `connect` with a valid [`BunkerURI`] secret (with empty [`AppPermissions`]).
+#[derive(Debug, Clone, Default)]
+pub struct AppPermissionsPolicy {
+    apps: Arc<RwLock<HashMap<XOnlyPublicKey, AppPermissions>>>,
+}
+
+impl AppPermissionsPolicy {
+    /// New empty policy
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Grant [`AppPermissions`] to an app (replace the previous ones)
+    pub async fn grant(&self, app: XOnlyPublicKey, permissions: AppPermissions) {
+        let mut apps = self.apps.write().await;
+        apps.insert(app, permissions);
+    }
+
+    /// Revoke all the permissions of an app
+    pub async fn revoke(&self, app: &XOnlyPublicKey) {
+        let mut apps = self.apps.write().await;
+        apps.remove(app);
+    }
+
+    /// Get [`AppPermissions`] of an app
+    pub async fn permissions(&self, app: &XOnlyPublicKey) -> Option<AppPermissions> {
+        let apps = self.apps.read().await;
+        apps.get(app).cloned()
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl NostrConnectSignerPolicy for AppPermissionsPolicy {
+    async fn approve(&self, app: XOnlyPublicKey, req: &Request) -> bool {
+        let apps = self.apps.read().await;
+        matches!(apps.get(&app), Some(permissions) if permissions.is_allowed(req))
+    }
+
+    async fn connect(&self, app: XOnlyPublicKey) {
+        let mut apps = self.apps.write().await;
+        apps.entry(app).or_default();
+    }
+}
+
+/// Nostr Connect Signer
+///
+/// Listen for the NIP46 requests, check them with the [`NostrConnectSignerPolicy`]
+/// and reply signing with local [`Keys`].
+#[derive(Debug, Clone)]
+pub struct NostrConnectSigner {
+    keys: Keys,
+    pool: RelayPool,
+    policy: Arc<dyn NostrConnectSignerPolicy>,
+    secrets: Arc<RwLock<HashSet<String>>>,
+}
+
+impl NostrConnectSigner {
+    /// New Nostr Connect Signer
+    pub fn new<P>(keys: Keys, policy: P) -> Self
+    where
+        P: NostrConnectSignerPolicy + 'static,
+    {
+        Self {
+            keys,
+            pool: RelayPool::new(),
+            policy: Arc::new(policy),
+            secrets: Arc::new(RwLock::new(HashSet::new())),
+        }
+    }
+
+    /// Get signer [`XOnlyPublicKey`]
+    pub fn public_key(&self) -> XOnlyPublicKey {
+        self.keys.public_key()
+    }
+
+    /// Add new relay
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn add_relay<S>(&self, url: S, proxy: Option<SocketAddr>) -> Result<(), Error>
+    where
+        S: Into<String>,
+    {
+        let url = Url::parse(&url.into())?;
+        self.pool
+            .add_relay(url, proxy, RelayOptions::default())
+            .await?;
+        Ok(())
+    }
+
+    /// Add new relay
+    #[cfg(target_arch = "wasm32")]
+    pub async fn add_relay<S>(&self, url: S) -> Result<(), Error>
+    where
+        S: Into<String>,
+    {
+        let url = Url::parse(&url.into())?;
+        self.pool.add_relay(url, RelayOptions::default()).await?;
+        Ok(())
+    }
+
+    /// Get [`BunkerURI`] with the relays of the signer and a new single-use secret
+    ///
+    /// The app connecting with the secret is passed to [`NostrConnectSignerPolicy::connect`].
+    pub async fn bunker_uri(&self) -> BunkerURI {
+        let relays: Vec<Url> = self.pool.relays().await.into_keys().collect();
+        let random: [u8; 32] = nostr::secp256k1::rand::random();
+        let secret: String = Sha256Hash::hash(&random).to_string()[..32].to_string();
+        let mut secrets = self.secrets.write().await;
+        secrets.insert(secret.clone());
+        BunkerURI::new(self.keys.public_key(), relays).secret(secret)
+    }
+
+    /// Accept a `nostrconnect://` [`NostrConnectURI`]
+    ///
+    /// Send the `connect` request, with the signer public key, to the app.
+    /// The app should also be approved by the [`NostrConnectSignerPolicy`].
+    pub async fn connect_app(&self, uri: &NostrConnectURI) -> Result<(), Error> {
+        #[cfg(not(target_arch = "wasm32"))]
+        self.add_relay(uri.relay_url.to_string(), None).await?;
+        #[cfg(target_arch = "wasm32")]
+        self.add_relay(uri.relay_url.to_string()).await?;
+        self.pool.connect(true).await;
+
+        let msg = Message::request(Request::Connect {
+            public_key: self.keys.public_key(),
+            secret: None,
+            permissions: None,
+        });
+        let event =
+            EventBuilder::nostr_connect(&self.keys, uri.public_key, msg)?.to_event(&self.keys)?;
+        self.pool
+            .send_msg_to(uri.relay_url.clone(), ClientMessage::new_event(event), true)
+            .await?;
+        Ok(())
+    }
+
+    /// Handle a NIP46 request [`Event`]
+    ///
+    /// Return the response [`Event`] to send to the app, if any.
+    /// Requests not approved by the [`NostrConnectSignerPolicy`] get an error response,
+    /// as well as `connect` requests with an unknown or already used secret.
+    pub async fn handle_event(&self, event: &Event) -> Result<Option<Event>, Error> {
+        if event.kind != Kind::NostrConnect {
+            return Ok(None);
+        }
+
+        let app: XOnlyPublicKey = event.pubkey;
+        let msg: String = nip04::decrypt(&self.keys.secret_key()?, &app, &event.content)?;
+        let msg = Message::from_json(msg)?;
+        if !msg.is_request() {
+            return Ok(None);
+        }
+
+        let res: Message = match msg.to_request() {
+            Ok(Request::Connect {
+                secret: Some(secret),
+                ..
+            }) if !secret.is_empty() => {
+                let mut secrets = self.secrets.write().await;
+                if secrets.remove(&secret) {
+                    drop(secrets);
+                    self.policy.connect(app).await;
+                    Message::response(msg.id(), Response::Connect(String::from("ack")))
+                } else {
+                    log::warn!("Invalid 'connect' secret of {app}");
+                    error_response(msg.id(), "invalid secret")
+                }
+            }
+            Ok(req) => {
+                if let Err(e) = self.check_request(&req) {
+                    log::warn!("Invalid '{}' request of {app}: {e}", req.method());
+                    error_response(msg.id(), e)
+                } else if self.policy.approve(app, &req).await {
+                    let res = match req {
+                        Request::GetRelays => Ok(Some(Response::GetRelays(self.relays().await))),
+                        req => req.generate_response(&self.keys),
+                    };
+                    match res {
+                        Ok(Some(res)) => Message::response(msg.id(), res),
+                        Ok(None) => return Ok(None),
+                        Err(e) => error_response(msg.id(), e),
+                    }
+                } else {
+                    log::warn!("Request '{}' of {app} rejected", req.method());
+                    error_response(msg.id(), "permission denied")
+                }
+            }
+            Err(e) => error_response(msg.id(), e),
+        };
+
+        Ok(Some(
+            EventBuilder::nostr_connect(&self.keys, app, res)?.to_event(&self.keys)?,
+        ))
+    }
+
+    /// Check that an event to sign is authored by the signer and has a valid id
+    fn check_request(&self, req: &Request) -> Result<(), &'static str> {
+        if let Request::SignEvent(unsigned) = req {
+            if unsigned.pubkey != self.keys.public_key() {
+                return Err("public key mismatch");
+            }
+            let id = EventId::new(
+                &unsigned.pubkey,
+                unsigned.created_at,
+                &unsigned.kind,
+                &unsigned.tags,
+                &unsigned.content,
+            );
+            if id != unsigned.id {
+                return Err("invalid event id");
+            }
+        }
+        Ok(())
+    }
+
+    async fn relays(&self) -> HashMap<Url, RelayPermissions> {
+        self.pool
+            .relays()
+            .await
+            .into_iter()
+            .map(|(url, relay)| {
+                let opts = relay.opts();
+                let permissions = RelayPermissions {
+                    read: opts.read(),
+                    write: opts.write(),
+                };
+                (url, permissions)
+            })
+            .collect()
+    }
+
+    /// Connect to the relays and serve the requests until the shutdown
+    pub async fn serve(&self) -> Result<(), Error> {
+        self.pool.connect(true).await;
+
+        let mut notifications = self.pool.notifications();
+        let filter = Filter::new()
+            .pubkey(self.keys.public_key())
+            .kind(Kind::NostrConnect)
+            .since(Timestamp::now());
+        self.pool.subscribe(vec![filter], true).await;
+
+        while let Ok(notification) = notifications.recv().await {
+            match notification {
+                RelayPoolNotification::Event(_url, event) => {
+                    match self.handle_event(&event).await {
+                        Ok(Some(res)) => {
+                            if let Err(e) = self
+                                .pool
+                                .send_msg(ClientMessage::new_event(res), false)
+                                .await
+                            {
+                                log::error!("Impossible to send NIP46 response: {e}");
+                            }
+                        }
+                        Ok(None) => (),
+                        Err(e) => log::error!("Impossible to handle NIP46 request: {e}"),
+                    }
+                }
+                RelayPoolNotification::Shutdown => break,
+                _ => (),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Disconnect from the relays and stop serving
+    pub async fn shutdown(self) -> Result<(), Error> {
+        self.pool.shutdown().await?;
+        Ok(())
+    }
+}
+
+fn error_response<E>(req_id: String, error: E) -> Message
+where
+    E: ToString,
+{
+    Message::Response {
+        id: req_id,
+        result: None,
+        error: Some(error.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr::serde_json;
+
+    use super::*;
+
+    async fn send_req(
+        signer: &NostrConnectSigner,
+        app_keys: &Keys,
+        req: Request,
+    ) -> Option<Message> {
+        let msg = Message::request(req);
+        let event = EventBuilder::nostr_connect(app_keys, signer.public_key(), msg)
+            .unwrap()
+            .to_event(app_keys)
+            .unwrap();
+        let res = signer.handle_event(&event).await.unwrap()?;
+        let res =
+            nip04::decrypt(&app_keys.secret_key().unwrap(), &res.pubkey, &res.content).unwrap();
+        Some(Message::from_json(res).unwrap())
+    }
+
+    #[test]
+    fn test_app_permissions() {
+        let keys = Keys::generate();
+        let note = EventBuilder::new_text_note("hello", &[]).to_unsigned_event(keys.public_key());
+        let metadata =
+            EventBuilder::new(Kind::Metadata, "{}", &[]).to_unsigned_event(keys.public_key());
+
+        let permissions = AppPermissions::new();
+        assert!(permissions.is_allowed(&Request::GetPublicKey));
+        assert!(!permissions.is_allowed(&Request::SignEvent(note.clone())));
+
+        let permissions = permissions.allow_method("sign_event");
+        assert!(permissions.is_allowed(&Request::SignEvent(note.clone())));
+        assert!(permissions.is_allowed(&Request::SignEvent(metadata.clone())));
+
+        let permissions = permissions.allow_kinds([Kind::TextNote]);
+        assert!(permissions.is_allowed(&Request::SignEvent(note)));
+        assert!(!permissions.is_allowed(&Request::SignEvent(metadata)));
+        assert!(!permissions.is_allowed(&Request::Nip04Encrypt {
+            public_key: keys.public_key(),
+            text: String::from("hello"),
+        }));
+    }
+
+    #[tokio::test]
+    async fn test_handle_requests() {
+        let signer_keys = Keys::generate();
+        let app_keys = Keys::generate();
+        let policy = AppPermissionsPolicy::new();
+        let signer = NostrConnectSigner::new(signer_keys.clone(), policy.clone());
+
+        let unsigned =
+            EventBuilder::new_text_note("hello", &[]).to_unsigned_event(signer_keys.public_key());
+
+        // Unknown app
+        let res = send_req(&signer, &app_keys, Request::SignEvent(unsigned.clone()))
+            .await
+            .unwrap();
+        assert!(matches!(
+            res,
+            Message::Response {
+                result: None,
+                error: Some(_),
+                ..
+            }
+        ));
+
+        policy
+            .grant(
+                app_keys.public_key(),
+                AppPermissions::new()
+                    .allow_method("sign_event")
+                    .allow_kinds([Kind::TextNote]),
+            )
+            .await;
+
+        let res = send_req(&signer, &app_keys, Request::SignEvent(unsigned.clone()))
+            .await
+            .unwrap();
+        match res {
+            Message::Response {
+                result: Some(result),
+                error: None,
+                ..
+            } => {
+                let sig = serde_json::from_value(result).unwrap();
+                let event = unsigned.clone().add_signature(sig).unwrap();
+                event.verify().unwrap();
+            }
+            _ => panic!("unexpected response: {res:?}"),
+        }
+
+        // Forged id: the kind allowed by the policy doesn't match the signed content
+        let metadata = EventBuilder::new(Kind::Metadata, "{}", &[])
+            .to_unsigned_event(signer_keys.public_key());
+        let mut forged = unsigned.clone();
+        forged.id = metadata.id;
+        let res = send_req(&signer, &app_keys, Request::SignEvent(forged))
+            .await
+            .unwrap();
+        assert!(
+            matches!(res, Message::Response { result: None, error: Some(e), .. } if e == "invalid event id")
+        );
+
+        // Event of another author
+        let other =
+            EventBuilder::new_text_note("hello", &[]).to_unsigned_event(app_keys.public_key());
+        let res = send_req(&signer, &app_keys, Request::SignEvent(other))
+            .await
+            .unwrap();
+        assert!(matches!(
+            res,
+            Message::Response {
+                result: None,
+                error: Some(_),
+                ..
+            }
+        ));
+
+        let req = Request::Connect {
+            public_key: signer.public_key(),
+            secret: None,
+            permissions: None,
+        };
+        let res = send_req(&signer, &app_keys, req.clone()).await.unwrap();
+        assert!(matches!(res.to_response(&req).unwrap(), Response::Connect(ack) if ack == "ack"));
+
+        // Disconnect doesn't have a response
+        assert!(send_req(&signer, &app_keys, Request::Disconnect)
+            .await
+            .is_none());
+
+        policy.revoke(&app_keys.public_key()).await;
+        let res = send_req(&signer, &app_keys, Request::GetPublicKey).await;
+        assert!(matches!(
+            res,
+            Some(Message::Response { error: Some(_), .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_connect_with_secret() {
+        let signer = NostrConnectSigner::new(Keys::generate(), AppPermissionsPolicy::new());
+        let app_keys = Keys::generate();
+        let connect = |secret: &str| Request::Connect {
+            public_key: signer.public_key(),
+            secret: Some(secret.to_string()),
+            permissions: None,
+        };
+
+        // Wrong secret
+        let uri = signer.bunker_uri().await;
+        let secret = uri.secret.unwrap();
+        let res = send_req(&signer, &app_keys, connect("wrong"))
+            .await
+            .unwrap();
+        assert!(
+            matches!(res, Message::Response { result: None, error: Some(e), .. } if e == "invalid secret")
+        );
+        let res = send_req(&signer, &app_keys, Request::GetPublicKey)
+            .await
+            .unwrap();
+        assert!(matches!(res, Message::Response { error: Some(_), .. }));
+
+        // Valid secret: the app is granted
+        let req = connect(&secret);
+        let res = send_req(&signer, &app_keys, req.clone()).await.unwrap();
+        assert!(matches!(res.to_response(&req).unwrap(), Response::Connect(ack) if ack == "ack"));
+        let req = Request::GetPublicKey;
+        let res = send_req(&signer, &app_keys, req.clone()).await.unwrap();
+        assert!(
+            matches!(res.to_response(&req).unwrap(), Response::GetPublicKey(pk) if pk == signer.public_key())
+        );
+
+        // Secret already used
+        let other_keys = Keys::generate();
+        let res = send_req(&signer, &other_keys, connect(&secret))
+            .await
+            .unwrap();
+        assert!(
+            matches!(res, Message::Response { result: None, error: Some(e), .. } if e == "invalid secret")
+        );
+    }
+}
@@ -3,4 +3,5 @@
 
 //! Client Signers
 
+pub mod connect;
 pub mod remote;
@@ -8,16 +8,20 @@
 use std::sync::Arc;
 use std::time::Duration;
 
+use async_trait::async_trait;
 use nostr::nips::nip04;
-use nostr::nips::nip46::{Message, Request, Response};
+use nostr::nips::nip46::{BunkerURI, Message, Request, Response};
 use nostr::secp256k1::XOnlyPublicKey;
-use nostr::serde_json;
-use nostr::{ClientMessage, EventBuilder, Filter, Kind, SubscriptionId, Timestamp, Url};
-use tokio::sync::Mutex;
+use nostr::{
+    ClientMessage, Event, EventBuilder, Filter, Keys, Kind, NostrSigner, SignerError,
+    SubscriptionId, Timestamp, UnsignedEvent, Url,
+};
+use tokio::sync::{broadcast, Mutex};
 
 #[cfg(feature = "blocking")]
 use crate::client::blocking::Client as BlockingClient;
-use crate::client::{Client, Error};
+use crate::client::{Client, Error, Options};
+use crate::relay::pool::RelayPool;
 use crate::relay::RelayPoolNotification;
 use crate::time;
 #[cfg(feature = "blocking")]
@@ -28,17 +32,32 @@ use crate::RUNTIME;
 pub struct RemoteSigner {
     relay_url: Url,
     signer_public_key: Arc<Mutex<Option<XOnlyPublicKey>>>,
+    secret: Option<String>,
+    auth_url_sender: broadcast::Sender<Url>,
 }
 
 impl RemoteSigner {
     /// New NIP46 remote signer
     pub fn new(relay_url: Url, signer_public_key: Option<XOnlyPublicKey>) -> Self {
+        let (auth_url_sender, _) = broadcast::channel(16);
         Self {
             relay_url,
             signer_public_key: Arc::new(Mutex::new(signer_public_key)),
+            secret: None,
+            auth_url_sender,
         }
     }
 
+    /// New NIP46 remote signer from [`BunkerURI`]
+    ///
+    /// Only the first relay is used. Return `None` if the URI has no relays.
+    pub fn from_bunker_uri(uri: BunkerURI) -> Option<Self> {
+        let relay_url: Url = uri.relays.into_iter().next()?;
+        let mut signer = Self::new(relay_url, Some(uri.signer_public_key));
+        signer.secret = uri.secret;
+        Some(signer)
+    }
+
     /// Get signer relay [`Url`]
     pub fn relay_url(&self) -> Url {
         self.relay_url.clone()
@@ -54,6 +73,18 @@ impl RemoteSigner {
         let mut pubkey = self.signer_public_key.lock().await;
         *pubkey = Some(public_key);
     }
+
+    /// Get secret (from [`BunkerURI`])
+    pub fn secret(&self) -> Option<String> {
+        self.secret.clone()
+    }
+
+    /// Subscribe to the `auth_url` challenges sent by the signer
+    ///
+    /// The user must open the [`Url`] to authorize the pending request.
+    pub fn auth_urls(&self) -> broadcast::Receiver<Url> {
+        self.auth_url_sender.subscribe()
+    }
 }
 
 impl Client {
@@ -107,11 +138,12 @@ impl Client {
             .remote_signer
             .as_ref()
             .ok_or(Error::SignerNotConfigured)?;
+        let keys: &Keys = self.keys.as_ref().ok_or(Error::SignerNotConfigured)?;
 
         if signer.signer_public_key().await.is_none() {
             let id = SubscriptionId::generate();
             let filter = Filter::new()
-                .pubkey(self.keys.public_key())
+                .pubkey(keys.public_key())
                 .kind(Kind::NostrConnect)
                 .since(Timestamp::now());
 
@@ -127,13 +159,10 @@ impl Client {
                 while let Ok(notification) = notifications.recv().await {
                     if let RelayPoolNotification::Event(_url, event) = notification {
                         if event.kind == Kind::NostrConnect {
-                            let msg: String = nip04::decrypt(
-                                &self.keys.secret_key()?,
-                                &event.pubkey,
-                                &event.content,
-                            )?;
+                            let msg: String =
+                                nip04::decrypt(&keys.secret_key()?, &event.pubkey, &event.content)?;
                             let msg = Message::from_json(msg)?;
-                            if let Ok(Request::Connect(public_key)) = msg.to_request() {
+                            if let Ok(Request::Connect { public_key, .. }) = msg.to_request() {
                                 signer.set_signer_public_key(public_key).await;
                                 break;
                             }
@@ -154,6 +183,27 @@ impl Client {
         Ok(())
     }
 
+    /// Send `connect` request to the signer, with the secret of the [`BunkerURI`] (if any)
+    pub async fn req_signer_connect(&self, timeout: Option<Duration>) -> Result<(), Error> {
+        let signer: &RemoteSigner = self
+            .remote_signer
+            .as_ref()
+            .ok_or(Error::SignerNotConfigured)?;
+        let public_key = signer
+            .signer_public_key()
+            .await
+            .ok_or(Error::SignerPublicKeyNotFound)?;
+        let req = Request::Connect {
+            public_key,
+            secret: signer.secret(),
+            permissions: None,
+        };
+        match self.send_req_to_signer(req, timeout).await? {
+            Response::Connect(_) => Ok(()),
+            _ => Err(Error::ResponseNotMatchRequest),
+        }
+    }
+
     /// Send NIP46 [`Request`] to signer
     pub async fn send_req_to_signer(
         &self,
@@ -164,6 +214,51 @@ impl Client {
             .remote_signer
             .as_ref()
             .ok_or(Error::SignerNotConfigured)?;
+        let keys: &Keys = self.keys.as_ref().ok_or(Error::SignerNotConfigured)?;
+        Nip46Signer::new(
+            signer.clone(),
+            keys.clone(),
+            self.pool.clone(),
+            self.opts.clone(),
+        )
+        .send_req(req, timeout)
+        .await
+    }
+}
+
+/// NIP46 signer backend: send the requests to the [`RemoteSigner`] through the relay pool
+#[derive(Debug, Clone)]
+pub(crate) struct Nip46Signer {
+    signer: RemoteSigner,
+    app_keys: Keys,
+    pool: RelayPool,
+    opts: Options,
+}
+
+impl Nip46Signer {
+    pub fn new(signer: RemoteSigner, app_keys: Keys, pool: RelayPool, opts: Options) -> Self {
+        Self {
+            signer,
+            app_keys,
+            pool,
+            opts,
+        }
+    }
+
+    async fn send_msg_to(&self, url: Url, msg: ClientMessage) -> Result<(), Error> {
+        self.pool
+            .send_msg_to(url, msg, self.opts.get_wait_for_send())
+            .await?;
+        Ok(())
+    }
+
+    /// Send NIP46 [`Request`] to signer
+    pub async fn send_req(
+        &self,
+        req: Request,
+        timeout: Option<Duration>,
+    ) -> Result<Response, Error> {
+        let signer: &RemoteSigner = &self.signer;
         let signer_pubkey = signer
             .signer_public_key()
             .await
@@ -173,13 +268,14 @@ impl Client {
         let req_id = msg.id();
 
         // Send request to signer
-        let event =
-            EventBuilder::nostr_connect(&self.keys, signer_pubkey, msg)?.to_event(&self.keys)?;
-        self.send_event_to(signer.relay_url(), event).await?;
+        let event = EventBuilder::nostr_connect(&self.app_keys, signer_pubkey, msg)?
+            .to_event(&self.app_keys)?;
+        self.send_msg_to(signer.relay_url(), ClientMessage::new_event(event))
+            .await?;
 
         let sub_id = SubscriptionId::generate();
         let filter = Filter::new()
-            .pubkey(self.keys.public_key())
+            .pubkey(self.app_keys.public_key())
             .kind(Kind::NostrConnect)
             .since(Timestamp::now());
 
@@ -190,13 +286,13 @@ impl Client {
         )
         .await?;
 
-        let mut notifications = self.notifications();
+        let mut notifications = self.pool.notifications();
         let future = async {
             while let Ok(notification) = notifications.recv().await {
                 if let RelayPoolNotification::Event(_url, event) = notification {
                     if event.kind == Kind::NostrConnect {
                         let msg = nip04::decrypt(
-                            &self.keys.secret_key()?,
+                            &self.app_keys.secret_key()?,
                             &event.pubkey,
                             &event.content,
                         )?;
@@ -206,33 +302,14 @@ impl Client {
 
                         if let Message::Response { id, result, error } = &msg {
                             if &req_id == id {
-                                if let Some(result) = result {
-                                    let res = match req {
-                                        Request::Describe => Response::Describe(
-                                            serde_json::from_value(result.to_owned())?,
-                                        ),
-                                        Request::GetPublicKey => {
-                                            let pubkey = serde_json::from_value(result.to_owned())?;
-                                            Response::GetPublicKey(pubkey)
-                                        }
-                                        Request::SignEvent(_) => {
-                                            let sig = serde_json::from_value(result.to_owned())?;
-                                            Response::SignEvent(sig)
-                                        }
-                                        Request::Delegate { .. } => Response::Delegate(
-                                            serde_json::from_value(result.to_owned())?,
-                                        ),
-                                        Request::Nip04Encrypt { .. } => Response::Nip04Encrypt(
-                                            serde_json::from_value(result.to_owned())?,
-                                        ),
-                                        Request::Nip04Decrypt { .. } => Response::Nip04Decrypt(
-                                            serde_json::from_value(result.to_owned())?,
-                                        ),
-                                        Request::SignSchnorr { .. } => Response::SignSchnorr(
-                                            serde_json::from_value(result.to_owned())?,
-                                        ),
-                                        _ => break,
-                                    };
+                                if let Some(url) = msg.to_auth_url() {
+                                    log::info!("Received auth_url challenge: {url}");
+                                    let _ = signer.auth_url_sender.send(url);
+                                    continue;
+                                }
+
+                                if result.is_some() {
+                                    let res: Response = msg.to_response(&req)?;
 
                                     // Unsubscribe
                                     self.send_msg_to(
@@ -272,6 +349,91 @@ impl Client {
 
         res
     }
+
+    async fn send_req_with_default_timeout(&self, req: Request) -> Result<Response, SignerError> {
+        self.send_req(req, self.opts.get_nip46_timeout())
+            .await
+            .map_err(SignerError::backend)
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl NostrSigner for Nip46Signer {
+    async fn public_key(&self) -> Result<XOnlyPublicKey, SignerError> {
+        self.signer
+            .signer_public_key()
+            .await
+            .ok_or_else(|| SignerError::backend(Error::SignerPublicKeyNotFound))
+    }
+
+    async fn sign_event(&self, unsigned: UnsignedEvent) -> Result<Event, SignerError> {
+        let req = Request::SignEvent(unsigned.clone());
+        match self.send_req_with_default_timeout(req).await? {
+            Response::SignEvent(sig) => unsigned.add_signature(sig).map_err(SignerError::backend),
+            _ => Err(SignerError::backend(Error::ResponseNotMatchRequest)),
+        }
+    }
+
+    async fn nip04_encrypt(
+        &self,
+        public_key: XOnlyPublicKey,
+        content: String,
+    ) -> Result<String, SignerError> {
+        let req = Request::Nip04Encrypt {
+            public_key,
+            text: content,
+        };
+        match self.send_req_with_default_timeout(req).await? {
+            Response::Nip04Encrypt(content) => Ok(content),
+            _ => Err(SignerError::backend(Error::ResponseNotMatchRequest)),
+        }
+    }
+
+    async fn nip04_decrypt(
+        &self,
+        public_key: XOnlyPublicKey,
+        payload: String,
+    ) -> Result<String, SignerError> {
+        let req = Request::Nip04Decrypt {
+            public_key,
+            text: payload,
+        };
+        match self.send_req_with_default_timeout(req).await? {
+            Response::Nip04Decrypt(content) => Ok(content),
+            _ => Err(SignerError::backend(Error::ResponseNotMatchRequest)),
+        }
+    }
+
+    async fn nip44_encrypt(
+        &self,
+        public_key: XOnlyPublicKey,
+        content: String,
+    ) -> Result<String, SignerError> {
+        let req = Request::Nip44Encrypt {
+            public_key,
+            text: content,
+        };
+        match self.send_req_with_default_timeout(req).await? {
+            Response::Nip44Encrypt(content) => Ok(content),
+            _ => Err(SignerError::backend(Error::ResponseNotMatchRequest)),
+        }
+    }
+
+    async fn nip44_decrypt(
+        &self,
+        public_key: XOnlyPublicKey,
+        payload: String,
+    ) -> Result<String, SignerError> {
+        let req = Request::Nip44Decrypt {
+            public_key,
+            text: payload,
+        };
+        match self.send_req_with_default_timeout(req).await? {
+            Response::Nip44Decrypt(content) => Ok(content),
+            _ => Err(SignerError::backend(Error::ResponseNotMatchRequest)),
+        }
+    }
 }
 
 #[cfg(feature = "blocking")]
@@ -281,6 +443,11 @@ impl BlockingClient {
         RUNTIME.block_on(async { self.client.req_signer_public_key(timeout).await })
     }
 
+    #[allow(missing_docs)]
+    pub fn req_signer_connect(&self, timeout: Option<Duration>) -> Result<(), Error> {
+        RUNTIME.block_on(async { self.client.req_signer_connect(timeout).await })
+    }
+
     #[allow(missing_docs)]
     pub fn send_req_to_signer(
         &self,
@@ -0,0 +1,401 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! Memory database
+
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use nostr::secp256k1::XOnlyPublicKey;
+use nostr::{Event, EventId, Filter, Kind, Tag, Timestamp, Url};
+use tokio::sync::RwLock;
+
+use super::{DatabaseError, NostrDatabase, SaveEventStatus};
+
+/// In-memory [`NostrDatabase`]
+#[derive(Debug, Clone, Default)]
+pub struct MemoryDatabase {
+    events: Arc<RwLock<HashMap<EventId, Event>>>,
+    seen_on: Arc<RwLock<HashMap<EventId, Vec<Url>>>>,
+    deleted_ids: Arc<RwLock<HashSet<(EventId, XOnlyPublicKey)>>>,
+    deleted_coordinates: Arc<RwLock<HashMap<String, Timestamp>>>,
+}
+
+impl MemoryDatabase {
+    /// New empty [`MemoryDatabase`]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check if the event has been deleted by its author (NIP-09)
+    async fn is_deleted(&self, event: &Event) -> bool {
+        if self
+            .deleted_ids
+            .read()
+            .await
+            .contains(&(event.id, event.pubkey))
+        {
+            return true;
+        }
+
+        if event.kind.is_replaceable() || event.kind.is_parameterized_replaceable() {
+            let deleted_coordinates = self.deleted_coordinates.read().await;
+            if let Some(created_at) = deleted_coordinates.get(&coordinate(
+                &event.kind,
+                &event.pubkey,
+                event.identifier().unwrap_or_default(),
+            )) {
+                return *created_at >= event.created_at;
+            }
+        }
+
+        false
+    }
+
+    /// Record the deletion (NIP-09) and return the IDs of the stored events to remove
+    async fn apply_deletion(
+        &self,
+        events: &HashMap<EventId, Event>,
+        deletion: &Event,
+    ) -> Vec<EventId> {
+        let mut deleted_ids = self.deleted_ids.write().await;
+        let mut deleted_coordinates = self.deleted_coordinates.write().await;
+        let mut to_remove: Vec<EventId> = Vec::new();
+
+        for tag in deletion.tags.iter() {
+            match tag {
+                Tag::Event(event_id, ..) => {
+                    deleted_ids.insert((*event_id, deletion.pubkey));
+                    if let Some(event) = events.get(event_id) {
+                        if event.pubkey == deletion.pubkey && event.kind != Kind::EventDeletion {
+                            to_remove.push(*event_id);
+                        }
+                    }
+                }
+                Tag::A {
+                    kind,
+                    public_key,
+                    identifier,
+                    ..
+                } if *public_key == deletion.pubkey => {
+                    let created_at = deleted_coordinates
+                        .entry(coordinate(kind, public_key, identifier))
+                        .or_insert(deletion.created_at);
+                    *created_at = (*created_at).max(deletion.created_at);
+                    to_remove.extend(
+                        events
+                            .values()
+                            .filter(|e| {
+                                e.pubkey == *public_key
+                                    && e.kind == *kind
+                                    && e.created_at <= deletion.created_at
+                                    && (!kind.is_parameterized_replaceable()
+                                        || e.identifier().unwrap_or_default() == identifier)
+                            })
+                            .map(|e| e.id),
+                    );
+                }
+                _ => (),
+            }
+        }
+
+        to_remove
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl NostrDatabase for MemoryDatabase {
+    async fn save_event(&self, event: &Event) -> Result<SaveEventStatus, DatabaseError> {
+        let mut events = self.events.write().await;
+
+        if events.contains_key(&event.id) {
+            return Ok(SaveEventStatus::Duplicate);
+        }
+
+        if event.is_expired() || self.is_deleted(event).await {
+            return Ok(SaveEventStatus::Rejected);
+        }
+
+        // Remove older versions of replaceable events
+        let mut replaced: Vec<EventId> = Vec::new();
+        if event.kind.is_replaceable() || event.kind.is_parameterized_replaceable() {
+            for stored in events.values().filter(|e| same_replaceable(e, event)) {
+                if is_newer(event, stored) {
+                    replaced.push(stored.id);
+                } else {
+                    return Ok(SaveEventStatus::Rejected);
+                }
+            }
+        }
+
+        // Remove events referenced by deletion
+        let deleted: Vec<EventId> = if event.kind == Kind::EventDeletion {
+            self.apply_deletion(&events, event).await
+        } else {
+            Vec::new()
+        };
+
+        let mut seen_on = self.seen_on.write().await;
+        for id in replaced.iter().chain(deleted.iter()) {
+            events.remove(id);
+            seen_on.remove(id);
+        }
+
+        events.insert(event.id, event.clone());
+
+        if replaced.is_empty() {
+            Ok(SaveEventStatus::Saved)
+        } else {
+            Ok(SaveEventStatus::Replaced)
+        }
+    }
+
+    async fn event_by_id(&self, event_id: EventId) -> Result<Event, DatabaseError> {
+        let events = self.events.read().await;
+        events
+            .get(&event_id)
+            .cloned()
+            .ok_or(DatabaseError::NotFound)
+    }
+
+    async fn query(&self, filters: Vec<Filter>) -> Result<Vec<Event>, DatabaseError> {
+        let events = self.events.read().await;
+        let mut ids: HashSet<EventId> = HashSet::new();
+        let mut result: Vec<Event> = Vec::new();
+
+        for filter in filters.into_iter() {
+            let mut matching: Vec<&Event> = events
+                .values()
+                .filter(|e| filter.match_event(e) && !e.is_expired())
+                .collect();
+            matching.sort_by_key(|e| Reverse(e.created_at));
+            if let Some(limit) = filter.limit {
+                matching.truncate(limit);
+            }
+            for event in matching.into_iter() {
+                if ids.insert(event.id) {
+                    result.push(event.clone());
+                }
+            }
+        }
+
+        result.sort_by_key(|e| Reverse(e.created_at));
+        Ok(result)
+    }
+
+    async fn count(&self, filters: Vec<Filter>) -> Result<usize, DatabaseError> {
+        Ok(self.query(filters).await?.len())
+    }
+
+    async fn delete(&self, filter: Filter) -> Result<(), DatabaseError> {
+        let ids: Vec<EventId> = self
+            .query(vec![filter])
+            .await?
+            .into_iter()
+            .map(|e| e.id)
+            .collect();
+        let mut events = self.events.write().await;
+        let mut seen_on = self.seen_on.write().await;
+        for id in ids.iter() {
+            events.remove(id);
+            seen_on.remove(id);
+        }
+        Ok(())
+    }
+
+    async fn purge_expired(&self) -> Result<usize, DatabaseError> {
+        let mut events = self.events.write().await;
+        let mut seen_on = self.seen_on.write().await;
+        let expired: Vec<EventId> = events
+            .values()
+            .filter(|e| e.is_expired())
+            .map(|e| e.id)
+            .collect();
+        for id in expired.iter() {
+            events.remove(id);
+            seen_on.remove(id);
+        }
+        Ok(expired.len())
+    }
+
+    async fn event_id_seen(&self, event_id: EventId, relay_url: Url) -> Result<(), DatabaseError> {
+        let mut seen_on = self.seen_on.write().await;
+        let relays = seen_on.entry(event_id).or_default();
+        if !relays.contains(&relay_url) {
+            relays.push(relay_url);
+        }
+        Ok(())
+    }
+
+    async fn event_seen_on_relays(&self, event_id: EventId) -> Result<Vec<Url>, DatabaseError> {
+        let seen_on = self.seen_on.read().await;
+        Ok(seen_on.get(&event_id).cloned().unwrap_or_default())
+    }
+}
+
+/// Get the `<kind>:<pubkey>:<d-identifier>` coordinate
+///
+/// The identifier is used only for parameterized replaceable kinds (empty otherwise).
+fn coordinate(kind: &Kind, public_key: &XOnlyPublicKey, identifier: &str) -> String {
+    let identifier: &str = if kind.is_parameterized_replaceable() {
+        identifier
+    } else {
+        ""
+    };
+    format!("{}:{public_key}:{identifier}", kind.as_u64())
+}
+
+/// Check if two events share the same replaceable coordinate
+fn same_replaceable(a: &Event, b: &Event) -> bool {
+    a.pubkey == b.pubkey
+        && a.kind == b.kind
+        && (!a.kind.is_parameterized_replaceable()
+            || a.identifier().unwrap_or_default() == b.identifier().unwrap_or_default())
+}
+
+/// Check if `a` must replace `b` (on same timestamp, the lowest id wins)
+fn is_newer(a: &Event, b: &Event) -> bool {
+    a.created_at > b.created_at || (a.created_at == b.created_at && a.id < b.id)
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr::{EventBuilder, Keys, Kind};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_memory_database() {
+        let database = MemoryDatabase::new();
+        let keys = Keys::generate();
+
+        let note = EventBuilder::new_text_note("hello", &[])
+            .to_event(&keys)
+            .unwrap();
+        let metadata = EventBuilder::new(Kind::Metadata, "{}", &[])
+            .to_event(&keys)
+            .unwrap();
+
+        assert_eq!(
+            database.save_event(&note).await.unwrap(),
+            SaveEventStatus::Saved
+        );
+        assert_eq!(
+            database.save_event(&note).await.unwrap(),
+            SaveEventStatus::Duplicate
+        );
+        assert!(database.save_event(&metadata).await.unwrap().is_saved());
+
+        assert_eq!(database.event_by_id(note.id).await.unwrap(), note);
+        assert_eq!(database.count(vec![Filter::new()]).await.unwrap(), 2);
+        assert_eq!(
+            database
+                .query(vec![Filter::new().kind(Kind::Metadata)])
+                .await
+                .unwrap(),
+            vec![metadata]
+        );
+
+        let relay_url = Url::parse("wss://relay.example.com").unwrap();
+        database
+            .event_id_seen(note.id, relay_url.clone())
+            .await
+            .unwrap();
+        assert_eq!(
+            database.event_seen_on_relays(note.id).await.unwrap(),
+            vec![relay_url]
+        );
+
+        database
+            .delete(Filter::new().kind(Kind::TextNote))
+            .await
+            .unwrap();
+        assert!(matches!(
+            database.event_by_id(note.id).await,
+            Err(DatabaseError::NotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_memory_database_deletion() {
+        let database = MemoryDatabase::new();
+        let keys = Keys::generate();
+
+        let note = EventBuilder::new_text_note("hello", &[])
+            .to_event(&keys)
+            .unwrap();
+        database.save_event(&note).await.unwrap();
+
+        let deletion = EventBuilder::delete::<String>(vec![note.id], None)
+            .to_event(&keys)
+            .unwrap();
+        database.save_event(&deletion).await.unwrap();
+        assert!(matches!(
+            database.event_by_id(note.id).await,
+            Err(DatabaseError::NotFound)
+        ));
+        assert_eq!(
+            database.save_event(&note).await.unwrap(),
+            SaveEventStatus::Rejected
+        );
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[tokio::test]
+    async fn test_coordinate_deletion_same_as_sqlite() {
+        use std::str::FromStr;
+        use std::time::Duration;
+
+        use nostr::event::tag::UncheckedUrl;
+        use nostr::SubscriptionId;
+        use nostr_sdk_sqlite::Store;
+
+        let path = std::env::temp_dir().join(format!("{}.db", SubscriptionId::generate()));
+        let store = Store::open(&path).unwrap();
+        let keys = Keys::generate();
+        let now = Timestamp::now();
+
+        // Metadata isn't parameterized replaceable: the stray `d` tag must be ignored
+        let metadata = |created_at: Timestamp| {
+            EventBuilder::new(Kind::Metadata, "{}", &[Tag::Identifier(String::from("x"))])
+                .custom_created_at(created_at)
+                .to_event(&keys)
+                .unwrap()
+        };
+        let deletion = EventBuilder::new(
+            Kind::EventDeletion,
+            "",
+            &[Tag::A {
+                kind: Kind::Metadata,
+                public_key: keys.public_key(),
+                identifier: String::new(),
+                relay_url: UncheckedUrl::from_str("").unwrap(),
+            }],
+        )
+        .custom_created_at(now)
+        .to_event(&keys)
+        .unwrap();
+        let older = metadata(now - Duration::from_secs(10));
+        let newer = metadata(now + Duration::from_secs(10));
+
+        for database in [
+            Arc::new(MemoryDatabase::new()) as Arc<dyn NostrDatabase>,
+            Arc::new(store.clone()),
+        ] {
+            assert!(database.save_event(&deletion).await.unwrap().is_saved());
+            assert_eq!(
+                database.save_event(&older).await.unwrap(),
+                SaveEventStatus::Rejected
+            );
+            assert!(database.save_event(&newer).await.unwrap().is_saved());
+        }
+
+        store.close();
+        for ext in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{ext}", path.display()));
+        }
+    }
+}
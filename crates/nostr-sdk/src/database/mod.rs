@@ -0,0 +1,99 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! Database
+//!
+//! Storage backends for the events received by the [`RelayPool`](crate::relay::pool::RelayPool).
+
+use std::fmt;
+
+use async_trait::async_trait;
+use nostr::{Event, EventId, Filter, Url};
+
+pub mod memory;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+
+pub use self::memory::MemoryDatabase;
+
+/// Database error
+#[derive(Debug, thiserror::Error)]
+pub enum DatabaseError {
+    /// Backend error
+    #[error(transparent)]
+    Backend(Box<dyn std::error::Error + Send + Sync>),
+    /// Event not found
+    #[error("event not found")]
+    NotFound,
+}
+
+impl DatabaseError {
+    /// Create a new [`DatabaseError::Backend`] from any backend error
+    pub fn backend<E>(error: E) -> Self
+    where
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        Self::Backend(Box::new(error))
+    }
+}
+
+/// Status of [`NostrDatabase::save_event`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaveEventStatus {
+    /// Event saved
+    Saved,
+    /// Event saved, replacing an older version
+    Replaced,
+    /// Event already stored
+    Duplicate,
+    /// Event rejected since a newer version is already stored
+    Rejected,
+}
+
+impl SaveEventStatus {
+    /// Check if the event has been saved
+    pub fn is_saved(&self) -> bool {
+        matches!(self, Self::Saved | Self::Replaced)
+    }
+}
+
+/// Nostr database
+///
+/// Implement this trait to plug a custom storage into [`Client`](crate::Client).
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub trait NostrDatabase: fmt::Debug + Send + Sync {
+    /// Save [`Event`] into store
+    ///
+    /// Replaceable and parameterized replaceable events must replace older versions
+    /// and must be rejected if a newer version is already stored.
+    ///
+    /// Expired events (NIP-40) and events deleted by their author (NIP-09) must be rejected,
+    /// while event deletions must remove the referenced events.
+    async fn save_event(&self, event: &Event) -> Result<SaveEventStatus, DatabaseError>;
+
+    /// Get [`Event`] by [`EventId`]
+    async fn event_by_id(&self, event_id: EventId) -> Result<Event, DatabaseError>;
+
+    /// Query stored events
+    ///
+    /// Events are ordered by `created_at` (newest first).
+    async fn query(&self, filters: Vec<Filter>) -> Result<Vec<Event>, DatabaseError>;
+
+    /// Count stored events matching the [`Filter`]s
+    async fn count(&self, filters: Vec<Filter>) -> Result<usize, DatabaseError>;
+
+    /// Delete all stored events matching the [`Filter`]
+    async fn delete(&self, filter: Filter) -> Result<(), DatabaseError>;
+
+    /// Delete expired events (NIP-40)
+    ///
+    /// Return the number of deleted events.
+    async fn purge_expired(&self) -> Result<usize, DatabaseError>;
+
+    /// Mark [`EventId`] as seen by relay
+    async fn event_id_seen(&self, event_id: EventId, relay_url: Url) -> Result<(), DatabaseError>;
+
+    /// Get list of relays that have seen the [`EventId`]
+    async fn event_seen_on_relays(&self, event_id: EventId) -> Result<Vec<Url>, DatabaseError>;
+}
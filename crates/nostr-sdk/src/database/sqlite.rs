@@ -0,0 +1,83 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! SQLite database
+
+use async_trait::async_trait;
+use nostr::{Event, EventId, Filter, Url};
+use nostr_sdk_sqlite::{Error, EventInsertStatus, Store};
+
+use super::{DatabaseError, NostrDatabase, SaveEventStatus};
+
+impl From<Error> for DatabaseError {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::NotFound => Self::NotFound,
+            e => Self::backend(e),
+        }
+    }
+}
+
+impl From<EventInsertStatus> for SaveEventStatus {
+    fn from(status: EventInsertStatus) -> Self {
+        match status {
+            EventInsertStatus::Inserted => Self::Saved,
+            EventInsertStatus::Replaced => Self::Replaced,
+            EventInsertStatus::Duplicate => Self::Duplicate,
+            EventInsertStatus::Rejected => Self::Rejected,
+        }
+    }
+}
+
+/// Run a blocking [`Store`] operation on the blocking thread pool
+async fn interact<F, T>(store: &Store, f: F) -> Result<T, DatabaseError>
+where
+    F: FnOnce(Store) -> Result<T, Error> + Send + 'static,
+    T: Send + 'static,
+{
+    let store: Store = store.clone();
+    Ok(tokio::task::spawn_blocking(move || f(store))
+        .await
+        .map_err(DatabaseError::backend)??)
+}
+
+#[async_trait]
+impl NostrDatabase for Store {
+    async fn save_event(&self, event: &Event) -> Result<SaveEventStatus, DatabaseError> {
+        let event: Event = event.clone();
+        Ok(interact(self, move |store| store.insert_event(event))
+            .await?
+            .into())
+    }
+
+    async fn event_by_id(&self, event_id: EventId) -> Result<Event, DatabaseError> {
+        interact(self, move |store| store.event_by_id(event_id)).await
+    }
+
+    async fn query(&self, filters: Vec<Filter>) -> Result<Vec<Event>, DatabaseError> {
+        interact(self, move |store| store.query(filters)).await
+    }
+
+    async fn count(&self, filters: Vec<Filter>) -> Result<usize, DatabaseError> {
+        interact(self, move |store| store.count(filters)).await
+    }
+
+    async fn delete(&self, filter: Filter) -> Result<(), DatabaseError> {
+        interact(self, move |store| store.delete(filter)).await
+    }
+
+    async fn purge_expired(&self) -> Result<usize, DatabaseError> {
+        interact(self, |store| store.purge_expired()).await
+    }
+
+    async fn event_id_seen(&self, event_id: EventId, relay_url: Url) -> Result<(), DatabaseError> {
+        interact(self, move |store| {
+            store.event_seen_on_relay(event_id, relay_url)
+        })
+        .await
+    }
+
+    async fn event_seen_on_relays(&self, event_id: EventId) -> Result<Vec<Url>, DatabaseError> {
+        interact(self, move |store| store.event_seen_on_relays(event_id)).await
+    }
+}
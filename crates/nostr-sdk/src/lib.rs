@@ -25,8 +25,10 @@ use once_cell::sync::Lazy;
 use tokio::runtime::Runtime;
 
 pub use nostr::{self, *};
+pub use nostr_sdk_net::futures_util;
 
 pub mod client;
+pub mod database;
 pub mod prelude;
 pub mod relay;
 mod thread;
@@ -35,7 +37,11 @@ mod time;
 #[cfg(feature = "blocking")]
 pub use self::client::blocking;
 pub use self::client::{Client, Options};
-pub use self::relay::{Relay, RelayOptions, RelayPoolNotification, RelayStatus};
+pub use self::database::{DatabaseError, MemoryDatabase, NostrDatabase, SaveEventStatus};
+pub use self::relay::{
+    EventStream, EventStreamItem, ReconnectPolicy, Relay, RelayOptions, RelayPoolNotification,
+    RelayStatus,
+};
 
 #[cfg(feature = "blocking")]
 static RUNTIME: Lazy<Runtime> = Lazy::new(|| Runtime::new().expect("Can't start Tokio runtime"));
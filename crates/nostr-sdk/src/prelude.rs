@@ -7,6 +7,9 @@
 pub use nostr::prelude::*;
 
 // Internal modules
+#[cfg(feature = "nip46")]
+pub use crate::client::signer;
 pub use crate::client::*;
+pub use crate::database::*;
 pub use crate::relay::*;
 pub use crate::*;
@@ -0,0 +1,259 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! Relay authentication (NIP-42)
+//!
+//! <https://github.com/nostr-protocol/nips/blob/master/42.md>
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::Duration;
+
+use nostr::{ClientMessage, Event, EventId, MachineReadablePrefix, SubscriptionId};
+
+use super::{Error, PublishStatus, Relay};
+
+/// Max number of sent events kept while waiting for the relay `OK`
+const MAX_UNACKED_EVENTS: usize = 1000;
+
+/// [`Relay`] authentication status
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RelayAuthStatus {
+    /// Authentication not requested by the relay
+    NotRequired,
+    /// Challenge received, not authenticated yet
+    Challenged(String),
+    /// Authenticated
+    Authenticated,
+    /// Authentication rejected by the relay, with reason
+    Failed(String),
+}
+
+/// Authentication state of a [`Relay`] connection
+#[derive(Debug)]
+pub(crate) struct AuthState {
+    status: RelayAuthStatus,
+    /// Id of the last `AUTH` event sent
+    event_id: Option<EventId>,
+    /// Events sent and waiting for the `OK`
+    unacked: HashMap<EventId, Event>,
+    unacked_order: VecDeque<EventId>,
+    /// Events rejected with `auth-required`
+    pending_events: HashMap<EventId, Event>,
+    /// Subscriptions closed with `auth-required`
+    pending_subscriptions: HashSet<SubscriptionId>,
+}
+
+impl Default for AuthState {
+    fn default() -> Self {
+        Self {
+            status: RelayAuthStatus::NotRequired,
+            event_id: None,
+            unacked: HashMap::new(),
+            unacked_order: VecDeque::new(),
+            pending_events: HashMap::new(),
+            pending_subscriptions: HashSet::new(),
+        }
+    }
+}
+
+impl AuthState {
+    /// Reset status and challenge (new connection)
+    ///
+    /// Pending events and subscriptions are kept, to retry them after the next authentication.
+    pub fn reset(&mut self) {
+        self.status = RelayAuthStatus::NotRequired;
+        self.event_id = None;
+    }
+
+    pub fn challenged(&mut self, challenge: String) {
+        self.status = RelayAuthStatus::Challenged(challenge);
+    }
+
+    pub fn event_sent(&mut self, event: Event) {
+        if self.unacked.insert(event.id, event.clone()).is_none() {
+            self.unacked_order.push_back(event.id);
+        }
+        while self.unacked_order.len() > MAX_UNACKED_EVENTS {
+            if let Some(id) = self.unacked_order.pop_front() {
+                self.unacked.remove(&id);
+            }
+        }
+    }
+
+    pub fn subscription_rejected(&mut self, id: SubscriptionId) {
+        self.pending_subscriptions.insert(id);
+    }
+
+    /// Handle an `OK` message
+    ///
+    /// Return the events and subscriptions to retry if the relay accepted the authentication.
+    pub fn ok(
+        &mut self,
+        event_id: EventId,
+        status: bool,
+        message: &str,
+    ) -> Option<(Vec<Event>, HashSet<SubscriptionId>)> {
+        if self.event_id == Some(event_id) {
+            self.event_id = None;
+            if status {
+                self.status = RelayAuthStatus::Authenticated;
+                let events = self.pending_events.drain().map(|(_, e)| e).collect();
+                let subscriptions = std::mem::take(&mut self.pending_subscriptions);
+                return Some((events, subscriptions));
+            } else {
+                self.status = RelayAuthStatus::Failed(message.to_string());
+                return None;
+            }
+        }
+
+        if let Some(event) = self.unacked.remove(&event_id) {
+            self.unacked_order.retain(|id| id != &event_id);
+            if !status
+                && MachineReadablePrefix::parse(message)
+                    == Some(MachineReadablePrefix::AuthRequired)
+            {
+                self.pending_events.insert(event_id, event);
+            }
+        }
+
+        None
+    }
+}
+
+impl Relay {
+    /// Get [`RelayAuthStatus`]
+    pub async fn auth_status(&self) -> RelayAuthStatus {
+        let auth = self.auth.lock().await;
+        auth.status.clone()
+    }
+
+    /// Get the last NIP-42 challenge received, if not authenticated yet
+    pub async fn auth_challenge(&self) -> Option<String> {
+        match self.auth_status().await {
+            RelayAuthStatus::Challenged(challenge) => Some(challenge),
+            _ => None,
+        }
+    }
+
+    /// Send a signed `AUTH` event and wait for the relay `OK`
+    ///
+    /// After a successful authentication, the subscriptions and the events
+    /// rejected with `auth-required` are sent again.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/42.md>
+    pub async fn auth(&self, event: Event, timeout: Duration) -> Result<(), Error> {
+        let id: EventId = event.id;
+
+        // Listen for notifications before sending, to not miss the `OK`
+        let notifications = self.notification_sender.subscribe();
+
+        {
+            let mut auth = self.auth.lock().await;
+            auth.event_id = Some(id);
+        }
+
+        self.send_msg(ClientMessage::new_auth(event), true).await?;
+
+        match self.wait_for_ok(notifications, id, timeout).await {
+            PublishStatus::Accepted => {
+                log::info!("Authenticated to {}", self.url);
+                Ok(())
+            }
+            PublishStatus::Rejected(message) => Err(Error::AuthFailed(message)),
+            PublishStatus::Timeout => Err(Error::Timeout),
+            PublishStatus::NotSent(..) => Err(Error::MessagetNotSent),
+        }
+    }
+
+    /// Handle `AUTH` challenge
+    pub(crate) async fn handle_auth_challenge(&self, challenge: &str) {
+        log::debug!("Received auth challenge from {}", self.url);
+        let mut auth = self.auth.lock().await;
+        auth.challenged(challenge.to_string());
+    }
+
+    /// Handle `OK` message, retrying the messages rejected with `auth-required` after authentication
+    pub(crate) async fn handle_ok(&self, event_id: EventId, status: bool, message: &str) {
+        let retry = {
+            let mut auth = self.auth.lock().await;
+            auth.ok(event_id, status, message)
+        };
+
+        if let Some((events, subscription_ids)) = retry {
+            let subscriptions = self.subscriptions().await;
+            for id in subscription_ids.into_iter() {
+                if let Some(subscription) = subscriptions.get(&id) {
+                    log::debug!("Retrying subscription {id} on {}", self.url);
+                    let msg = ClientMessage::new_req(id, subscription.filters.clone());
+                    if let Err(e) = self.send_msg(msg, false).await {
+                        log::error!("Impossible to retry subscription on {}: {e}", self.url);
+                    }
+                }
+            }
+
+            for event in events.into_iter() {
+                log::debug!("Retrying event {} on {}", event.id, self.url);
+                if let Err(e) = self.send_msg(ClientMessage::new_event(event), false).await {
+                    log::error!("Impossible to retry event on {}: {e}", self.url);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr::{EventBuilder, Keys};
+
+    use super::*;
+
+    #[test]
+    fn test_auth_state_retry() {
+        let keys = Keys::generate();
+        let event = EventBuilder::new_text_note("hello", &[])
+            .to_event(&keys)
+            .unwrap();
+        let other = EventBuilder::new_text_note("world", &[])
+            .to_event(&keys)
+            .unwrap();
+        let sub_id = SubscriptionId::generate();
+
+        let mut auth = AuthState::default();
+        auth.challenged(String::from("challenge"));
+        auth.event_sent(event.clone());
+        auth.event_sent(other.clone());
+        auth.subscription_rejected(sub_id.clone());
+
+        assert!(auth
+            .ok(
+                event.id,
+                false,
+                "auth-required: we only accept events from registered users"
+            )
+            .is_none());
+        assert!(auth.ok(other.id, false, "blocked: spam").is_none());
+        assert!(auth.unacked.is_empty());
+
+        // Auth rejected
+        let auth_event = EventBuilder::new_text_note("auth", &[])
+            .to_event(&keys)
+            .unwrap();
+        auth.event_id = Some(auth_event.id);
+        assert!(auth
+            .ok(auth_event.id, false, "invalid: bad challenge")
+            .is_none());
+        assert_eq!(
+            auth.status,
+            RelayAuthStatus::Failed(String::from("invalid: bad challenge"))
+        );
+
+        // Auth accepted
+        auth.event_id = Some(auth_event.id);
+        let (events, subscriptions) = auth.ok(auth_event.id, true, "").unwrap();
+        assert_eq!(auth.status, RelayAuthStatus::Authenticated);
+        assert_eq!(events, vec![event]);
+        assert!(subscriptions.contains(&sub_id));
+        assert!(auth.pending_events.is_empty());
+        assert!(auth.pending_subscriptions.is_empty());
+    }
+}
@@ -3,24 +3,37 @@
 
 //! Relay
 
+use std::collections::HashMap;
 use std::fmt;
 #[cfg(not(target_arch = "wasm32"))]
 use std::net::SocketAddr;
 use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
 
 #[cfg(feature = "nip11")]
 use nostr::nips::nip11::RelayInformationDocument;
-use nostr::{ClientMessage, Event, Filter, RelayMessage, SubscriptionId, Timestamp, Url};
+use nostr::secp256k1::rand::{self, Rng};
+use nostr::{
+    ClientMessage, Event, EventId, Filter, MachineReadablePrefix, RelayMessage, SubscriptionId,
+    Timestamp, Url,
+};
 use nostr_sdk_net::futures_util::{Future, SinkExt, StreamExt};
 use nostr_sdk_net::{self as net, WsMessage};
+use tokio::sync::broadcast::error::RecvError;
 use tokio::sync::mpsc::{self, Receiver, Sender};
 use tokio::sync::{broadcast, oneshot, Mutex};
 
+pub mod auth;
 pub mod pool;
+pub mod stream;
 
-pub use self::pool::{RelayPoolMessage, RelayPoolNotification};
+use self::auth::AuthState;
+pub use self::auth::RelayAuthStatus;
+pub use self::pool::{CountOutput, PublishOutput, RelayPoolMessage, RelayPoolNotification};
+pub use self::stream::{EventStream, EventStreamItem};
 #[cfg(feature = "blocking")]
 use crate::RUNTIME;
 use crate::{thread, time};
@@ -54,6 +67,18 @@ pub enum Error {
     /// Filters empty
     #[error("filters empty")]
     FiltersEmpty,
+    /// Subscription not found
+    #[error("subscription not found")]
+    SubscriptionNotFound,
+    /// Max subscriptions reached
+    #[error("max subscriptions reached (limit: {0})")]
+    MaxSubscriptionsReached(usize),
+    /// Authentication rejected by the relay
+    #[error("authentication failed: {0}")]
+    AuthFailed(String),
+    /// Request closed by the relay
+    #[error("request closed by relay: {0}")]
+    RequestClosed(String),
 }
 
 /// Relay connection status
@@ -83,18 +108,175 @@ impl fmt::Display for RelayStatus {
     }
 }
 
+/// Outcome of an [`Event`] published to a relay
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PublishStatus {
+    /// Event accepted by the relay
+    Accepted,
+    /// Event rejected by the relay, with reason
+    Rejected(String),
+    /// No `OK` received from the relay before the timeout
+    Timeout,
+    /// Event not sent to the relay
+    NotSent(String),
+}
+
+impl PublishStatus {
+    /// Check if the event has been accepted
+    pub fn is_accepted(&self) -> bool {
+        matches!(self, Self::Accepted)
+    }
+
+    /// Get the [`MachineReadablePrefix`] of the rejection reason, if any
+    pub fn prefix(&self) -> Option<MachineReadablePrefix> {
+        match self {
+            Self::Rejected(reason) => MachineReadablePrefix::parse(reason),
+            _ => None,
+        }
+    }
+}
+
 /// Relay event
 #[derive(Debug)]
 pub enum RelayEvent {
     /// Send [`ClientMessage`]
     SendMsg(Box<ClientMessage>),
-    // Ping,
+    /// Ping
+    Ping {
+        /// Nonce sent as ping payload
+        nonce: u64,
+    },
     /// Close
-    Close,
+    Close {
+        /// Reason of the disconnection
+        reason: Option<String>,
+    },
     /// Completly disconnect
     Terminate,
 }
 
+/// [`Relay`] reconnection policy
+///
+/// The delay between attempts grows exponentially with the number of consecutive failures,
+/// up to `max_delay`, and is randomized by `jitter` to avoid reconnection bursts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReconnectPolicy {
+    /// Automatically reconnect (default: true)
+    pub auto_reconnect: bool,
+    /// Delay before the first retry (default: 10 secs)
+    pub initial_delay: Duration,
+    /// Delay multiplier applied at every consecutive failure (default: 2.0)
+    pub multiplier: f64,
+    /// Max delay between attempts (default: 5 min)
+    pub max_delay: Duration,
+    /// Random variation of the delay, as fraction of it, between 0.0 and 1.0 (default: 0.1)
+    pub jitter: f64,
+    /// Max consecutive failed attempts before giving up (default: `None`, retry forever)
+    pub max_attempts: Option<usize>,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            auto_reconnect: true,
+            initial_delay: Duration::from_secs(10),
+            multiplier: 2.0,
+            max_delay: Duration::from_secs(300),
+            jitter: 0.1,
+            max_attempts: None,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// New default [`ReconnectPolicy`]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Policy that never reconnects automatically
+    pub fn disabled() -> Self {
+        Self {
+            auto_reconnect: false,
+            ..Self::default()
+        }
+    }
+
+    /// Enable/disable automatic reconnection
+    pub fn auto_reconnect(self, auto_reconnect: bool) -> Self {
+        Self {
+            auto_reconnect,
+            ..self
+        }
+    }
+
+    /// Set initial delay
+    pub fn initial_delay(self, initial_delay: Duration) -> Self {
+        Self {
+            initial_delay,
+            ..self
+        }
+    }
+
+    /// Set delay multiplier
+    pub fn multiplier(self, multiplier: f64) -> Self {
+        Self { multiplier, ..self }
+    }
+
+    /// Set max delay
+    pub fn max_delay(self, max_delay: Duration) -> Self {
+        Self { max_delay, ..self }
+    }
+
+    /// Set jitter (clamped between 0.0 and 1.0)
+    pub fn jitter(self, jitter: f64) -> Self {
+        Self {
+            jitter: jitter.clamp(0.0, 1.0),
+            ..self
+        }
+    }
+
+    /// Set max consecutive failed attempts
+    pub fn max_attempts(self, max_attempts: Option<usize>) -> Self {
+        Self {
+            max_attempts,
+            ..self
+        }
+    }
+
+    /// Check if the max number of consecutive failed attempts has been reached
+    pub fn is_exhausted(&self, failures: usize) -> bool {
+        match self.max_attempts {
+            Some(max) => failures >= max,
+            None => false,
+        }
+    }
+
+    /// Get delay (without jitter) before the next attempt after `failures` consecutive failures
+    pub fn base_delay(&self, failures: usize) -> Duration {
+        let exp: i32 = failures.saturating_sub(1).min(i32::MAX as usize) as i32;
+        let secs: f64 = self.initial_delay.as_secs_f64() * self.multiplier.max(1.0).powi(exp);
+        let max: f64 = self.max_delay.as_secs_f64();
+        if secs.is_finite() && secs < max {
+            Duration::from_secs_f64(secs)
+        } else {
+            self.max_delay
+        }
+    }
+
+    /// Get randomized delay before the next attempt after `failures` consecutive failures
+    pub fn delay(&self, failures: usize) -> Duration {
+        let base: Duration = self.base_delay(failures);
+        if self.jitter > 0.0 {
+            let jitter: f64 = self.jitter.clamp(0.0, 1.0);
+            let factor: f64 = rand::thread_rng().gen_range(1.0 - jitter..=1.0 + jitter);
+            base.mul_f64(factor)
+        } else {
+            base
+        }
+    }
+}
+
 /// [`Relay`] options
 #[derive(Debug, Clone)]
 pub struct RelayOptions {
@@ -102,6 +284,12 @@ pub struct RelayOptions {
     read: Arc<AtomicBool>,
     /// Allow/disallow write actions
     write: Arc<AtomicBool>,
+    /// Reconnection policy
+    reconnect: ReconnectPolicy,
+    /// Ping interval (`None` to disable ping)
+    ping_interval: Option<Duration>,
+    /// Max time to wait for the pong before considering the connection stale
+    pong_timeout: Duration,
 }
 
 impl Default for RelayOptions {
@@ -116,9 +304,50 @@ impl RelayOptions {
         Self {
             read: Arc::new(AtomicBool::new(read)),
             write: Arc::new(AtomicBool::new(write)),
+            reconnect: ReconnectPolicy::default(),
+            ping_interval: Some(Duration::from_secs(55)),
+            pong_timeout: Duration::from_secs(10),
+        }
+    }
+
+    /// Set [`ReconnectPolicy`]
+    pub fn reconnect_policy(self, reconnect: ReconnectPolicy) -> Self {
+        Self { reconnect, ..self }
+    }
+
+    /// Get [`ReconnectPolicy`]
+    pub fn get_reconnect_policy(&self) -> ReconnectPolicy {
+        self.reconnect
+    }
+
+    /// Set ping interval (default: 55 secs, `None` to disable)
+    ///
+    /// Ping is not supported on `wasm32`.
+    pub fn ping_interval(self, ping_interval: Option<Duration>) -> Self {
+        Self {
+            ping_interval,
+            ..self
         }
     }
 
+    /// Get ping interval
+    pub fn get_ping_interval(&self) -> Option<Duration> {
+        self.ping_interval
+    }
+
+    /// Set pong timeout (default: 10 secs)
+    pub fn pong_timeout(self, pong_timeout: Duration) -> Self {
+        Self {
+            pong_timeout,
+            ..self
+        }
+    }
+
+    /// Get pong timeout
+    pub fn get_pong_timeout(&self) -> Duration {
+        self.pong_timeout
+    }
+
     /// Get read option
     pub fn read(&self) -> bool {
         self.read.load(Ordering::SeqCst)
@@ -150,6 +379,7 @@ pub struct RelayConnectionStats {
     attempts: Arc<AtomicUsize>,
     success: Arc<AtomicUsize>,
     connected_at: Arc<AtomicU64>,
+    latency: Arc<AtomicU64>,
 }
 
 impl Default for RelayConnectionStats {
@@ -165,6 +395,7 @@ impl RelayConnectionStats {
             attempts: Arc::new(AtomicUsize::new(0)),
             success: Arc::new(AtomicUsize::new(0)),
             connected_at: Arc::new(AtomicU64::new(0)),
+            latency: Arc::new(AtomicU64::new(0)),
         }
     }
 
@@ -183,6 +414,20 @@ impl RelayConnectionStats {
         Timestamp::from(self.connected_at.load(Ordering::SeqCst))
     }
 
+    /// Get the round-trip latency measured with the last ping (`None` if not measured yet)
+    pub fn latency(&self) -> Option<Duration> {
+        match self.latency.load(Ordering::SeqCst) {
+            0 => None,
+            micros => Some(Duration::from_micros(micros)),
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn save_latency(&self, latency: Duration) {
+        let micros: u64 = latency.as_micros().clamp(1, u64::MAX as u128) as u64;
+        self.latency.store(micros, Ordering::SeqCst);
+    }
+
     pub(crate) fn new_attempt(&self) {
         self.attempts.fetch_add(1, Ordering::SeqCst);
     }
@@ -197,6 +442,40 @@ impl RelayConnectionStats {
     }
 }
 
+/// Track the last ping sent to the relay
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone, Default)]
+struct PingTracker {
+    nonce: Arc<AtomicU64>,
+    replied: Arc<AtomicBool>,
+    sent_at: Arc<Mutex<Option<Instant>>>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl PingTracker {
+    /// Register a new ping and get its nonce
+    async fn new_ping(&self) -> u64 {
+        let nonce: u64 = self.nonce.fetch_add(1, Ordering::SeqCst) + 1;
+        self.replied.store(false, Ordering::SeqCst);
+        let mut sent_at = self.sent_at.lock().await;
+        *sent_at = Some(Instant::now());
+        nonce
+    }
+
+    /// Register a pong and get the round-trip latency if it matches the last ping
+    async fn pong(&self, nonce: u64) -> Option<Duration> {
+        if nonce != self.nonce.load(Ordering::SeqCst) || self.replied.swap(true, Ordering::SeqCst) {
+            return None;
+        }
+        let sent_at = self.sent_at.lock().await;
+        sent_at.map(|sent_at| sent_at.elapsed())
+    }
+
+    fn is_replied(&self) -> bool {
+        self.replied.load(Ordering::SeqCst)
+    }
+}
+
 /// Relay instance's actual subscription with its unique id
 #[derive(Debug, Clone)]
 pub struct ActiveSubscription {
@@ -215,11 +494,47 @@ impl Default for ActiveSubscription {
 impl ActiveSubscription {
     /// Create new [`ActiveSubscription`]
     pub fn new() -> Self {
-        Self {
-            id: SubscriptionId::generate(),
-            filters: Vec::new(),
-        }
+        Self::with_id(SubscriptionId::generate(), Vec::new())
     }
+
+    /// Create new [`ActiveSubscription`] with custom [`SubscriptionId`] and [`Filter`]s
+    pub fn with_id(id: SubscriptionId, filters: Vec<Filter>) -> Self {
+        Self { id, filters }
+    }
+}
+
+/// Subscriptions with their insertion index
+pub(crate) type OrderedSubscriptions<T> = HashMap<SubscriptionId, (usize, T)>;
+
+/// Insert or replace a subscription
+///
+/// A replaced subscription keeps its insertion index.
+pub(crate) fn insert_subscription<T>(
+    subscriptions: &mut OrderedSubscriptions<T>,
+    id: SubscriptionId,
+    value: T,
+) {
+    let index: usize = match subscriptions.get(&id) {
+        Some((index, _)) => *index,
+        None => subscriptions
+            .values()
+            .map(|(index, _)| index + 1)
+            .max()
+            .unwrap_or_default(),
+    };
+    subscriptions.insert(id, (index, value));
+}
+
+/// Subscriptions sorted by insertion index (oldest first)
+pub(crate) fn sorted_subscriptions<T>(
+    subscriptions: OrderedSubscriptions<T>,
+) -> Vec<(SubscriptionId, T)> {
+    let mut subscriptions: Vec<(SubscriptionId, (usize, T))> = subscriptions.into_iter().collect();
+    subscriptions.sort_by_key(|(_, (index, _))| *index);
+    subscriptions
+        .into_iter()
+        .map(|(id, (_, value))| (id, value))
+        .collect()
 }
 
 /// Relay
@@ -233,12 +548,16 @@ pub struct Relay {
     document: Arc<Mutex<RelayInformationDocument>>,
     opts: RelayOptions,
     stats: RelayConnectionStats,
+    #[cfg(not(target_arch = "wasm32"))]
+    ping: PingTracker,
     scheduled_for_termination: Arc<AtomicBool>,
     pool_sender: Sender<RelayPoolMessage>,
     relay_sender: Sender<Message>,
     relay_receiver: Arc<Mutex<Receiver<Message>>>,
     notification_sender: broadcast::Sender<RelayPoolNotification>,
-    subscription: Arc<Mutex<ActiveSubscription>>,
+    subscriptions: Arc<Mutex<OrderedSubscriptions<ActiveSubscription>>>,
+    default_subscription_id: SubscriptionId,
+    auth: Arc<Mutex<AuthState>>,
 }
 
 impl PartialEq for Relay {
@@ -267,12 +586,15 @@ impl Relay {
             document: Arc::new(Mutex::new(RelayInformationDocument::new())),
             opts,
             stats: RelayConnectionStats::new(),
+            ping: PingTracker::default(),
             scheduled_for_termination: Arc::new(AtomicBool::new(false)),
             pool_sender,
             relay_sender,
             relay_receiver: Arc::new(Mutex::new(relay_receiver)),
             notification_sender,
-            subscription: Arc::new(Mutex::new(ActiveSubscription::new())),
+            subscriptions: Arc::new(Mutex::new(HashMap::new())),
+            default_subscription_id: SubscriptionId::generate(),
+            auth: Arc::new(Mutex::new(AuthState::default())),
         }
     }
 
@@ -298,7 +620,9 @@ impl Relay {
             relay_sender,
             relay_receiver: Arc::new(Mutex::new(relay_receiver)),
             notification_sender,
-            subscription: Arc::new(Mutex::new(ActiveSubscription::new())),
+            subscriptions: Arc::new(Mutex::new(HashMap::new())),
+            default_subscription_id: SubscriptionId::generate(),
+            auth: Arc::new(Mutex::new(AuthState::default())),
         }
     }
 
@@ -325,9 +649,22 @@ impl Relay {
         RUNTIME.block_on(async { self.status().await })
     }
 
-    async fn set_status(&self, status: RelayStatus) {
+    async fn set_status(&self, status: RelayStatus, reason: Option<String>) {
         let mut s = self.status.lock().await;
-        *s = status;
+        if *s == status {
+            return;
+        }
+        *s = status.clone();
+        drop(s);
+
+        let _ = self
+            .notification_sender
+            .send(RelayPoolNotification::RelayStatus {
+                relay_url: self.url(),
+                status,
+                reason,
+                attempts: self.stats.attempts(),
+            });
     }
 
     /// Get [`RelayInformationDocument`]
@@ -349,16 +686,66 @@ impl Relay {
         *d = document;
     }
 
-    /// Get [`ActiveSubscription`]
-    pub async fn subscription(&self) -> ActiveSubscription {
-        let subscription = self.subscription.lock().await;
-        subscription.clone()
+    /// Get [`ActiveSubscription`]s
+    pub async fn subscriptions(&self) -> HashMap<SubscriptionId, ActiveSubscription> {
+        let subscriptions = self.subscriptions.lock().await;
+        subscriptions
+            .iter()
+            .map(|(id, (_, subscription))| (id.clone(), subscription.clone()))
+            .collect()
+    }
+
+    /// Get [`ActiveSubscription`] by [`SubscriptionId`]
+    pub async fn subscription(&self, id: &SubscriptionId) -> Option<ActiveSubscription> {
+        let subscriptions = self.subscriptions.lock().await;
+        subscriptions
+            .get(id)
+            .map(|(_, subscription)| subscription.clone())
+    }
+
+    /// Update [`ActiveSubscription`] filters, without sending the `REQ` to the relay
+    ///
+    /// The subscription will be sent at the next (re)connection.
+    pub async fn update_subscription_filters(&self, id: SubscriptionId, filters: Vec<Filter>) {
+        let mut subscriptions = self.subscriptions.lock().await;
+        insert_subscription(
+            &mut subscriptions,
+            id.clone(),
+            ActiveSubscription::with_id(id, filters),
+        );
+    }
+
+    /// Check if the relay advertises the support of a NIP (NIP-11)
+    ///
+    /// Return `None` if the information document is not available.
+    pub async fn supports_nip(&self, nip: u16) -> Option<bool> {
+        #[cfg(feature = "nip11")]
+        {
+            let document = self.document().await;
+            document.supported_nips.map(|nips| nips.contains(&nip))
+        }
+
+        #[cfg(not(feature = "nip11"))]
+        {
+            let _ = nip;
+            None
+        }
     }
 
-    /// Update [`ActiveSubscription`]
-    pub async fn update_subscription_filters(&self, filters: Vec<Filter>) {
-        let mut s = self.subscription.lock().await;
-        s.filters = filters;
+    /// Get max number of subscriptions allowed by the relay (NIP-11), if any
+    async fn max_subscriptions(&self) -> Option<usize> {
+        #[cfg(feature = "nip11")]
+        {
+            let document = self.document().await;
+            document
+                .limitation
+                .and_then(|l| l.max_subscriptions)
+                .filter(|max| *max > 0)
+                .map(|max| max as usize)
+        }
+
+        #[cfg(not(feature = "nip11"))]
+        None
     }
 
     /// Get [`RelayOptions`]
@@ -388,11 +775,29 @@ impl Relay {
                 self.try_connect().await
             } else {
                 // Update relay status
-                self.set_status(RelayStatus::Disconnected).await;
+                self.set_status(RelayStatus::Disconnected, None).await;
+            }
+
+            let policy: ReconnectPolicy = self.opts.get_reconnect_policy();
+
+            if !policy.auto_reconnect {
+                if !wait_for_connection {
+                    let relay = self.clone();
+                    thread::spawn(async move { relay.try_connect().await });
+                }
+                return;
             }
 
             let relay = self.clone();
             thread::spawn(async move {
+                // Consecutive failed attempts
+                let mut failures: usize =
+                    if wait_for_connection && relay.status().await == RelayStatus::Disconnected {
+                        1
+                    } else {
+                        0
+                    };
+
                 loop {
                     log::debug!(
                         "{} channel capacity: {}",
@@ -403,7 +808,7 @@ impl Relay {
                     // Schedule relay for termination
                     // Needed to terminate the auto reconnect loop, also if the relay is not connected yet.
                     if relay.is_scheduled_for_termination() {
-                        relay.set_status(RelayStatus::Terminated).await;
+                        relay.set_status(RelayStatus::Terminated, None).await;
                         relay.schedule_for_termination(false);
                         log::debug!("Auto connect loop terminated for {}", relay.url);
                         break;
@@ -411,15 +816,46 @@ impl Relay {
 
                     // Check status
                     match relay.status().await {
-                        RelayStatus::Disconnected => relay.try_connect().await,
+                        RelayStatus::Disconnected => {
+                            if policy.is_exhausted(failures) {
+                                log::warn!(
+                                    "Giving up reconnecting to {} after {failures} failed attempts",
+                                    relay.url
+                                );
+                                relay
+                                    .set_status(
+                                        RelayStatus::Terminated,
+                                        Some(format!("giving up after {failures} failed attempts")),
+                                    )
+                                    .await;
+                                break;
+                            }
+
+                            relay.try_connect().await;
+
+                            if relay.status().await == RelayStatus::Connected {
+                                failures = 0;
+                            } else {
+                                failures += 1;
+                            }
+                        }
                         RelayStatus::Terminated => {
                             log::debug!("Auto connect loop terminated for {}", relay.url);
                             break;
                         }
+                        RelayStatus::Connected => failures = 0,
                         _ => (),
                     };
 
-                    thread::sleep(Duration::from_secs(20)).await;
+                    let delay: Duration = policy.delay(failures);
+                    if failures > 0 {
+                        log::debug!(
+                            "Retrying connection to {} in {} secs (failed attempts: {failures})",
+                            relay.url,
+                            delay.as_secs()
+                        );
+                    }
+                    thread::sleep(delay).await;
                 }
             });
         }
@@ -431,7 +867,7 @@ impl Relay {
         let url: String = self.url.to_string();
 
         // Set RelayStatus to `Connecting`
-        self.set_status(RelayStatus::Connecting).await;
+        self.set_status(RelayStatus::Connecting, None).await;
         log::debug!("Connecting to {}", url);
 
         // Request `RelayInformationDocument`
@@ -463,10 +899,18 @@ impl Relay {
         // Connect
         match connection {
             Ok((mut ws_tx, mut ws_rx)) => {
-                self.set_status(RelayStatus::Connected).await;
+                self.set_status(RelayStatus::Connected, None).await;
                 log::info!("Connected to {}", url);
 
                 self.stats.new_success();
+                self.auth.lock().await.reset();
+                let connection_id: usize = self.stats.success();
+
+                #[cfg(not(target_arch = "wasm32"))]
+                if let Some(interval) = self.opts.get_ping_interval() {
+                    let relay = self.clone();
+                    thread::spawn(async move { relay.keepalive(interval, connection_id).await });
+                }
 
                 let relay = self.clone();
                 thread::spawn(async move {
@@ -495,24 +939,40 @@ impl Relay {
                                     }
                                 }
                             }
-                            RelayEvent::Close => {
+                            RelayEvent::Ping { nonce } => {
+                                #[cfg(not(target_arch = "wasm32"))]
+                                if let Err(e) = ws_tx
+                                    .send(WsMessage::Ping(nonce.to_be_bytes().to_vec()))
+                                    .await
+                                {
+                                    log::error!("Impossible to ping {}: {}", relay.url(), e);
+                                    break;
+                                }
+                                #[cfg(target_arch = "wasm32")]
+                                let _ = nonce;
+                            }
+                            RelayEvent::Close { reason } => {
                                 let _ = ws_tx.close().await;
-                                relay.set_status(RelayStatus::Disconnected).await;
+                                relay.set_status(RelayStatus::Disconnected, reason).await;
                                 log::info!("Disconnected from {}", url);
                                 break;
                             }
                             RelayEvent::Terminate => {
-                                // Unsubscribe from relay
-                                if let Err(e) = relay.unsubscribe(false).await {
-                                    log::error!(
-                                        "Impossible to unsubscribe from {}: {}",
-                                        relay.url(),
-                                        e.to_string()
-                                    )
+                                // Close subscriptions
+                                for id in relay.subscriptions().await.into_keys() {
+                                    let msg = ClientMessage::close(id);
+                                    if let Err(e) = ws_tx.send(WsMessage::Text(msg.as_json())).await
+                                    {
+                                        log::error!(
+                                            "Impossible to close subscription with {}: {}",
+                                            relay.url(),
+                                            e.to_string()
+                                        );
+                                    }
                                 }
                                 // Close stream
                                 let _ = ws_tx.close().await;
-                                relay.set_status(RelayStatus::Terminated).await;
+                                relay.set_status(RelayStatus::Terminated, None).await;
                                 relay.schedule_for_termination(false);
                                 log::info!("Completely disconnected from {}", url);
                                 break;
@@ -530,6 +990,24 @@ impl Relay {
                             Ok(data) => match RelayMessage::from_json(&data) {
                                 Ok(msg) => {
                                     log::trace!("Received message to {}: {:?}", relay.url, msg);
+
+                                    match &msg {
+                                        // Subscription closed by relay
+                                        RelayMessage::Closed {
+                                            subscription_id,
+                                            message,
+                                        } => relay.handle_closed(subscription_id, message).await,
+                                        RelayMessage::Auth { challenge } => {
+                                            relay.handle_auth_challenge(challenge).await
+                                        }
+                                        RelayMessage::Ok {
+                                            event_id,
+                                            status,
+                                            message,
+                                        } => relay.handle_ok(*event_id, *status, message).await,
+                                        _ => (),
+                                    }
+
                                     if let Err(err) = relay
                                         .pool_sender
                                         .send(RelayPoolMessage::ReceivedMsg {
@@ -554,9 +1032,14 @@ impl Relay {
 
                     #[cfg(not(target_arch = "wasm32"))]
                     while let Some(msg_res) = ws_rx.next().await {
-                        if let Ok(msg) = msg_res {
-                            let data: Vec<u8> = msg.into_data();
-                            func(&relay, data).await;
+                        match msg_res {
+                            Ok(WsMessage::Pong(data)) => relay.handle_pong(data).await,
+                            Ok(WsMessage::Ping(..)) => (),
+                            Ok(msg) => {
+                                let data: Vec<u8> = msg.into_data();
+                                func(&relay, data).await;
+                            }
+                            Err(..) => (),
                         }
                     }
 
@@ -568,8 +1051,14 @@ impl Relay {
 
                     log::debug!("Exited from Message Thread of {}", relay.url);
 
-                    if relay.status().await != RelayStatus::Terminated {
-                        if let Err(err) = relay.disconnect().await {
+                    // Don't close a newer connection
+                    if relay.stats.success() == connection_id
+                        && relay.status().await != RelayStatus::Terminated
+                    {
+                        if let Err(err) = relay
+                            .disconnect(Some(String::from("connection closed")))
+                            .await
+                        {
                             log::error!("Impossible to disconnect {}: {}", relay.url, err);
                         }
                     }
@@ -577,25 +1066,77 @@ impl Relay {
 
                 // Subscribe to relay
                 if self.opts.read() {
-                    if let Err(e) = self.resubscribe(false).await {
-                        match e {
-                            Error::FiltersEmpty => log::debug!("Filters empty for {}", self.url()),
-                            _ => log::error!(
-                                "Impossible to subscribe to {}: {}",
-                                self.url(),
-                                e.to_string()
-                            ),
-                        }
-                    }
+                    self.resubscribe_all(false).await;
                 }
             }
             Err(err) => {
-                self.set_status(RelayStatus::Disconnected).await;
+                self.set_status(RelayStatus::Disconnected, Some(err.to_string()))
+                    .await;
                 log::error!("Impossible to connect to {}: {}", url, err);
             }
         };
     }
 
+    /// Periodically ping the relay and disconnect it if the pong is not received in time
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn keepalive(&self, interval: Duration, connection_id: usize) {
+        let pong_timeout: Duration = self.opts.get_pong_timeout();
+        loop {
+            thread::sleep(interval).await;
+
+            // Stop when the connection is closed or replaced by a new one
+            if self.stats.success() != connection_id
+                || self.status().await != RelayStatus::Connected
+            {
+                break;
+            }
+
+            let nonce: u64 = self.ping.new_ping().await;
+            log::trace!("Ping {} (nonce {nonce})", self.url);
+            if let Err(e) = self
+                .send_relay_event(RelayEvent::Ping { nonce }, None)
+                .await
+            {
+                log::error!("Impossible to ping {}: {}", self.url, e);
+                break;
+            }
+
+            thread::sleep(pong_timeout).await;
+
+            if self.stats.success() != connection_id {
+                break;
+            }
+
+            if !self.ping.is_replied() {
+                log::warn!(
+                    "{} not replied to ping in {} secs: connection is stale",
+                    self.url,
+                    pong_timeout.as_secs()
+                );
+                if let Err(e) = self
+                    .disconnect(Some(String::from("pong timeout: connection is stale")))
+                    .await
+                {
+                    log::error!("Impossible to disconnect {}: {}", self.url, e);
+                }
+                break;
+            }
+        }
+        log::debug!("Keepalive loop terminated for {}", self.url);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn handle_pong(&self, data: Vec<u8>) {
+        let nonce: u64 = match <[u8; 8]>::try_from(data.as_slice()) {
+            Ok(bytes) => u64::from_be_bytes(bytes),
+            Err(..) => return,
+        };
+        if let Some(latency) = self.ping.pong(nonce).await {
+            log::trace!("Pong from {} in {} ms", self.url, latency.as_millis());
+            self.stats.save_latency(latency);
+        }
+    }
+
     async fn send_relay_event(
         &self,
         relay_msg: RelayEvent,
@@ -613,10 +1154,11 @@ impl Relay {
     }
 
     /// Disconnect from relay and set status to 'Disconnected'
-    async fn disconnect(&self) -> Result<(), Error> {
+    async fn disconnect(&self, reason: Option<String>) -> Result<(), Error> {
         let status = self.status().await;
         if status.ne(&RelayStatus::Disconnected) && status.ne(&RelayStatus::Terminated) {
-            self.send_relay_event(RelayEvent::Close, None).await?;
+            self.send_relay_event(RelayEvent::Close { reason }, None)
+                .await?;
         }
         Ok(())
     }
@@ -647,6 +1189,12 @@ impl Relay {
             }
         }
 
+        // Keep the event until the `OK`, to retry it if the relay requires authentication
+        if let ClientMessage::Event(event) = &msg {
+            let mut auth = self.auth.lock().await;
+            auth.event_sent(event.as_ref().clone());
+        }
+
         if wait {
             let (tx, rx) = oneshot::channel::<bool>();
             self.send_relay_event(RelayEvent::SendMsg(Box::new(msg)), Some(tx))
@@ -670,51 +1218,261 @@ impl Relay {
         }
     }
 
-    /// Subscribes relay with existing filter
-    async fn resubscribe(&self, wait: bool) -> Result<SubscriptionId, Error> {
-        if !self.opts.read() {
-            return Err(Error::ReadDisabled);
+    /// Handle subscription closed by relay
+    async fn handle_closed(&self, id: &SubscriptionId, message: &str) {
+        match MachineReadablePrefix::parse(message) {
+            // Keep the subscription, to retry after authentication
+            Some(MachineReadablePrefix::AuthRequired) => {
+                log::warn!("Subscription {id} requires authentication on {}", self.url);
+                let mut auth = self.auth.lock().await;
+                auth.subscription_rejected(id.clone());
+            }
+            _ => {
+                log::warn!("Subscription {id} closed by {}: {message}", self.url);
+                let mut subscriptions = self.subscriptions.lock().await;
+                subscriptions.remove(id);
+            }
+        }
+    }
+
+    /// Send `REQ` for all the [`ActiveSubscription`]s
+    ///
+    /// If the relay has a `max_subscriptions` limitation (NIP-11), the oldest subscriptions are kept.
+    async fn resubscribe_all(&self, wait: bool) {
+        let subscriptions = self.subscriptions.lock().await.clone();
+        let max_subscriptions: Option<usize> = self.max_subscriptions().await;
+
+        for (index, (_, subscription)) in
+            sorted_subscriptions(subscriptions).into_iter().enumerate()
+        {
+            if let Some(max) = max_subscriptions {
+                if index >= max {
+                    log::warn!(
+                        "Skipped subscription {} for {}: {}",
+                        subscription.id,
+                        self.url(),
+                        Error::MaxSubscriptionsReached(max)
+                    );
+                    continue;
+                }
+            }
+
+            if subscription.filters.is_empty() {
+                log::debug!("Filters empty for subscription {}", subscription.id);
+                continue;
+            }
+
+            if let Err(e) = self
+                .send_msg(
+                    ClientMessage::new_req(subscription.id.clone(), subscription.filters),
+                    wait,
+                )
+                .await
+            {
+                log::error!(
+                    "Impossible to subscribe {} to {}: {}",
+                    subscription.id,
+                    self.url(),
+                    e.to_string()
+                );
+            }
         }
-        let subscription = self.subscription().await;
+    }
 
-        if subscription.filters.is_empty() {
-            return Err(Error::FiltersEmpty);
+    /// Send [`Event`] and wait for the relay `OK` response
+    pub async fn publish_event(
+        &self,
+        event: Event,
+        timeout: Duration,
+        wait: bool,
+    ) -> PublishStatus {
+        let id: EventId = event.id;
+
+        // Listen for notifications before sending, to not miss the `OK`
+        let notifications = self.notification_sender.subscribe();
+
+        if let Err(e) = self.send_msg(ClientMessage::new_event(event), wait).await {
+            return PublishStatus::NotSent(e.to_string());
         }
 
-        self.send_msg(
-            ClientMessage::new_req(subscription.id.clone(), subscription.filters),
-            wait,
-        )
-        .await?;
-        Ok(subscription.id)
+        self.wait_for_ok(notifications, id, timeout).await
     }
 
-    /// Subscribe
+    /// Wait for the `OK` of an [`Event`]
+    ///
+    /// If the event is rejected with `auth-required`, wait for the retry after authentication.
+    async fn wait_for_ok(
+        &self,
+        mut notifications: broadcast::Receiver<RelayPoolNotification>,
+        id: EventId,
+        timeout: Duration,
+    ) -> PublishStatus {
+        let mut auth_required: Option<String> = None;
+        let status: Option<PublishStatus> = time::timeout(Some(timeout), async {
+            loop {
+                match notifications.recv().await {
+                    Ok(RelayPoolNotification::Message(
+                        url,
+                        RelayMessage::Ok {
+                            event_id,
+                            status,
+                            message,
+                        },
+                    )) => {
+                        if url == self.url && event_id == id {
+                            if status {
+                                break PublishStatus::Accepted;
+                            } else if MachineReadablePrefix::parse(&message)
+                                == Some(MachineReadablePrefix::AuthRequired)
+                                && auth_required.is_none()
+                            {
+                                auth_required = Some(message);
+                            } else {
+                                break PublishStatus::Rejected(message);
+                            }
+                        }
+                    }
+                    Ok(RelayPoolNotification::Shutdown) | Err(RecvError::Closed) => {
+                        break PublishStatus::NotSent(String::from("relay pool shutdown"));
+                    }
+                    Ok(_) | Err(RecvError::Lagged(_)) => (),
+                }
+            }
+        })
+        .await;
+
+        match (status, auth_required) {
+            (Some(status), _) => status,
+            (None, Some(message)) => PublishStatus::Rejected(message),
+            (None, None) => PublishStatus::Timeout,
+        }
+    }
+
+    /// Subscribe to filters
+    ///
+    /// Every call replaces the filters of the default subscription:
+    /// use [`Relay::subscribe_with_id`] to open other subscriptions.
     pub async fn subscribe(
         &self,
         filters: Vec<Filter>,
         wait: bool,
     ) -> Result<SubscriptionId, Error> {
+        let id = self.default_subscription_id.clone();
+        self.subscribe_with_id(id.clone(), filters, wait).await?;
+        Ok(id)
+    }
+
+    /// Subscribe to filters with custom [`SubscriptionId`]
+    ///
+    /// If a subscription with the same [`SubscriptionId`] already exists, its filters are replaced.
+    pub async fn subscribe_with_id(
+        &self,
+        id: SubscriptionId,
+        filters: Vec<Filter>,
+        wait: bool,
+    ) -> Result<(), Error> {
         if !self.opts.read() {
             return Err(Error::ReadDisabled);
         }
 
-        self.update_subscription_filters(filters).await;
-        self.resubscribe(wait).await
+        if filters.is_empty() {
+            return Err(Error::FiltersEmpty);
+        }
+
+        {
+            let mut subscriptions = self.subscriptions.lock().await;
+            if !subscriptions.contains_key(&id) {
+                if let Some(max) = self.max_subscriptions().await {
+                    if subscriptions.len() >= max {
+                        return Err(Error::MaxSubscriptionsReached(max));
+                    }
+                }
+            }
+            insert_subscription(
+                &mut subscriptions,
+                id.clone(),
+                ActiveSubscription::with_id(id.clone(), filters.clone()),
+            );
+        }
+
+        self.send_msg(ClientMessage::new_req(id, filters), wait)
+            .await
     }
 
     /// Unsubscribe
-    pub async fn unsubscribe(&self, wait: bool) -> Result<(), Error> {
+    pub async fn unsubscribe(&self, id: SubscriptionId, wait: bool) -> Result<(), Error> {
         if !self.opts.read() {
             return Err(Error::ReadDisabled);
         }
 
-        let subscription = self.subscription().await;
-        self.send_msg(ClientMessage::close(subscription.id), wait)
-            .await?;
+        let mut subscriptions = self.subscriptions.lock().await;
+        if subscriptions.remove(&id).is_none() {
+            return Err(Error::SubscriptionNotFound);
+        }
+        drop(subscriptions);
+
+        self.send_msg(ClientMessage::close(id), wait).await
+    }
+
+    /// Unsubscribe from all subscriptions
+    pub async fn unsubscribe_all(&self, wait: bool) -> Result<(), Error> {
+        if !self.opts.read() {
+            return Err(Error::ReadDisabled);
+        }
+
+        let subscriptions = self.subscriptions().await;
+        for id in subscriptions.into_keys() {
+            self.unsubscribe(id, wait).await?;
+        }
+
         Ok(())
     }
 
+    /// Count events of filters
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/45.md>
+    pub async fn count_events_of(
+        &self,
+        filters: Vec<Filter>,
+        timeout: Option<Duration>,
+    ) -> Result<usize, Error> {
+        if !self.opts.read() {
+            return Err(Error::ReadDisabled);
+        }
+
+        let id = SubscriptionId::generate();
+
+        // Listen for notifications before sending, to not miss the response
+        let mut notifications = self.notification_sender.subscribe();
+
+        self.send_msg(ClientMessage::new_count(id.clone(), filters), true)
+            .await?;
+
+        time::timeout(timeout, async {
+            loop {
+                match notifications.recv().await {
+                    Ok(RelayPoolNotification::Message(url, msg)) if url == self.url => match msg {
+                        RelayMessage::Count {
+                            subscription_id,
+                            count,
+                        } if subscription_id == id => break Ok(count),
+                        RelayMessage::Closed {
+                            subscription_id,
+                            message,
+                        } if subscription_id == id => break Err(Error::RequestClosed(message)),
+                        _ => (),
+                    },
+                    Ok(RelayPoolNotification::Shutdown) | Err(RecvError::Closed) => {
+                        break Err(Error::MessagetNotSent)
+                    }
+                    Ok(_) | Err(RecvError::Lagged(_)) => (),
+                }
+            }
+        })
+        .await
+        .ok_or(Error::Timeout)?
+    }
+
     /// Get events of filters with custom callback
     pub async fn get_events_of_with_callback<F>(
         &self,
@@ -747,7 +1505,10 @@ impl Relay {
                                 callback(*event).await;
                             }
                         }
-                        RelayMessage::EndOfStoredEvents(subscription_id) => {
+                        RelayMessage::EndOfStoredEvents(subscription_id)
+                        | RelayMessage::Closed {
+                            subscription_id, ..
+                        } => {
                             if subscription_id.eq(&id) {
                                 break;
                             }
@@ -808,7 +1569,10 @@ impl Relay {
                 while let Ok(notification) = notifications.recv().await {
                     if let RelayPoolNotification::Message(
                         _,
-                        RelayMessage::EndOfStoredEvents(subscription_id),
+                        RelayMessage::EndOfStoredEvents(subscription_id)
+                        | RelayMessage::Closed {
+                            subscription_id, ..
+                        },
                     ) = notification
                     {
                         if subscription_id.eq(&id) {
@@ -834,3 +1598,120 @@ impl Relay {
         });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subscriptions_order() {
+        let ids: Vec<SubscriptionId> = (0..10)
+            .map(|i| SubscriptionId::new(format!("sub-{i}")))
+            .collect();
+        let mut subscriptions: OrderedSubscriptions<usize> = HashMap::new();
+        for (i, id) in ids.iter().enumerate() {
+            insert_subscription(&mut subscriptions, id.clone(), i);
+        }
+
+        // Replaced subscriptions keep their position
+        insert_subscription(&mut subscriptions, ids[0].clone(), 42);
+
+        let sorted = sorted_subscriptions(subscriptions);
+        assert_eq!(
+            sorted.iter().map(|(id, _)| id.clone()).collect::<Vec<_>>(),
+            ids
+        );
+        assert_eq!(sorted[0].1, 42);
+    }
+
+    #[test]
+    fn test_reconnect_policy_delay() {
+        let policy = ReconnectPolicy::new()
+            .initial_delay(Duration::from_secs(1))
+            .multiplier(2.0)
+            .max_delay(Duration::from_secs(10))
+            .jitter(0.0);
+
+        assert_eq!(policy.base_delay(0), Duration::from_secs(1));
+        assert_eq!(policy.base_delay(1), Duration::from_secs(1));
+        assert_eq!(policy.base_delay(2), Duration::from_secs(2));
+        assert_eq!(policy.base_delay(3), Duration::from_secs(4));
+        assert_eq!(policy.base_delay(5), Duration::from_secs(10));
+        assert_eq!(policy.base_delay(usize::MAX), Duration::from_secs(10));
+        assert_eq!(policy.delay(3), Duration::from_secs(4));
+
+        let policy = policy.jitter(0.5);
+        for _ in 0..100 {
+            let delay = policy.delay(3);
+            assert!(delay >= Duration::from_secs(2) && delay <= Duration::from_secs(6));
+        }
+
+        let policy = policy.max_attempts(Some(3));
+        assert!(!policy.is_exhausted(2));
+        assert!(policy.is_exhausted(3));
+        assert!(!ReconnectPolicy::new().is_exhausted(usize::MAX));
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_relay_status_notification() {
+        let (pool_sender, _pool_receiver) = mpsc::channel(16);
+        let (notification_sender, mut notifications) = broadcast::channel(16);
+        let url = Url::parse("wss://relay.example.com").unwrap();
+        let relay = Relay::new(
+            url.clone(),
+            pool_sender,
+            notification_sender,
+            None,
+            RelayOptions::default(),
+        );
+
+        relay
+            .set_status(RelayStatus::Disconnected, Some(String::from("test")))
+            .await;
+        // Unchanged status
+        relay.set_status(RelayStatus::Disconnected, None).await;
+        relay.set_status(RelayStatus::Terminated, None).await;
+
+        match notifications.recv().await.unwrap() {
+            RelayPoolNotification::RelayStatus {
+                relay_url,
+                status,
+                reason,
+                attempts,
+            } => {
+                assert_eq!(relay_url, url);
+                assert_eq!(status, RelayStatus::Disconnected);
+                assert_eq!(reason, Some(String::from("test")));
+                assert_eq!(attempts, 0);
+            }
+            n => panic!("unexpected notification: {n:?}"),
+        }
+        assert!(matches!(
+            notifications.recv().await.unwrap(),
+            RelayPoolNotification::RelayStatus {
+                status: RelayStatus::Terminated,
+                ..
+            }
+        ));
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_ping_tracker() {
+        let ping = PingTracker::default();
+        let first: u64 = ping.new_ping().await;
+        let second: u64 = ping.new_ping().await;
+        assert!(!ping.is_replied());
+
+        // Pong of an old ping
+        assert!(ping.pong(first).await.is_none());
+        assert!(!ping.is_replied());
+
+        assert!(ping.pong(second).await.is_some());
+        assert!(ping.is_replied());
+
+        // Duplicated pong
+        assert!(ping.pong(second).await.is_none());
+    }
+}
@@ -11,14 +11,22 @@ use std::path::Path;
 use std::sync::Arc;
 use std::time::Duration;
 
+use nostr::secp256k1::XOnlyPublicKey;
 use nostr::url::Url;
-use nostr::{ClientMessage, Event, EventId, Filter, RelayMessage};
+use nostr::{
+    ClientMessage, Event, EventId, Filter, Kind, RelayMessage, SubscriptionId, Tag, Timestamp,
+};
 #[cfg(feature = "sqlite")]
 use nostr_sdk_sqlite::Store;
-use tokio::sync::mpsc::{self, Receiver, Sender};
+use tokio::sync::mpsc::{self, Receiver, Sender, UnboundedSender};
 use tokio::sync::{broadcast, Mutex};
 
-use super::{Error as RelayError, Relay, RelayOptions};
+use super::stream::EventStream;
+use super::{
+    insert_subscription, sorted_subscriptions, Error as RelayError, OrderedSubscriptions,
+    PublishStatus, Relay, RelayOptions, RelayStatus,
+};
+use crate::database::{DatabaseError, NostrDatabase, SaveEventStatus};
 use crate::thread;
 
 /// [`RelayPool`] error
@@ -36,6 +44,9 @@ pub enum Error {
     /// Thread error
     #[error(transparent)]
     Thread(#[from] thread::Error),
+    /// Database error
+    #[error(transparent)]
+    Database(#[from] DatabaseError),
     /// Store error
     #[cfg(feature = "sqlite")]
     #[error(transparent)]
@@ -58,6 +69,8 @@ pub enum RelayPoolMessage {
     },
     /// Event sent
     EventSent(Box<Event>),
+    /// [`EventStream`] dropped: close its subscription
+    CloseStream(SubscriptionId),
     /// Shutdown
     Shutdown,
 }
@@ -69,45 +82,59 @@ pub enum RelayPoolNotification {
     Event(Url, Event),
     /// Received a [`RelayMessage`]
     Message(Url, RelayMessage),
+    /// Relay status changed
+    RelayStatus {
+        /// Relay url
+        relay_url: Url,
+        /// New relay status
+        status: RelayStatus,
+        /// Reason of the change, if known (ex. the disconnection cause)
+        reason: Option<String>,
+        /// Total connection attempts to the relay
+        attempts: usize,
+    },
     /// Shutdown
     Shutdown,
 }
 
+type StreamSenders = Arc<Mutex<HashMap<SubscriptionId, UnboundedSender<(Url, RelayMessage)>>>>;
+
 struct RelayPoolTask {
     receiver: Receiver<RelayPoolMessage>,
     notification_sender: broadcast::Sender<RelayPoolNotification>,
+    relays: Arc<Mutex<HashMap<Url, Relay>>>,
+    subscriptions: Arc<Mutex<OrderedSubscriptions<Vec<Filter>>>>,
     events: VecDeque<EventId>,
-    #[cfg(feature = "sqlite")]
-    store: Option<Store>,
+    database: Option<Arc<dyn NostrDatabase>>,
+    /// Events deleted by their author (NIP-09), tracked only if there isn't a database
+    deleted_ids: VecDeque<(EventId, XOnlyPublicKey)>,
+    /// Coordinates deleted by their author (NIP-09), with the deletion timestamp
+    deleted_coordinates: VecDeque<(Kind, XOnlyPublicKey, String, Timestamp)>,
+    streams: StreamSenders,
 }
 
 const MAX_EVENTS: usize = 100000;
+const PURGE_EXPIRED_INTERVAL: Duration = Duration::from_secs(60);
 
 impl RelayPoolTask {
     pub fn new(
         pool_task_receiver: Receiver<RelayPoolMessage>,
         notification_sender: broadcast::Sender<RelayPoolNotification>,
+        relays: Arc<Mutex<HashMap<Url, Relay>>>,
+        subscriptions: Arc<Mutex<OrderedSubscriptions<Vec<Filter>>>>,
+        database: Option<Arc<dyn NostrDatabase>>,
+        streams: StreamSenders,
     ) -> Self {
         Self {
             receiver: pool_task_receiver,
             events: VecDeque::new(),
             notification_sender,
-            #[cfg(feature = "sqlite")]
-            store: None,
-        }
-    }
-
-    #[cfg(feature = "sqlite")]
-    pub fn with_store(
-        pool_task_receiver: Receiver<RelayPoolMessage>,
-        notification_sender: broadcast::Sender<RelayPoolNotification>,
-        store: Option<Store>,
-    ) -> Self {
-        Self {
-            receiver: pool_task_receiver,
-            events: VecDeque::new(),
-            notification_sender,
-            store,
+            relays,
+            subscriptions,
+            database,
+            deleted_ids: VecDeque::new(),
+            deleted_coordinates: VecDeque::new(),
+            streams,
         }
     }
 
@@ -116,6 +143,13 @@ impl RelayPoolTask {
         while let Some(msg) = self.receiver.recv().await {
             match msg {
                 RelayPoolMessage::ReceivedMsg { relay_url, msg } => {
+                    // Discard invalid, expired, deleted and rejected events
+                    if let RelayMessage::Event { event, .. } = &msg {
+                        if !self.handle_event(&relay_url, event).await {
+                            continue;
+                        }
+                    }
+
                     let _ = self
                         .notification_sender
                         .send(RelayPoolNotification::Message(
@@ -123,33 +157,45 @@ impl RelayPoolTask {
                             msg.clone(),
                         ));
 
-                    if let RelayMessage::Event { event, .. } = msg {
-                        // Verifies if the event is valid
-                        if event.verify().is_ok() {
-                            // Adds only new events
-                            if !self.events.contains(&event.id) {
-                                self.add_event(event.id);
-                                let notification =
-                                    RelayPoolNotification::Event(relay_url, event.as_ref().clone());
-                                let _ = self.notification_sender.send(notification);
+                    // Forward to event stream
+                    if let RelayMessage::Event {
+                        subscription_id, ..
+                    }
+                    | RelayMessage::EndOfStoredEvents(subscription_id)
+                    | RelayMessage::Closed {
+                        subscription_id, ..
+                    } = &msg
+                    {
+                        let mut streams = self.streams.lock().await;
+                        if let Some(sender) = streams.get(subscription_id) {
+                            if sender.send((relay_url.clone(), msg.clone())).is_err() {
+                                streams.remove(subscription_id);
                             }
+                        }
+                    }
 
-                            // Save event into store
-                            #[cfg(feature = "sqlite")]
-                            if let Some(store) = &self.store {
-                                match store.insert_event(*event) {
-                                    Ok(_) => log::trace!("Event saved into store"),
-                                    Err(e) => {
-                                        log::error!("Imposible to insert event into store: {e}")
-                                    }
-                                }
-                            }
+                    // Adds only new events
+                    if let RelayMessage::Event { event, .. } = msg {
+                        if !self.events.contains(&event.id) {
+                            self.add_event(event.id);
+                            let notification = RelayPoolNotification::Event(relay_url, *event);
+                            let _ = self.notification_sender.send(notification);
                         }
                     }
                 }
                 RelayPoolMessage::EventSent(event) => {
                     self.add_event(event.id);
                 }
+                RelayPoolMessage::CloseStream(id) => {
+                    self.streams.lock().await.remove(&id);
+                    self.subscriptions.lock().await.remove(&id);
+                    let relays = self.relays.lock().await.clone();
+                    for relay in relays.values() {
+                        if let Err(e) = relay.unsubscribe(id.clone(), false).await {
+                            log::error!("Impossible to unsubscribe from {}: {e}", relay.url());
+                        }
+                    }
+                }
                 RelayPoolMessage::Shutdown => {
                     if let Err(e) = self
                         .notification_sender
@@ -165,12 +211,181 @@ impl RelayPoolTask {
         }
     }
 
+    /// Verify the received [`Event`] and save it into the database
+    ///
+    /// Return `false` if the event is invalid, expired, deleted by its author (NIP-09)
+    /// or rejected by the database.
+    async fn handle_event(&mut self, relay_url: &Url, event: &Event) -> bool {
+        // Skip expired events
+        if event.is_expired() {
+            log::trace!("Received expired event {}", event.id);
+            return false;
+        }
+
+        // Verifies if the event is valid
+        if event.verify().is_err() {
+            log::warn!("Received invalid event {} from {relay_url}", event.id);
+            return false;
+        }
+
+        match &self.database {
+            Some(database) => {
+                match database.save_event(event).await {
+                    Ok(SaveEventStatus::Rejected) => {
+                        log::trace!("Event {} rejected by database", event.id);
+                        return false;
+                    }
+                    Ok(_) => log::trace!("Event saved into database"),
+                    Err(e) => log::error!("Imposible to save event into database: {e}"),
+                }
+                if let Err(e) = database.event_id_seen(event.id, relay_url.clone()).await {
+                    log::error!("Impossible to set event as seen by relay: {e}");
+                }
+            }
+            None => {
+                if self.is_deleted(event) {
+                    log::trace!("Received deleted event {}", event.id);
+                    return false;
+                }
+                if event.kind == Kind::EventDeletion {
+                    self.add_deletion(event);
+                }
+            }
+        }
+
+        true
+    }
+
     fn add_event(&mut self, event_id: EventId) {
         while self.events.len() >= MAX_EVENTS {
             self.events.pop_front();
         }
         self.events.push_back(event_id);
     }
+
+    /// Check if the event has been deleted by its author (NIP-09)
+    ///
+    /// Replaceable and parameterized replaceable events are deleted by coordinate
+    /// if not newer than the deletion.
+    fn is_deleted(&self, event: &Event) -> bool {
+        if self.deleted_ids.contains(&(event.id, event.pubkey)) {
+            return true;
+        }
+
+        if event.kind.is_replaceable() || event.kind.is_parameterized_replaceable() {
+            let identifier: &str = if event.kind.is_parameterized_replaceable() {
+                event.identifier().unwrap_or_default()
+            } else {
+                ""
+            };
+            return self.deleted_coordinates.iter().any(
+                |(kind, public_key, deleted_identifier, created_at)| {
+                    *kind == event.kind
+                        && *public_key == event.pubkey
+                        && deleted_identifier == identifier
+                        && *created_at >= event.created_at
+                },
+            );
+        }
+
+        false
+    }
+
+    /// Record the event IDs and coordinates referenced by the deletion (NIP-09)
+    ///
+    /// Only the coordinates of the deletion author are recorded.
+    fn add_deletion(&mut self, deletion: &Event) {
+        for tag in deletion.tags.iter() {
+            match tag {
+                Tag::Event(event_id, ..) => {
+                    while self.deleted_ids.len() >= MAX_EVENTS {
+                        self.deleted_ids.pop_front();
+                    }
+                    self.deleted_ids.push_back((*event_id, deletion.pubkey));
+                }
+                Tag::A {
+                    kind,
+                    public_key,
+                    identifier,
+                    ..
+                } if *public_key == deletion.pubkey => {
+                    let identifier: String = if kind.is_parameterized_replaceable() {
+                        identifier.clone()
+                    } else {
+                        String::new()
+                    };
+                    while self.deleted_coordinates.len() >= MAX_EVENTS {
+                        self.deleted_coordinates.pop_front();
+                    }
+                    self.deleted_coordinates.push_back((
+                        *kind,
+                        *public_key,
+                        identifier,
+                        deletion.created_at,
+                    ));
+                }
+                _ => (),
+            }
+        }
+    }
+}
+
+/// Output of [`RelayPool::publish_event`]
+#[derive(Debug, Clone)]
+pub struct PublishOutput {
+    /// Published [`EventId`]
+    pub event_id: EventId,
+    /// Outcome for each relay
+    pub relays: HashMap<Url, PublishStatus>,
+}
+
+impl PublishOutput {
+    /// Get relays that have accepted the event
+    pub fn accepted(&self) -> Vec<Url> {
+        self.relays
+            .iter()
+            .filter(|(_, status)| status.is_accepted())
+            .map(|(url, _)| url.clone())
+            .collect()
+    }
+
+    /// Get relays that have rejected the event, with reason
+    pub fn rejected(&self) -> HashMap<Url, String> {
+        self.relays
+            .iter()
+            .filter_map(|(url, status)| match status {
+                PublishStatus::Rejected(reason) => Some((url.clone(), reason.clone())),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// Output of [`RelayPool::count_events_of`]
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/45.md>
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CountOutput {
+    /// Count returned by each relay
+    pub relays: HashMap<Url, usize>,
+    /// Count from the local database, used when no relay returned a count
+    pub local: Option<usize>,
+    /// Aggregate count
+    ///
+    /// The max of the relay counts, since the events can't be deduplicated across relays,
+    /// or the local count as fallback.
+    pub count: usize,
+}
+
+impl CountOutput {
+    fn new(relays: HashMap<Url, usize>, local: Option<usize>) -> Self {
+        let count: usize = relays.values().copied().max().or(local).unwrap_or_default();
+        Self {
+            relays,
+            local,
+            count,
+        }
+    }
 }
 
 /// Relay Pool
@@ -179,7 +394,10 @@ pub struct RelayPool {
     relays: Arc<Mutex<HashMap<Url, Relay>>>,
     pool_task_sender: Sender<RelayPoolMessage>,
     notification_sender: broadcast::Sender<RelayPoolNotification>,
-    filters: Arc<Mutex<Vec<Filter>>>,
+    subscriptions: Arc<Mutex<OrderedSubscriptions<Vec<Filter>>>>,
+    default_subscription_id: SubscriptionId,
+    streams: StreamSenders,
+    database: Option<Arc<dyn NostrDatabase>>,
     #[cfg(feature = "sqlite")]
     store: Option<Store>,
 }
@@ -193,22 +411,20 @@ impl Default for RelayPool {
 impl RelayPool {
     /// Create new `RelayPool`
     pub fn new() -> Self {
-        let (notification_sender, _) = broadcast::channel(1024);
-        let (pool_task_sender, pool_task_receiver) = mpsc::channel(1024);
-
-        let mut relay_pool_task =
-            RelayPoolTask::new(pool_task_receiver, notification_sender.clone());
-
-        thread::spawn(async move { relay_pool_task.run().await });
+        Self::build(
+            None,
+            #[cfg(feature = "sqlite")]
+            None,
+        )
+    }
 
-        Self {
-            relays: Arc::new(Mutex::new(HashMap::new())),
-            pool_task_sender,
-            notification_sender,
-            filters: Arc::new(Mutex::new(Vec::new())),
+    /// Create new `RelayPool` with custom [`NostrDatabase`]
+    pub fn with_database(database: Arc<dyn NostrDatabase>) -> Self {
+        Self::build(
+            Some(database),
             #[cfg(feature = "sqlite")]
-            store: None,
-        }
+            None,
+        )
     }
 
     /// Create new `RelayPool`
@@ -217,27 +433,63 @@ impl RelayPool {
     where
         P: AsRef<Path>,
     {
+        let store = Store::open(path)?;
+        Ok(Self::build(Some(Arc::new(store.clone())), Some(store)))
+    }
+
+    fn build(
+        database: Option<Arc<dyn NostrDatabase>>,
+        #[cfg(feature = "sqlite")] store: Option<Store>,
+    ) -> Self {
         let (notification_sender, _) = broadcast::channel(1024);
         let (pool_task_sender, pool_task_receiver) = mpsc::channel(1024);
 
-        let store = Some(Store::open(path)?);
+        let relays = Arc::new(Mutex::new(HashMap::new()));
+        let subscriptions = Arc::new(Mutex::new(HashMap::new()));
+        let streams: StreamSenders = Arc::new(Mutex::new(HashMap::new()));
 
-        let mut relay_pool_task = RelayPoolTask::with_store(
+        let mut relay_pool_task = RelayPoolTask::new(
             pool_task_receiver,
             notification_sender.clone(),
-            store.clone(),
+            relays.clone(),
+            subscriptions.clone(),
+            database.clone(),
+            streams.clone(),
         );
 
         thread::spawn(async move { relay_pool_task.run().await });
 
-        Ok(Self {
-            relays: Arc::new(Mutex::new(HashMap::new())),
+        // Periodically purge expired events from database
+        if let Some(database) = database.clone() {
+            let pool_task_sender = pool_task_sender.downgrade();
+            thread::spawn(async move {
+                loop {
+                    thread::sleep(PURGE_EXPIRED_INTERVAL).await;
+                    match pool_task_sender.upgrade() {
+                        Some(sender) if !sender.is_closed() => (),
+                        _ => break,
+                    }
+                    match database.purge_expired().await {
+                        Ok(0) => (),
+                        Ok(n) => log::debug!("Purged {n} expired events from database"),
+                        Err(e) => log::error!("Impossible to purge expired events: {e}"),
+                    }
+                }
+                log::debug!("Exited from purge expired events thread");
+            });
+        }
+
+        Self {
+            relays,
             pool_task_sender,
             notification_sender,
-            filters: Arc::new(Mutex::new(Vec::new())),
+            subscriptions,
+            default_subscription_id: SubscriptionId::generate(),
+            streams,
+            database,
             #[cfg(feature = "sqlite")]
             store,
-        })
+        }
     }
 
     /// Get new notification listener
@@ -251,21 +503,30 @@ impl RelayPool {
         relays.clone()
     }
 
+    /// Get [`Relay`]
+    pub async fn relay(&self, url: &Url) -> Result<Relay, Error> {
+        let relays = self.relays.lock().await;
+        relays.get(url).cloned().ok_or(Error::RelayNotFound)
+    }
+
+    /// Get [`NostrDatabase`]
+    pub fn database(&self) -> Option<Arc<dyn NostrDatabase>> {
+        self.database.clone()
+    }
+
     /// Get [`Store`]
     #[cfg(feature = "sqlite")]
     pub fn store(&self) -> Option<Store> {
         self.store.clone()
     }
 
-    /// Get subscription filters
-    pub async fn subscription_filters(&self) -> Vec<Filter> {
-        self.filters.lock().await.clone()
-    }
-
-    /// Update subscription filters
-    async fn update_subscription_filters(&self, filters: Vec<Filter>) {
-        let mut f = self.filters.lock().await;
-        *f = filters;
+    /// Get subscriptions
+    pub async fn subscriptions(&self) -> HashMap<SubscriptionId, Vec<Filter>> {
+        let subscriptions = self.subscriptions.lock().await;
+        subscriptions
+            .iter()
+            .map(|(id, (_, filters))| (id.clone(), filters.clone()))
+            .collect()
     }
 
     /// Add new relay
@@ -378,27 +639,130 @@ impl RelayPool {
         }
     }
 
+    /// Send [`Event`] to all relays and wait for their `OK` responses
+    ///
+    /// If `min_accepted` is set, return as soon as that number of relays have accepted the event
+    /// (the relays that haven't answered yet are not included in the output).
+    pub async fn publish_event(
+        &self,
+        event: Event,
+        timeout: Duration,
+        min_accepted: Option<usize>,
+        wait: bool,
+    ) -> Result<PublishOutput, Error> {
+        let relays = self.relays().await;
+
+        if relays.is_empty() {
+            return Err(Error::NoRelayConnected);
+        }
+
+        if let Err(e) = self
+            .pool_task_sender
+            .send(RelayPoolMessage::EventSent(Box::new(event.clone())))
+            .await
+        {
+            log::error!("{e}");
+        };
+
+        let (tx, mut rx) = mpsc::channel(relays.len());
+        for (url, relay) in relays.into_iter() {
+            let event = event.clone();
+            let tx = tx.clone();
+            thread::spawn(async move {
+                let status = relay.publish_event(event, timeout, wait).await;
+                let _ = tx.send((url, status)).await;
+            });
+        }
+        drop(tx);
+
+        let mut output = PublishOutput {
+            event_id: event.id,
+            relays: HashMap::new(),
+        };
+        while let Some((url, status)) = rx.recv().await {
+            match &status {
+                PublishStatus::Accepted => log::debug!("Event {} accepted by {url}", event.id),
+                PublishStatus::Rejected(reason) => {
+                    log::warn!("Event {} rejected by {url}: {reason}", event.id)
+                }
+                PublishStatus::Timeout => log::warn!("Timeout waiting OK from {url}"),
+                PublishStatus::NotSent(e) => log::error!("Impossible to send event to {url}: {e}"),
+            }
+            output.relays.insert(url, status);
+
+            if let Some(min_accepted) = min_accepted {
+                if output.accepted().len() >= min_accepted {
+                    break;
+                }
+            }
+        }
+
+        Ok(output)
+    }
+
     /// Subscribe to filters
-    pub async fn subscribe(&self, filters: Vec<Filter>, wait: bool) {
+    ///
+    /// Every call replaces the filters of the default subscription:
+    /// use [`RelayPool::subscribe_with_id`] to open other subscriptions.
+    pub async fn subscribe(&self, filters: Vec<Filter>, wait: bool) -> SubscriptionId {
+        let id = self.default_subscription_id.clone();
+        self.subscribe_with_id(id.clone(), filters, wait).await;
+        id
+    }
+
+    /// Subscribe to filters with custom [`SubscriptionId`]
+    ///
+    /// If a subscription with the same [`SubscriptionId`] already exists, its filters are replaced.
+    pub async fn subscribe_with_id(&self, id: SubscriptionId, filters: Vec<Filter>, wait: bool) {
         let relays = self.relays().await;
-        self.update_subscription_filters(filters.clone()).await;
+        insert_subscription(
+            &mut *self.subscriptions.lock().await,
+            id.clone(),
+            filters.clone(),
+        );
         for relay in relays.values() {
-            if let Err(e) = relay.subscribe(filters.clone(), wait).await {
-                log::error!("{e}");
+            if let Err(e) = relay
+                .subscribe_with_id(id.clone(), filters.clone(), wait)
+                .await
+            {
+                log::error!("Impossible to subscribe to {}: {e}", relay.url());
             }
         }
     }
 
-    /// Unsubscribe from filters
-    pub async fn unsubscribe(&self, wait: bool) {
+    /// Unsubscribe
+    pub async fn unsubscribe(&self, id: SubscriptionId, wait: bool) {
         let relays = self.relays().await;
+        self.subscriptions.lock().await.remove(&id);
         for relay in relays.values() {
-            if let Err(e) = relay.unsubscribe(wait).await {
-                log::error!("{e}");
+            if let Err(e) = relay.unsubscribe(id.clone(), wait).await {
+                log::error!("Impossible to unsubscribe from {}: {e}", relay.url());
+            }
+        }
+    }
+
+    /// Unsubscribe from all subscriptions
+    pub async fn unsubscribe_all(&self, wait: bool) {
+        let relays = self.relays().await;
+        self.subscriptions.lock().await.clear();
+        for relay in relays.values() {
+            if let Err(e) = relay.unsubscribe_all(wait).await {
+                log::error!("Impossible to unsubscribe from {}: {e}", relay.url());
             }
         }
     }
 
+    /// Subscribe to filters and get a [`EventStream`] of the received events
+    ///
+    /// The subscription is closed when the [`EventStream`] is dropped.
+    pub async fn stream_events_of(&self, filters: Vec<Filter>, wait: bool) -> EventStream {
+        let id = SubscriptionId::generate();
+        let (sender, receiver) = mpsc::unbounded_channel();
+        self.streams.lock().await.insert(id.clone(), sender);
+        self.subscribe_with_id(id.clone(), filters, wait).await;
+        EventStream::new(id, receiver, self.pool_task_sender.clone())
+    }
+
     /// Get events of filters
     pub async fn get_events_of(
         &self,
@@ -431,6 +795,55 @@ impl RelayPool {
         Ok(events.lock_owned().await.clone())
     }
 
+    /// Count events of filters (NIP-45)
+    ///
+    /// Only the read relays that advertise NIP-45 in their information document are queried.
+    /// If no relay returns a count, the events are counted in the local database (if any).
+    pub async fn count_events_of(
+        &self,
+        filters: Vec<Filter>,
+        timeout: Option<Duration>,
+    ) -> Result<CountOutput, Error> {
+        let relays = self.relays().await;
+
+        let (tx, mut rx) = mpsc::channel(relays.len().max(1));
+        for (url, relay) in relays.into_iter() {
+            if !relay.opts().read() {
+                continue;
+            }
+
+            #[cfg(feature = "nip11")]
+            if relay.supports_nip(45).await != Some(true) {
+                log::debug!("{url} doesn't advertise NIP-45 support");
+                continue;
+            }
+
+            let filters = filters.clone();
+            let tx = tx.clone();
+            thread::spawn(async move {
+                match relay.count_events_of(filters, timeout).await {
+                    Ok(count) => {
+                        let _ = tx.send((url, count)).await;
+                    }
+                    Err(e) => log::error!("Failed to count events on {url}: {e}"),
+                }
+            });
+        }
+        drop(tx);
+
+        let mut counts: HashMap<Url, usize> = HashMap::new();
+        while let Some((url, count)) = rx.recv().await {
+            counts.insert(url, count);
+        }
+
+        let local: Option<usize> = match &self.database {
+            Some(database) if counts.is_empty() => Some(database.count(filters).await?),
+            _ => None,
+        };
+
+        Ok(CountOutput::new(counts, local))
+    }
+
     /// Request events of filter. All events will be sent to notification listener
     pub async fn req_events_of(&self, filters: Vec<Filter>, timeout: Option<Duration>) {
         let relays = self.relays().await;
@@ -459,8 +872,10 @@ impl RelayPool {
 
     /// Connect to relay
     pub async fn connect_relay(&self, relay: &Relay, wait_for_connection: bool) {
-        let filters: Vec<Filter> = self.subscription_filters().await;
-        relay.update_subscription_filters(filters).await;
+        let subscriptions = self.subscriptions.lock().await.clone();
+        for (id, filters) in sorted_subscriptions(subscriptions).into_iter() {
+            relay.update_subscription_filters(id, filters).await;
+        }
         relay.connect(wait_for_connection).await;
         #[cfg(feature = "sqlite")]
         if let Some(store) = &self.store {
@@ -494,3 +909,145 @@ impl RelayPool {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use nostr::event::tag::UncheckedUrl;
+    use nostr::{EventBuilder, Keys, Kind};
+
+    use super::*;
+    use crate::database::memory::MemoryDatabase;
+
+    #[test]
+    fn test_count_output_aggregate() {
+        let relay_a = Url::parse("wss://relay.a.com").unwrap();
+        let relay_b = Url::parse("wss://relay.b.com").unwrap();
+        let relays = HashMap::from([(relay_a, 10), (relay_b, 42)]);
+        assert_eq!(CountOutput::new(relays, None).count, 42);
+        assert_eq!(CountOutput::new(HashMap::new(), Some(7)).count, 7);
+        assert_eq!(CountOutput::new(HashMap::new(), None).count, 0);
+    }
+
+    /// Feed the events to a [`RelayPoolTask`] and return the IDs of the notified ones
+    async fn notified_events(
+        database: Option<Arc<dyn NostrDatabase>>,
+        events: Vec<Event>,
+    ) -> Vec<EventId> {
+        let relay_url = Url::parse("wss://relay.example.com").unwrap();
+        let (sender, receiver) = mpsc::channel(16);
+        let (notification_sender, mut notifications) = broadcast::channel(16);
+        let mut task = RelayPoolTask::new(
+            receiver,
+            notification_sender,
+            Arc::new(Mutex::new(HashMap::new())),
+            Arc::new(Mutex::new(HashMap::new())),
+            database,
+            Arc::new(Mutex::new(HashMap::new())),
+        );
+
+        for event in events.into_iter() {
+            let msg = RelayMessage::new_event(SubscriptionId::generate(), event);
+            sender
+                .send(RelayPoolMessage::ReceivedMsg {
+                    relay_url: relay_url.clone(),
+                    msg,
+                })
+                .await
+                .unwrap();
+        }
+        sender.send(RelayPoolMessage::Shutdown).await.unwrap();
+        task.run().await;
+
+        let mut received: Vec<EventId> = Vec::new();
+        while let Ok(notification) = notifications.try_recv() {
+            if let RelayPoolNotification::Event(_, event) = notification {
+                received.push(event.id);
+            }
+        }
+        received
+    }
+
+    #[tokio::test]
+    async fn test_deleted_events_not_notified() {
+        let keys = Keys::generate();
+        let note = EventBuilder::new_text_note("hello", &[])
+            .to_event(&keys)
+            .unwrap();
+        let deletion = EventBuilder::delete::<String>(vec![note.id], None)
+            .to_event(&keys)
+            .unwrap();
+
+        for database in [
+            None,
+            Some(Arc::new(MemoryDatabase::new()) as Arc<dyn NostrDatabase>),
+        ] {
+            let received = notified_events(database, vec![deletion.clone(), note.clone()]).await;
+            assert_eq!(received, vec![deletion.id]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_deleted_coordinates_not_notified() {
+        let keys = Keys::generate();
+        let now = Timestamp::now();
+        let article = |identifier: &str, created_at: Timestamp| {
+            EventBuilder::long_form_text_note("article", &[Tag::Identifier(identifier.into())])
+                .custom_created_at(created_at)
+                .to_event(&keys)
+                .unwrap()
+        };
+        let deleted = article("a", now - Duration::from_secs(10));
+        let other = article("b", now - Duration::from_secs(10));
+        let newer = article("a", now + Duration::from_secs(10));
+        let deletion = EventBuilder::new(
+            Kind::EventDeletion,
+            "",
+            &[Tag::A {
+                kind: Kind::LongFormTextNote,
+                public_key: keys.public_key(),
+                identifier: String::from("a"),
+                relay_url: UncheckedUrl::from_str("").unwrap(),
+            }],
+        )
+        .custom_created_at(now)
+        .to_event(&keys)
+        .unwrap();
+
+        for database in [
+            None,
+            Some(Arc::new(MemoryDatabase::new()) as Arc<dyn NostrDatabase>),
+        ] {
+            let events = vec![
+                deletion.clone(),
+                deleted.clone(),
+                other.clone(),
+                newer.clone(),
+            ];
+            let received = notified_events(database, events).await;
+            assert_eq!(received, vec![deletion.id, other.id, newer.id]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_count_events_of_local_fallback() {
+        let keys = Keys::generate();
+        let database = Arc::new(MemoryDatabase::new());
+        for content in ["a", "b", "c"] {
+            let event = EventBuilder::new_text_note(content, &[])
+                .to_event(&keys)
+                .unwrap();
+            database.save_event(&event).await.unwrap();
+        }
+
+        let pool = RelayPool::with_database(database);
+        let filter = Filter::new()
+            .author(keys.public_key().to_string())
+            .kind(Kind::TextNote);
+        let output = pool.count_events_of(vec![filter], None).await.unwrap();
+        assert!(output.relays.is_empty());
+        assert_eq!(output.local, Some(3));
+        assert_eq!(output.count, 3);
+    }
+}
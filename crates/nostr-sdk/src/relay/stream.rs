@@ -0,0 +1,177 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! Event stream
+
+use std::collections::{HashMap, HashSet};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use nostr::url::Url;
+use nostr::{Event, EventId, RelayMessage, SubscriptionId};
+use nostr_sdk_net::futures_util::Stream;
+use tokio::sync::mpsc::{Sender, UnboundedReceiver};
+
+use super::pool::RelayPoolMessage;
+
+/// Item of an [`EventStream`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EventStreamItem {
+    /// Received an [`Event`]
+    Event(Event),
+    /// End of stored events sent by a relay
+    Eose(Url),
+    /// Subscription closed by a relay, with reason
+    Closed(Url, String),
+}
+
+/// Stream of the [`Event`]s received for a single subscription
+///
+/// Events are deduplicated across relays. The subscription is closed when the stream is dropped.
+#[derive(Debug)]
+pub struct EventStream {
+    id: SubscriptionId,
+    receiver: UnboundedReceiver<(Url, RelayMessage)>,
+    seen: HashSet<EventId>,
+    eose: HashSet<Url>,
+    closed: HashMap<Url, String>,
+    pool_task_sender: Sender<RelayPoolMessage>,
+}
+
+impl EventStream {
+    pub(crate) fn new(
+        id: SubscriptionId,
+        receiver: UnboundedReceiver<(Url, RelayMessage)>,
+        pool_task_sender: Sender<RelayPoolMessage>,
+    ) -> Self {
+        Self {
+            id,
+            receiver,
+            seen: HashSet::new(),
+            eose: HashSet::new(),
+            closed: HashMap::new(),
+            pool_task_sender,
+        }
+    }
+
+    /// Get [`SubscriptionId`]
+    pub fn id(&self) -> SubscriptionId {
+        self.id.clone()
+    }
+
+    /// Get relays that have sent the `EOSE` for this subscription
+    pub fn eose_relays(&self) -> &HashSet<Url> {
+        &self.eose
+    }
+
+    /// Check if the relay has sent the `EOSE` for this subscription
+    pub fn is_eose(&self, relay_url: &Url) -> bool {
+        self.eose.contains(relay_url)
+    }
+
+    /// Get relays that have closed this subscription, with reason
+    pub fn closed_relays(&self) -> &HashMap<Url, String> {
+        &self.closed
+    }
+}
+
+impl Stream for EventStream {
+    type Item = EventStreamItem;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            match self.receiver.poll_recv(cx) {
+                Poll::Ready(Some((relay_url, msg))) => match msg {
+                    RelayMessage::Event { event, .. } => {
+                        if event.is_expired() || event.verify().is_err() {
+                            continue;
+                        }
+                        if self.seen.insert(event.id) {
+                            return Poll::Ready(Some(EventStreamItem::Event(*event)));
+                        }
+                    }
+                    RelayMessage::EndOfStoredEvents(..) => {
+                        log::debug!("Received EOSE for {} from {relay_url}", self.id);
+                        if self.eose.insert(relay_url.clone()) {
+                            return Poll::Ready(Some(EventStreamItem::Eose(relay_url)));
+                        }
+                    }
+                    RelayMessage::Closed { message, .. } => {
+                        log::debug!("Subscription {} closed by {relay_url}: {message}", self.id);
+                        self.closed.insert(relay_url.clone(), message.clone());
+                        return Poll::Ready(Some(EventStreamItem::Closed(relay_url, message)));
+                    }
+                    _ => (),
+                },
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl Drop for EventStream {
+    fn drop(&mut self) {
+        let msg = RelayPoolMessage::CloseStream(self.id.clone());
+        if let Err(e) = self.pool_task_sender.try_send(msg) {
+            log::error!("Impossible to close subscription {}: {e}", self.id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr::{EventBuilder, Keys};
+    use nostr_sdk_net::futures_util::StreamExt;
+    use tokio::sync::mpsc;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_event_stream_dedup() {
+        let keys = Keys::generate();
+        let event = EventBuilder::new_text_note("hello", &[])
+            .to_event(&keys)
+            .unwrap();
+        let id = SubscriptionId::generate();
+        let relay_a = Url::parse("wss://relay.a.com").unwrap();
+        let relay_b = Url::parse("wss://relay.b.com").unwrap();
+
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let (pool_task_sender, mut pool_task_receiver) = mpsc::channel(1);
+        let mut stream = EventStream::new(id.clone(), receiver, pool_task_sender);
+
+        for relay_url in [relay_a.clone(), relay_b.clone()] {
+            sender
+                .send((
+                    relay_url.clone(),
+                    RelayMessage::new_event(id.clone(), event.clone()),
+                ))
+                .unwrap();
+            sender
+                .send((relay_url, RelayMessage::new_eose(id.clone())))
+                .unwrap();
+        }
+        drop(sender);
+
+        assert_eq!(stream.next().await, Some(EventStreamItem::Event(event)));
+        assert_eq!(
+            stream.next().await,
+            Some(EventStreamItem::Eose(relay_a.clone()))
+        );
+        assert_eq!(
+            stream.next().await,
+            Some(EventStreamItem::Eose(relay_b.clone()))
+        );
+        assert_eq!(stream.next().await, None);
+        assert!(stream.is_eose(&relay_a));
+        assert!(stream.is_eose(&relay_b));
+
+        // The subscription is closed outside of any runtime
+        std::thread::spawn(move || drop(stream)).join().unwrap();
+        assert!(matches!(
+            pool_task_receiver.recv().await,
+            Some(RelayPoolMessage::CloseStream(closed)) if closed == id
+        ));
+    }
+}
@@ -13,14 +13,19 @@ use serde_json::{json, Value};
 use url::Url;
 
 pub use super::kind::Kind;
-pub use super::tag::{Marker, Tag, TagKind};
+pub use super::tag::{Marker, Tag, TagKind, UncheckedUrl};
 use super::{Event, EventId, UnsignedEvent};
 use crate::key::{self, Keys};
 #[cfg(feature = "nip04")]
 use crate::nips::nip04;
 use crate::nips::nip13;
+#[cfg(feature = "nip44")]
+use crate::nips::nip44;
 #[cfg(feature = "nip46")]
 use crate::nips::nip46::Message as NostrConnectMessage;
+#[cfg(feature = "nip59")]
+use crate::nips::nip59;
+use crate::signer::{NostrSigner, SignerError};
 use crate::types::{ChannelId, Contact, Metadata, Timestamp};
 
 /// [`EventBuilder`] error
@@ -42,6 +47,17 @@ pub enum Error {
     #[cfg(feature = "nip04")]
     #[error(transparent)]
     NIP04(#[from] nip04::Error),
+    /// NIP44 error
+    #[cfg(feature = "nip44")]
+    #[error(transparent)]
+    NIP44(#[from] nip44::Error),
+    /// NIP59 error
+    #[cfg(feature = "nip59")]
+    #[error(transparent)]
+    NIP59(#[from] nip59::Error),
+    /// Signer error
+    #[error(transparent)]
+    Signer(#[from] SignerError),
 }
 
 /// [`Event`] builder
@@ -50,6 +66,7 @@ pub struct EventBuilder {
     kind: Kind,
     tags: Vec<Tag>,
     content: String,
+    custom_created_at: Option<Timestamp>,
 }
 
 impl EventBuilder {
@@ -62,6 +79,15 @@ impl EventBuilder {
             kind,
             tags: tags.to_vec(),
             content: content.into(),
+            custom_created_at: None,
+        }
+    }
+
+    /// Set a custom `created_at` (default: now)
+    pub fn custom_created_at(self, created_at: Timestamp) -> Self {
+        Self {
+            custom_created_at: Some(created_at),
+            ..self
         }
     }
 
@@ -73,7 +99,7 @@ impl EventBuilder {
 
     /// Build [`UnsignedEvent`]
     pub fn to_unsigned_event(self, pubkey: XOnlyPublicKey) -> UnsignedEvent {
-        let created_at: Timestamp = Timestamp::now();
+        let created_at: Timestamp = self.custom_created_at.unwrap_or_else(Timestamp::now);
         let id = EventId::new(&pubkey, created_at, &self.kind, &self.tags, &self.content);
         UnsignedEvent {
             id,
@@ -85,6 +111,16 @@ impl EventBuilder {
         }
     }
 
+    /// Build [`Event`] signed with a [`NostrSigner`]
+    pub async fn sign<T>(self, signer: &T) -> Result<Event, Error>
+    where
+        T: NostrSigner + ?Sized,
+    {
+        let public_key: XOnlyPublicKey = signer.public_key().await?;
+        let unsigned: UnsignedEvent = self.to_unsigned_event(public_key);
+        Ok(signer.sign_event(unsigned).await?)
+    }
+
     /// Build POW [`Event`]
     pub fn to_pow_event(self, keys: &Keys, difficulty: u8) -> Result<Event, Error> {
         let pubkey: XOnlyPublicKey = keys.public_key();
@@ -175,6 +211,38 @@ impl EventBuilder {
         Self::new(Kind::TextNote, content, tags)
     }
 
+    /// Text note reply
+    ///
+    /// If `root` is not set, it's taken from the `parent` thread references
+    /// (or the `parent` itself, if it's the root). The `p` tags of the `parent`, and its author, are inherited.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/10.md>
+    pub fn reply_to<S>(
+        content: S,
+        parent: &Event,
+        root: Option<EventId>,
+        relay_hint: Option<UncheckedUrl>,
+    ) -> Self
+    where
+        S: Into<String>,
+    {
+        let refs = parent.thread_refs();
+        let root: EventId = root.or(refs.root).unwrap_or(parent.id);
+
+        let mut tags: Vec<Tag> = vec![Tag::Event(root, relay_hint.clone(), Some(Marker::Root))];
+        if root != parent.id {
+            tags.push(Tag::Event(parent.id, relay_hint, Some(Marker::Reply)));
+        }
+
+        let mut public_keys: Vec<XOnlyPublicKey> = refs.public_keys;
+        if !public_keys.contains(&parent.pubkey) {
+            public_keys.push(parent.pubkey);
+        }
+        tags.extend(public_keys.into_iter().map(|pk| Tag::PubKey(pk, None)));
+
+        Self::new(Kind::TextNote, content, &tags)
+    }
+
     /// Long-form text note (generally referred to as "articles" or "blog posts").
     ///
     /// <https://github.com/nostr-protocol/nips/blob/master/23.md>
@@ -235,6 +303,150 @@ impl EventBuilder {
         ))
     }
 
+    /// Seal a rumor (NIP59)
+    ///
+    /// The rumor must be authored by `sender_keys`.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/59.md>
+    #[cfg(feature = "nip59")]
+    pub fn seal(
+        sender_keys: &Keys,
+        receiver_pubkey: &XOnlyPublicKey,
+        rumor: UnsignedEvent,
+    ) -> Result<Self, Error> {
+        if rumor.pubkey != sender_keys.public_key() {
+            return Err(Error::NIP59(nip59::Error::SenderMismatch));
+        }
+
+        let content: String = nip44::encrypt(
+            &sender_keys.secret_key()?,
+            receiver_pubkey,
+            rumor.as_json(),
+            nip44::Version::default(),
+        )?;
+        Ok(Self::new(Kind::Seal, content, &[]).custom_created_at(nip59::random_timestamp()))
+    }
+
+    /// Seal a rumor and gift wrap it for the receiver (NIP59)
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/59.md>
+    #[cfg(feature = "nip59")]
+    pub fn gift_wrap(
+        sender_keys: &Keys,
+        receiver_pubkey: &XOnlyPublicKey,
+        rumor: UnsignedEvent,
+        expiration: Option<Timestamp>,
+    ) -> Result<Event, Error> {
+        let seal: Event = Self::seal(sender_keys, receiver_pubkey, rumor)?.to_event(sender_keys)?;
+        Self::gift_wrap_from_seal(receiver_pubkey, &seal, expiration)
+    }
+
+    /// Seal a rumor with a [`NostrSigner`] (NIP59)
+    ///
+    /// The rumor must be authored by the signer.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/59.md>
+    #[cfg(feature = "nip59")]
+    pub async fn seal_with_signer<T>(
+        signer: &T,
+        receiver_pubkey: &XOnlyPublicKey,
+        rumor: UnsignedEvent,
+    ) -> Result<Event, Error>
+    where
+        T: NostrSigner + ?Sized,
+    {
+        if rumor.pubkey != signer.public_key().await? {
+            return Err(Error::NIP59(nip59::Error::SenderMismatch));
+        }
+
+        let content: String = signer
+            .nip44_encrypt(*receiver_pubkey, rumor.as_json())
+            .await?;
+        Self::new(Kind::Seal, content, &[])
+            .custom_created_at(nip59::random_timestamp())
+            .sign(signer)
+            .await
+    }
+
+    /// Seal a rumor with a [`NostrSigner`] and gift wrap it for the receiver (NIP59)
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/59.md>
+    #[cfg(feature = "nip59")]
+    pub async fn gift_wrap_with_signer<T>(
+        signer: &T,
+        receiver_pubkey: &XOnlyPublicKey,
+        rumor: UnsignedEvent,
+        expiration: Option<Timestamp>,
+    ) -> Result<Event, Error>
+    where
+        T: NostrSigner + ?Sized,
+    {
+        let seal: Event = Self::seal_with_signer(signer, receiver_pubkey, rumor).await?;
+        Self::gift_wrap_from_seal(receiver_pubkey, &seal, expiration)
+    }
+
+    /// Gift wrap a seal, signing with a random ephemeral key (NIP59)
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/59.md>
+    #[cfg(feature = "nip59")]
+    pub fn gift_wrap_from_seal(
+        receiver_pubkey: &XOnlyPublicKey,
+        seal: &Event,
+        expiration: Option<Timestamp>,
+    ) -> Result<Event, Error> {
+        if seal.kind != Kind::Seal {
+            return Err(Error::NIP59(nip59::Error::NotSeal));
+        }
+
+        let keys = Keys::generate();
+        let content: String = nip44::encrypt(
+            &keys.secret_key()?,
+            receiver_pubkey,
+            seal.as_json(),
+            nip44::Version::default(),
+        )?;
+
+        let mut tags: Vec<Tag> = vec![Tag::PubKey(*receiver_pubkey, None)];
+        if let Some(expiration) = expiration {
+            tags.push(Tag::Expiration(expiration));
+        }
+
+        Self::new(Kind::GiftWrap, content, &tags)
+            .custom_created_at(nip59::random_timestamp())
+            .to_event(&keys)
+    }
+
+    /// Private direct message rumor (NIP17)
+    ///
+    /// The rumor must be sealed and gift wrapped for each receiver (see [`EventBuilder::gift_wrap`]).
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/17.md>
+    pub fn private_msg_rumor<S>(
+        receivers: &[XOnlyPublicKey],
+        message: S,
+        reply_to: Option<EventId>,
+    ) -> Self
+    where
+        S: Into<String>,
+    {
+        let mut tags: Vec<Tag> = receivers
+            .iter()
+            .map(|public_key| Tag::PubKey(*public_key, None))
+            .collect();
+        if let Some(event_id) = reply_to {
+            tags.push(Tag::Event(event_id, None, Some(Marker::Reply)));
+        }
+        Self::new(Kind::PrivateDirectMessage, message, &tags)
+    }
+
+    /// Inbox relays, where to receive private direct messages (NIP17)
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/17.md>
+    pub fn inbox_relays(relays: Vec<Url>) -> Self {
+        let tags: Vec<Tag> = relays.into_iter().map(Tag::Relay).collect();
+        Self::new(Kind::InboxRelays, "", &tags)
+    }
+
     /// Repost event
     pub fn repost(event_id: EventId, public_key: XOnlyPublicKey) -> Self {
         Self::new(
@@ -25,6 +25,10 @@ pub enum Kind {
     EncryptedDirectMessage,
     /// Event Deletion (NIP09)
     EventDeletion,
+    /// Seal (NIP59)
+    Seal,
+    /// Private Direct Message (NIP17)
+    PrivateDirectMessage,
     /// Repost (NIP18)
     Repost,
     /// Reaction (NIP25)
@@ -51,6 +55,8 @@ pub enum Kind {
     PublicChatReserved48,
     /// Public Chat Reserved (NIP28)
     PublicChatReserved49,
+    /// Gift Wrap (NIP59)
+    GiftWrap,
     /// Reporting (NIP56)
     Reporting,
     /// Zap Request (NIP57)
@@ -63,6 +69,8 @@ pub enum Kind {
     PinList,
     /// Relay List Metadata (NIP65)
     RelayList,
+    /// Inbox Relays (NIP17)
+    InboxRelays,
     /// Client Authentication (NIP42)
     Authentication,
     /// Nostr Connect (NIP46)
@@ -101,6 +109,28 @@ impl Kind {
     pub fn as_u64(&self) -> u64 {
         (*self).into()
     }
+
+    /// Check if [`Kind`] is `Replaceable`
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/01.md>
+    pub fn is_replaceable(&self) -> bool {
+        matches!(self, Kind::Metadata | Kind::ContactList)
+            || (10_000..20_000).contains(&self.as_u64())
+    }
+
+    /// Check if [`Kind`] is `Ephemeral`
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/01.md>
+    pub fn is_ephemeral(&self) -> bool {
+        (20_000..30_000).contains(&self.as_u64())
+    }
+
+    /// Check if [`Kind`] is `Parameterized replaceable`
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/01.md>
+    pub fn is_parameterized_replaceable(&self) -> bool {
+        (30_000..40_000).contains(&self.as_u64())
+    }
 }
 
 impl From<u64> for Kind {
@@ -115,6 +145,8 @@ impl From<u64> for Kind {
             6 => Self::Repost,
             7 => Self::Reaction,
             8 => Self::BadgeAward,
+            13 => Self::Seal,
+            14 => Self::PrivateDirectMessage,
             40 => Self::ChannelCreation,
             41 => Self::ChannelMetadata,
             42 => Self::ChannelMessage,
@@ -125,12 +157,14 @@ impl From<u64> for Kind {
             47 => Self::PublicChatReserved47,
             48 => Self::PublicChatReserved48,
             49 => Self::PublicChatReserved49,
+            1059 => Self::GiftWrap,
             1984 => Self::Reporting,
             9734 => Self::ZapRequest,
             9735 => Self::Zap,
             10000 => Self::MuteList,
             10001 => Self::PinList,
             10002 => Self::RelayList,
+            10050 => Self::InboxRelays,
             22242 => Self::Authentication,
             24133 => Self::NostrConnect,
             30000 => Self::CategorizedPeopleList,
@@ -160,6 +194,8 @@ impl From<Kind> for u64 {
             Kind::Repost => 6,
             Kind::Reaction => 7,
             Kind::BadgeAward => 8,
+            Kind::Seal => 13,
+            Kind::PrivateDirectMessage => 14,
             Kind::ChannelCreation => 40,
             Kind::ChannelMetadata => 41,
             Kind::ChannelMessage => 42,
@@ -170,12 +206,14 @@ impl From<Kind> for u64 {
             Kind::PublicChatReserved47 => 47,
             Kind::PublicChatReserved48 => 48,
             Kind::PublicChatReserved49 => 49,
+            Kind::GiftWrap => 1059,
             Kind::Reporting => 1984,
             Kind::ZapRequest => 9734,
             Kind::Zap => 9735,
             Kind::MuteList => 10000,
             Kind::PinList => 10001,
             Kind::RelayList => 10002,
+            Kind::InboxRelays => 10050,
             Kind::Authentication => 22242,
             Kind::NostrConnect => 24133,
             Kind::CategorizedPeopleList => 30000,
@@ -253,6 +291,18 @@ mod tests {
         assert_eq!(Kind::TextNote, Kind::Custom(1));
     }
 
+    #[test]
+    fn test_kind_ranges() {
+        assert!(Kind::Metadata.is_replaceable());
+        assert!(Kind::ContactList.is_replaceable());
+        assert!(Kind::MuteList.is_replaceable());
+        assert!(Kind::Custom(19999).is_replaceable());
+        assert!(!Kind::TextNote.is_replaceable());
+        assert!(Kind::NostrConnect.is_ephemeral());
+        assert!(Kind::LongFormTextNote.is_parameterized_replaceable());
+        assert!(!Kind::Custom(40000).is_parameterized_replaceable());
+    }
+
     #[test]
     fn test_not_equal_kind() {
         assert_ne!(Kind::Custom(20100), Kind::Custom(2000));
@@ -22,6 +22,7 @@ pub use self::id::EventId;
 pub use self::kind::Kind;
 pub use self::tag::{Marker, Tag, TagKind};
 pub use self::unsigned::UnsignedEvent;
+use crate::nips::nip10::EventThreadRefs;
 use crate::{Timestamp, SECP256K1};
 
 /// [`Event`] error
@@ -106,6 +107,41 @@ impl Event {
         serde_json::json!(self).to_string()
     }
 
+    /// Get the expiration [`Timestamp`] of the event, if any
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/40.md>
+    pub fn expiration(&self) -> Option<&Timestamp> {
+        self.tags.iter().find_map(|tag| match tag {
+            Tag::Expiration(timestamp) => Some(timestamp),
+            _ => None,
+        })
+    }
+
+    /// Check if the event is expired
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/40.md>
+    pub fn is_expired(&self) -> bool {
+        match self.expiration() {
+            Some(timestamp) => timestamp < &Timestamp::now(),
+            None => false,
+        }
+    }
+
+    /// Get the thread references (root, reply, mentions) of the event
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/10.md>
+    pub fn thread_refs(&self) -> EventThreadRefs {
+        EventThreadRefs::from_tags(&self.tags)
+    }
+
+    /// Get the identifier (`d` tag) of the event, if any
+    pub fn identifier(&self) -> Option<&str> {
+        self.tags.iter().find_map(|tag| match tag {
+            Tag::Identifier(identifier) => Some(identifier.as_str()),
+            _ => None,
+        })
+    }
+
     /// Timestamp this event with OpenTimestamps, according to NIP-03
     #[cfg(feature = "nip03")]
     pub fn timestamp(&mut self) -> Result<(), Error> {
@@ -22,6 +22,8 @@ pub mod vanity;
 
 #[cfg(feature = "nip19")]
 use crate::nips::nip19::FromBech32;
+#[cfg(feature = "nip49")]
+use crate::nips::nip49::{self, EncryptedSecretKey};
 
 /// [`Keys`] error
 #[derive(Debug, Eq, PartialEq, thiserror::Error)]
@@ -89,6 +91,21 @@ impl Keys {
         }
     }
 
+    /// Initialize from a password-encrypted secret key (NIP49)
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/49.md>
+    #[cfg(feature = "nip49")]
+    pub fn from_encrypted<S>(
+        encrypted_secret_key: &EncryptedSecretKey,
+        password: S,
+    ) -> Result<Self, nip49::Error>
+    where
+        S: AsRef<str>,
+    {
+        let secret_key: SecretKey = encrypted_secret_key.to_secret_key(password)?;
+        Ok(Self::new(secret_key))
+    }
+
     /// Generate new random [`Keys`]
     pub fn generate() -> Self {
         let mut rng = OsRng::default();
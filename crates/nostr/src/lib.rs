@@ -27,11 +27,15 @@ pub mod key;
 pub mod message;
 pub mod nips;
 pub mod prelude;
+pub mod signer;
 pub mod types;
 
 pub use self::event::{Event, EventBuilder, EventId, Kind, Tag, UnsignedEvent};
 pub use self::key::Keys;
-pub use self::message::{ClientMessage, Filter, RelayMessage, SubscriptionId};
+pub use self::message::{
+    ClientMessage, Filter, MachineReadablePrefix, RelayMessage, SingleLetterTag, SubscriptionId,
+};
+pub use self::signer::{NostrSigner, SignerError};
 pub use self::types::{ChannelId, Contact, Entity, Metadata, Profile, Timestamp};
 
 /// Result
@@ -8,8 +8,8 @@ pub mod relay;
 pub mod subscription;
 
 pub use self::client::ClientMessage;
-pub use self::relay::RelayMessage;
-pub use self::subscription::{Filter, SubscriptionId};
+pub use self::relay::{MachineReadablePrefix, RelayMessage};
+pub use self::subscription::{Filter, SingleLetterTag, SubscriptionId};
 
 /// Messages error
 #[derive(Debug, thiserror::Error)]
@@ -4,6 +4,9 @@
 
 //! Relay messages
 
+use std::fmt;
+use std::str::FromStr;
+
 use serde::de::Error;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_json::{json, Value};
@@ -11,6 +14,73 @@ use serde_json::{json, Value};
 use super::MessageHandleError;
 use crate::{Event, EventId, SubscriptionId};
 
+/// Machine-readable prefix of `OK` and `CLOSED` messages
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/01.md>
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MachineReadablePrefix {
+    /// Event already stored
+    Duplicate,
+    /// Proof of Work (NIP-13) difficulty not satisfied
+    Pow,
+    /// Pubkey or network address blocked
+    Blocked,
+    /// Rate-limited
+    RateLimited,
+    /// Invalid event or message
+    Invalid,
+    /// Relay error
+    Error,
+    /// Authentication (NIP-42) required
+    AuthRequired,
+    /// Authenticated pubkey not allowed
+    Restricted,
+}
+
+impl MachineReadablePrefix {
+    /// Parse the machine-readable prefix of a relay message (ex. `blocked: you are banned`)
+    pub fn parse<S>(message: S) -> Option<Self>
+    where
+        S: AsRef<str>,
+    {
+        let (prefix, _) = message.as_ref().split_once(':')?;
+        Self::from_str(prefix.trim()).ok()
+    }
+}
+
+impl fmt::Display for MachineReadablePrefix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Duplicate => write!(f, "duplicate"),
+            Self::Pow => write!(f, "pow"),
+            Self::Blocked => write!(f, "blocked"),
+            Self::RateLimited => write!(f, "rate-limited"),
+            Self::Invalid => write!(f, "invalid"),
+            Self::Error => write!(f, "error"),
+            Self::AuthRequired => write!(f, "auth-required"),
+            Self::Restricted => write!(f, "restricted"),
+        }
+    }
+}
+
+impl FromStr for MachineReadablePrefix {
+    type Err = MessageHandleError;
+
+    fn from_str(prefix: &str) -> Result<Self, Self::Err> {
+        match prefix {
+            "duplicate" => Ok(Self::Duplicate),
+            "pow" => Ok(Self::Pow),
+            "blocked" => Ok(Self::Blocked),
+            "rate-limited" => Ok(Self::RateLimited),
+            "invalid" => Ok(Self::Invalid),
+            "error" => Ok(Self::Error),
+            "auth-required" => Ok(Self::AuthRequired),
+            "restricted" => Ok(Self::Restricted),
+            _ => Err(MessageHandleError::InvalidMessageFormat),
+        }
+    }
+}
+
 /// Messages sent by relays, received by clients
 #[allow(missing_docs)]
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -28,6 +98,10 @@ pub enum RelayMessage {
         status: bool,
         message: String,
     },
+    Closed {
+        subscription_id: SubscriptionId,
+        message: String,
+    },
     Auth {
         challenge: String,
     },
@@ -94,6 +168,27 @@ impl RelayMessage {
         }
     }
 
+    /// Create new `CLOSED` message
+    pub fn new_closed<S>(subscription_id: SubscriptionId, message: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self::Closed {
+            subscription_id,
+            message: message.into(),
+        }
+    }
+
+    /// Get the [`MachineReadablePrefix`] of `OK` and `CLOSED` messages, if any
+    pub fn machine_readable_prefix(&self) -> Option<MachineReadablePrefix> {
+        match self {
+            Self::Ok { message, .. } | Self::Closed { message, .. } => {
+                MachineReadablePrefix::parse(message)
+            }
+            _ => None,
+        }
+    }
+
     /// Create new `AUTH` message
     pub fn new_auth<S>(challenge: S) -> Self
     where
@@ -127,6 +222,10 @@ impl RelayMessage {
                 status,
                 message,
             } => json!(["OK", event_id, status, message]),
+            Self::Closed {
+                subscription_id,
+                message,
+            } => json!(["CLOSED", subscription_id, message]),
             Self::Auth { challenge } => json!(["AUTH", challenge]),
             Self::Count {
                 subscription_id,
@@ -204,6 +303,19 @@ impl RelayMessage {
             return Ok(Self::new_ok(event_id, status, message));
         }
 
+        // CLOSED
+        // Relay response format: ["CLOSED", <subscription_id>, <message>]
+        if v[0] == "CLOSED" {
+            if v_len != 3 {
+                return Err(MessageHandleError::InvalidMessageFormat);
+            }
+
+            let subscription_id: SubscriptionId = serde_json::from_value(v[1].clone())?;
+            let message: String = serde_json::from_value(v[2].clone())?;
+
+            return Ok(Self::new_closed(subscription_id, message));
+        }
+
         // OK (NIP-42)
         // Relay response format: ["AUTH", <challenge>]
         if v[0] == "AUTH" {
@@ -377,6 +489,46 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_handle_valid_closed() -> Result<()> {
+        let valid_closed_msg =
+            r#"["CLOSED","sub1","auth-required: we only serve registered users"]"#;
+        let handled_valid_closed_msg = RelayMessage::new_closed(
+            SubscriptionId::new("sub1"),
+            "auth-required: we only serve registered users",
+        );
+
+        assert_eq!(
+            RelayMessage::from_json(valid_closed_msg)?,
+            handled_valid_closed_msg
+        );
+        assert_eq!(handled_valid_closed_msg.as_json(), valid_closed_msg);
+        assert_eq!(
+            handled_valid_closed_msg.machine_readable_prefix(),
+            Some(MachineReadablePrefix::AuthRequired)
+        );
+
+        // Missing message
+        assert!(RelayMessage::from_json(r#"["CLOSED","sub1"]"#).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_machine_readable_prefix() {
+        assert_eq!(
+            MachineReadablePrefix::parse("pow: difficulty 25>=24"),
+            Some(MachineReadablePrefix::Pow)
+        );
+        assert_eq!(
+            MachineReadablePrefix::parse("rate-limited: slow down there chief"),
+            Some(MachineReadablePrefix::RateLimited)
+        );
+        assert_eq!(MachineReadablePrefix::parse("unknown: message"), None);
+        assert_eq!(MachineReadablePrefix::parse("no prefix"), None);
+        assert_eq!(MachineReadablePrefix::Restricted.to_string(), "restricted");
+    }
+
     #[test]
     fn parse_message() -> Result<()> {
         // Got this fresh off the wire
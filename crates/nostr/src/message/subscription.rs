@@ -4,7 +4,9 @@
 
 //! Subscription filters
 
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
+use std::str::FromStr;
 
 use bitcoin_hashes::sha256::Hash as Sha256Hash;
 use bitcoin_hashes::Hash;
@@ -16,10 +18,18 @@ use serde::ser::{SerializeMap, Serializer};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Map, Value};
 
-use crate::{EventId, Kind, Timestamp};
+use crate::{Event, EventId, Kind, Timestamp};
+
+/// Subscription filters error
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum Error {
+    /// Invalid single-letter tag
+    #[error("invalid single-letter tag: {0}")]
+    InvalidSingleLetterTag(String),
+}
 
 /// Subscription ID
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct SubscriptionId(String);
 
 impl SubscriptionId {
@@ -46,6 +56,54 @@ impl fmt::Display for SubscriptionId {
     }
 }
 
+/// Single-letter tag (`a-z` or `A-Z`)
+///
+/// Used as key of the generic tag queries (`#<letter>`) of a [`Filter`].
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/01.md>
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SingleLetterTag(char);
+
+impl SingleLetterTag {
+    /// Create new [`SingleLetterTag`]
+    pub fn new(c: char) -> Result<Self, Error> {
+        if c.is_ascii_alphabetic() {
+            Ok(Self(c))
+        } else {
+            Err(Error::InvalidSingleLetterTag(c.to_string()))
+        }
+    }
+
+    /// Get tag as [`char`]
+    pub fn as_char(&self) -> char {
+        self.0
+    }
+}
+
+impl TryFrom<char> for SingleLetterTag {
+    type Error = Error;
+    fn try_from(c: char) -> Result<Self, Self::Error> {
+        Self::new(c)
+    }
+}
+
+impl FromStr for SingleLetterTag {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Self::new(c),
+            _ => Err(Error::InvalidSingleLetterTag(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for SingleLetterTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
 /// Subscription filters
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Filter {
@@ -73,6 +131,8 @@ pub struct Filter {
     pub until: Option<Timestamp>,
     /// Maximum number of events to be returned in the initial query
     pub limit: Option<usize>,
+    /// Generic tag queries (`#<single-letter>`)
+    pub generic_tags: BTreeMap<SingleLetterTag, BTreeSet<String>>,
     /// Custom fields
     pub custom: Map<String, Value>,
 }
@@ -98,6 +158,7 @@ impl Filter {
             since: None,
             until: None,
             limit: None,
+            generic_tags: BTreeMap::new(),
             custom: Map::new(),
         }
     }
@@ -282,6 +343,61 @@ impl Filter {
         }
     }
 
+    /// Set identifier (`#d` tag)
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/33.md>
+    pub fn identifier<S>(self, identifier: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.custom_tag(SingleLetterTag('d'), vec![identifier])
+    }
+
+    /// Set identifiers (`#d` tag)
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/33.md>
+    pub fn identifiers<S>(self, identifiers: Vec<S>) -> Self
+    where
+        S: Into<String>,
+    {
+        self.custom_tag(SingleLetterTag('d'), identifiers)
+    }
+
+    /// Set coordinate of a parameterized replaceable event (`#a` tag)
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/33.md>
+    pub fn coordinate<S>(self, kind: Kind, public_key: XOnlyPublicKey, identifier: S) -> Self
+    where
+        S: Into<String>,
+    {
+        let coordinate: String = format!("{}:{public_key}:{}", kind.as_u64(), identifier.into());
+        self.custom_tag(SingleLetterTag('a'), vec![coordinate])
+    }
+
+    /// Set generic tag query (`#<tag>`)
+    ///
+    /// Values of a tag also covered by a typed field (`#e`, `#p`, `#t` or `#r`) are merged with it.
+    ///
+    /// # Example
+    /// ```rust
+    /// use nostr::{Filter, SingleLetterTag};
+    ///
+    /// let tag = SingleLetterTag::new('x').unwrap();
+    /// let filter = Filter::new().custom_tag(tag, vec!["value"]);
+    /// assert_eq!(filter.as_json(), r##"{"#x":["value"]}"##);
+    /// ```
+    pub fn custom_tag<S>(self, tag: SingleLetterTag, values: Vec<S>) -> Self
+    where
+        S: Into<String>,
+    {
+        let mut generic_tags = self.generic_tags;
+        generic_tags.insert(tag, values.into_iter().map(|v| v.into()).collect());
+        Self {
+            generic_tags,
+            ..self
+        }
+    }
+
     /// Set custom filters
     pub fn custom(self, map: Map<String, Value>) -> Self {
         Self {
@@ -289,6 +405,131 @@ impl Filter {
             ..self
         }
     }
+
+    /// Determine if [`Filter`] match the provided [`Event`].
+    ///
+    /// The `search` field is **not** evaluated, since its semantic is relay-specific (NIP50).
+    pub fn match_event(&self, event: &Event) -> bool {
+        self.ids_match(event)
+            && self.authors_match(event)
+            && self.kinds_match(event)
+            && self.tags_match(event)
+            && self.since.map_or(true, |since| event.created_at >= since)
+            && self.until.map_or(true, |until| event.created_at <= until)
+    }
+
+    fn ids_match(&self, event: &Event) -> bool {
+        match &self.ids {
+            Some(ids) => {
+                let id: String = event.id.to_hex();
+                ids.iter().any(|prefix| id.starts_with(prefix))
+            }
+            None => true,
+        }
+    }
+
+    fn authors_match(&self, event: &Event) -> bool {
+        match &self.authors {
+            Some(authors) => {
+                let pubkey: String = event.pubkey.to_string();
+                authors.iter().any(|prefix| pubkey.starts_with(prefix))
+            }
+            None => true,
+        }
+    }
+
+    fn kinds_match(&self, event: &Event) -> bool {
+        match &self.kinds {
+            Some(kinds) => kinds.contains(&event.kind),
+            None => true,
+        }
+    }
+
+    fn tags_match(&self, event: &Event) -> bool {
+        // Typed and generic values of the same tag are merged into a single set
+        let mut queries: BTreeMap<char, BTreeSet<String>> = BTreeMap::new();
+        if let Some(events) = &self.events {
+            queries.insert('e', events.iter().map(|id| id.to_hex()).collect());
+        }
+        if let Some(pubkeys) = &self.pubkeys {
+            queries.insert('p', pubkeys.iter().map(|p| p.to_string()).collect());
+        }
+        if let Some(hashtags) = &self.hashtags {
+            queries.insert('t', hashtags.iter().cloned().collect());
+        }
+        if let Some(references) = &self.references {
+            queries.insert('r', references.iter().cloned().collect());
+        }
+        for (tag, values) in self.generic_tags.iter() {
+            queries
+                .entry(tag.as_char())
+                .or_default()
+                .extend(values.iter().cloned());
+        }
+
+        for (tag, values) in queries.iter() {
+            if !tag_values_match(event, &tag.to_string(), values) {
+                return false;
+            }
+        }
+
+        for (key, value) in self.custom.iter() {
+            if let Some(tag_name) = key.strip_prefix('#') {
+                let values: BTreeSet<String> = match value {
+                    Value::Array(array) => array
+                        .iter()
+                        .filter_map(|v| v.as_str().map(|s| s.to_string()))
+                        .collect(),
+                    Value::String(s) => BTreeSet::from([s.clone()]),
+                    _ => BTreeSet::new(),
+                };
+                if !tag_values_match(event, tag_name, &values) {
+                    return false;
+                }
+            }
+        }
+
+        true
+    }
+}
+
+/// Check if the [`Event`] has a tag named `tag_name` whose first value is in `values`
+fn tag_values_match(event: &Event, tag_name: &str, values: &BTreeSet<String>) -> bool {
+    event.tags.iter().any(|tag| {
+        let tag: Vec<String> = tag.as_vec();
+        match (tag.first(), tag.get(1)) {
+            (Some(name), Some(value)) => name == tag_name && values.contains(value),
+            _ => false,
+        }
+    })
+}
+
+impl Filter {
+    /// Check if the tag is already covered by one of the typed fields (`#e`, `#p`, `#t` or `#r`)
+    fn has_typed_tag(&self, tag: char) -> bool {
+        match tag {
+            'e' => self.events.is_some(),
+            'p' => self.pubkeys.is_some(),
+            't' => self.hashtags.is_some(),
+            'r' => self.references.is_some(),
+            _ => false,
+        }
+    }
+
+    /// Merge typed field values with the ones of the same generic tag, if any
+    fn merge_generic_tag(&self, tag: char, mut values: Vec<String>) -> Value {
+        if let Some(generic) = SingleLetterTag::new(tag)
+            .ok()
+            .and_then(|tag| self.generic_tags.get(&tag))
+        {
+            for value in generic.iter() {
+                if !values.contains(value) {
+                    values.push(value.clone());
+                }
+            }
+        }
+        json!(values)
+    }
 }
 
 impl Serialize for Filter {
@@ -296,7 +537,7 @@ impl Serialize for Filter {
     where
         S: Serializer,
     {
-        let len: usize = 11 + self.custom.len();
+        let len: usize = 11 + self.generic_tags.len() + self.custom.len();
         let mut map = serializer.serialize_map(Some(len))?;
         if let Some(value) = &self.ids {
             map.serialize_entry("ids", &json!(value))?;
@@ -308,16 +549,23 @@ impl Serialize for Filter {
             map.serialize_entry("authors", &json!(value))?;
         }
         if let Some(value) = &self.events {
-            map.serialize_entry("#e", &json!(value))?;
+            let value: Vec<String> = value.iter().map(|id| id.to_hex()).collect();
+            map.serialize_entry("#e", &self.merge_generic_tag('e', value))?;
         }
         if let Some(value) = &self.pubkeys {
-            map.serialize_entry("#p", &json!(value))?;
+            let value: Vec<String> = value.iter().map(|p| p.to_string()).collect();
+            map.serialize_entry("#p", &self.merge_generic_tag('p', value))?;
         }
         if let Some(value) = &self.hashtags {
-            map.serialize_entry("#t", &json!(value))?;
+            map.serialize_entry("#t", &self.merge_generic_tag('t', value.clone()))?;
         }
         if let Some(value) = &self.references {
-            map.serialize_entry("#r", &json!(value))?;
+            map.serialize_entry("#r", &self.merge_generic_tag('r', value.clone()))?;
+        }
+        for (tag, values) in &self.generic_tags {
+            if !self.has_typed_tag(tag.as_char()) {
+                map.serialize_entry(&format!("#{tag}"), &json!(values))?;
+            }
         }
         if let Some(value) = &self.search {
             map.serialize_entry("search", &json!(value))?;
@@ -382,15 +630,28 @@ impl<'de> Visitor<'de> for FilterVisitor {
             f.kinds = Some(kinds);
         }
 
+        // Values that aren't event IDs or public keys are kept as generic tag values
         if let Some(value) = map.remove("#e") {
-            let events: Vec<EventId> = serde_json::from_value(value).map_err(de::Error::custom)?;
-            f.events = Some(events);
+            let values: Vec<String> = serde_json::from_value(value).map_err(de::Error::custom)?;
+            let (events, others) = split_typed_values(values, |v| EventId::from_hex(v).ok());
+            if !events.is_empty() || others.is_empty() {
+                f.events = Some(events);
+            }
+            if !others.is_empty() {
+                f.generic_tags.insert(SingleLetterTag('e'), others);
+            }
         }
 
         if let Some(value) = map.remove("#p") {
-            let pubkeys: Vec<XOnlyPublicKey> =
-                serde_json::from_value(value).map_err(de::Error::custom)?;
-            f.pubkeys = Some(pubkeys);
+            let values: Vec<String> = serde_json::from_value(value).map_err(de::Error::custom)?;
+            let (pubkeys, others) =
+                split_typed_values(values, |v| XOnlyPublicKey::from_str(v).ok());
+            if !pubkeys.is_empty() || others.is_empty() {
+                f.pubkeys = Some(pubkeys);
+            }
+            if !others.is_empty() {
+                f.generic_tags.insert(SingleLetterTag('p'), others);
+            }
         }
 
         if let Some(value) = map.remove("#t") {
@@ -423,15 +684,53 @@ impl<'de> Visitor<'de> for FilterVisitor {
             f.limit = Some(limit);
         }
 
+        // Generic tag queries (`#<single-letter>`)
+        let generic_keys: Vec<String> = map
+            .keys()
+            .filter(|k| k.len() == 2 && k.starts_with('#'))
+            .cloned()
+            .collect();
+        for key in generic_keys.into_iter() {
+            if let Ok(tag) = SingleLetterTag::from_str(&key[1..]) {
+                if let Some(Value::Array(values)) = map.get(&key) {
+                    if let Ok(values) =
+                        serde_json::from_value::<BTreeSet<String>>(Value::Array(values.clone()))
+                    {
+                        map.remove(&key);
+                        f.generic_tags.insert(tag, values);
+                    }
+                }
+            }
+        }
+
         f.custom = map;
 
         Ok(f)
     }
 }
 
+/// Split tag values into the parsed ones and the others
+fn split_typed_values<T, F>(values: Vec<String>, parse: F) -> (Vec<T>, BTreeSet<String>)
+where
+    F: Fn(&str) -> Option<T>,
+{
+    let mut parsed: Vec<T> = Vec::new();
+    let mut others: BTreeSet<String> = BTreeSet::new();
+    for value in values.into_iter() {
+        match parse(&value) {
+            Some(v) => parsed.push(v),
+            None => {
+                others.insert(value);
+            }
+        }
+    }
+    (parsed, others)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::{EventBuilder, Keys, Tag};
 
     #[test]
     fn test_filter_serialization() {
@@ -442,6 +741,130 @@ mod test {
         assert_eq!(filter.as_json(), json.to_string());
     }
 
+    #[test]
+    fn test_filter_generic_tags_serialization() {
+        let public_key = XOnlyPublicKey::from_str(
+            "379e863e8357163b5bce5d2688dc4f1dcc2d505222fb8d74db600f30535dfdfe",
+        )
+        .unwrap();
+        let filter = Filter::new()
+            .identifier("my-id")
+            .coordinate(Kind::LongFormTextNote, public_key, "article")
+            .custom_tag(SingleLetterTag::new('x').unwrap(), vec!["a", "b"]);
+        let json = r##"{"#a":["30023:379e863e8357163b5bce5d2688dc4f1dcc2d505222fb8d74db600f30535dfdfe:article"],"#d":["my-id"],"#x":["a","b"]}"##;
+        assert_eq!(filter.as_json(), json.to_string());
+        assert_eq!(Filter::from_json(json).unwrap(), filter);
+
+        // Invalid single-letter tag
+        assert_eq!(
+            SingleLetterTag::new('1'),
+            Err(Error::InvalidSingleLetterTag(String::from("1")))
+        );
+
+        // Typed and generic values of the same tag are merged
+        let filter = Filter::new()
+            .hashtag("nostr")
+            .custom_tag(SingleLetterTag('t'), vec!["bitcoin"]);
+        assert_eq!(filter.as_json(), r##"{"#t":["nostr","bitcoin"]}"##);
+
+        // Values that aren't valid public keys are kept as generic tag values
+        let filter = Filter::new()
+            .pubkey(public_key)
+            .custom_tag(SingleLetterTag('p'), vec!["not-a-public-key"]);
+        let json = r##"{"#p":["379e863e8357163b5bce5d2688dc4f1dcc2d505222fb8d74db600f30535dfdfe","not-a-public-key"]}"##;
+        assert_eq!(filter.as_json(), json);
+        assert_eq!(Filter::from_json(json).unwrap(), filter);
+        let filter = Filter::new().custom_tag(SingleLetterTag('e'), vec!["not-an-id"]);
+        assert_eq!(Filter::from_json(filter.as_json()).unwrap(), filter);
+    }
+
+    #[test]
+    fn test_match_event() {
+        let keys = Keys::generate();
+        let other = Keys::generate();
+        let event_id =
+            EventId::from_hex("70b10f70c1318967eddf12527799411b1a9780ad9c43858f5e5fcd45486a13a5")
+                .unwrap();
+        let event: Event = EventBuilder::new_text_note(
+            "test",
+            &[
+                Tag::Event(event_id, None, None),
+                Tag::PubKey(other.public_key(), None),
+                Tag::Hashtag("nostr".to_string()),
+                Tag::Identifier("my-id".to_string()),
+            ],
+        )
+        .to_event(&keys)
+        .unwrap();
+
+        // Empty filter match everything
+        assert!(Filter::new().match_event(&event));
+
+        // Ids and authors prefixes
+        assert!(Filter::new()
+            .id(&event.id.to_hex()[..8])
+            .match_event(&event));
+        assert!(!Filter::new().id("0000").match_event(&event));
+        assert!(Filter::new()
+            .author(&keys.public_key().to_string()[..10])
+            .match_event(&event));
+        assert!(!Filter::new()
+            .author(other.public_key().to_string())
+            .match_event(&event));
+
+        // Kinds
+        assert!(Filter::new()
+            .kinds(vec![Kind::Metadata, Kind::TextNote])
+            .match_event(&event));
+        assert!(!Filter::new().kind(Kind::Metadata).match_event(&event));
+
+        // Tags
+        assert!(Filter::new().event(event_id).match_event(&event));
+        assert!(Filter::new().pubkey(other.public_key()).match_event(&event));
+        assert!(!Filter::new().pubkey(keys.public_key()).match_event(&event));
+        assert!(Filter::new().hashtag("nostr").match_event(&event));
+        assert!(!Filter::new()
+            .reference("https://example.com")
+            .match_event(&event));
+
+        // Custom tags
+        let mut custom = Map::new();
+        custom.insert("#d".to_string(), json!(["my-id", "another-id"]));
+        assert!(Filter::new().custom(custom).match_event(&event));
+        let mut custom = Map::new();
+        custom.insert("#d".to_string(), json!(["another-id"]));
+        assert!(!Filter::new().custom(custom).match_event(&event));
+
+        // Generic tags
+        assert!(Filter::new().identifier("my-id").match_event(&event));
+        assert!(!Filter::new().identifier("another-id").match_event(&event));
+        assert!(Filter::new()
+            .custom_tag(SingleLetterTag('t'), vec!["bitcoin", "nostr"])
+            .match_event(&event));
+
+        // Typed and generic values of the same tag are a single OR set
+        assert!(Filter::new()
+            .hashtag("bitcoin")
+            .custom_tag(SingleLetterTag('t'), vec!["nostr"])
+            .match_event(&event));
+        assert!(Filter::new()
+            .pubkey(keys.public_key())
+            .custom_tag(SingleLetterTag('p'), vec![other.public_key().to_string()])
+            .match_event(&event));
+
+        // Since and until
+        assert!(Filter::new()
+            .since(event.created_at)
+            .until(event.created_at)
+            .match_event(&event));
+        assert!(!Filter::new()
+            .since(event.created_at + 1_i64)
+            .match_event(&event));
+        assert!(!Filter::new()
+            .until(event.created_at - 1_i64)
+            .match_event(&event));
+    }
+
     #[test]
     fn test_filter_deserialization() {
         let json = r##"{"#a":"...","search":"test","ids":["myid", "mysecondid"]}"##;
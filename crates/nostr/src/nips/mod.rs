@@ -11,12 +11,25 @@ pub mod nip04;
 pub mod nip05;
 #[cfg(feature = "nip06")]
 pub mod nip06;
+pub mod nip10;
 #[cfg(feature = "nip11")]
 pub mod nip11;
 pub mod nip13;
+#[cfg(feature = "nip17")]
+pub mod nip17;
 #[cfg(feature = "nip19")]
 pub mod nip19;
+#[cfg(feature = "nip19")]
+pub mod nip21;
 pub mod nip26;
+#[cfg(feature = "nip19")]
+pub mod nip27;
+#[cfg(feature = "nip44")]
+pub mod nip44;
 #[cfg(feature = "nip46")]
 pub mod nip46;
+#[cfg(feature = "nip49")]
+pub mod nip49;
+#[cfg(feature = "nip59")]
+pub mod nip59;
 pub mod nip65;
@@ -0,0 +1,293 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! NIP10
+//!
+//! <https://github.com/nostr-protocol/nips/blob/master/10.md>
+
+use std::collections::{BTreeMap, HashMap};
+
+use secp256k1::XOnlyPublicKey;
+
+use crate::event::tag::Marker;
+use crate::{Event, EventId, Tag};
+
+/// Thread references of a text note, parsed from its `e` and `p` tags
+///
+/// Both the marked and the deprecated positional `e` tags schemes are supported.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EventThreadRefs {
+    /// Root of the thread
+    pub root: Option<EventId>,
+    /// Event directly replied to, if it's not the root
+    pub reply: Option<EventId>,
+    /// Mentioned events
+    pub mentions: Vec<EventId>,
+    /// Mentioned public keys
+    pub public_keys: Vec<XOnlyPublicKey>,
+}
+
+impl EventThreadRefs {
+    /// Parse thread references from tags
+    pub fn from_tags(tags: &[Tag]) -> Self {
+        let mut refs = Self::default();
+
+        let mut event_tags: Vec<(EventId, Option<&Marker>)> = Vec::new();
+        for tag in tags.iter() {
+            match tag {
+                Tag::Event(event_id, _, marker) => event_tags.push((*event_id, marker.as_ref())),
+                Tag::PubKey(public_key, _) if !refs.public_keys.contains(public_key) => {
+                    refs.public_keys.push(*public_key);
+                }
+                _ => (),
+            }
+        }
+
+        let marked: bool = event_tags.iter().any(|(_, marker)| marker.is_some());
+
+        if marked {
+            for (event_id, marker) in event_tags.into_iter() {
+                match marker {
+                    Some(Marker::Root) => refs.root = Some(event_id),
+                    Some(Marker::Reply) => refs.reply = Some(event_id),
+                    _ => refs.mentions.push(event_id),
+                }
+            }
+        } else {
+            // Deprecated positional scheme: first is the root, last is the reply,
+            // the others are mentions
+            let mut event_ids = event_tags.into_iter().map(|(event_id, _)| event_id);
+            refs.root = event_ids.next();
+            refs.reply = event_ids.next_back();
+            refs.mentions = event_ids.collect();
+        }
+
+        // Direct reply to the root
+        if refs.reply == refs.root {
+            refs.reply = None;
+        }
+
+        refs
+    }
+
+    /// Event replied to (the reply or, if missing, the root)
+    pub fn parent(&self) -> Option<EventId> {
+        self.reply.or(self.root)
+    }
+
+    /// Check if the note is a reply
+    pub fn is_reply(&self) -> bool {
+        self.root.is_some() || self.reply.is_some()
+    }
+}
+
+/// Node of a thread tree: an event and its replies, ordered by `created_at`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThreadNode {
+    /// Event
+    pub event: Event,
+    /// Direct replies
+    pub replies: Vec<ThreadNode>,
+}
+
+impl ThreadNode {
+    /// Build the reply tree of `root`
+    ///
+    /// Each event is attached to its parent (see [`EventThreadRefs::parent`]) or, if the parent
+    /// is not available, to the `root`. Duplicated events are removed.
+    pub fn from_events<I>(root: Event, events: I) -> Self
+    where
+        I: IntoIterator<Item = Event>,
+    {
+        let mut events: HashMap<EventId, Event> = events
+            .into_iter()
+            .filter(|e| e.id != root.id)
+            .map(|e| (e.id, e))
+            .collect();
+
+        let mut children: BTreeMap<EventId, Vec<Event>> = BTreeMap::new();
+        let parents: Vec<(EventId, EventId)> = events
+            .values()
+            .map(|e| {
+                let parent: EventId = match e.thread_refs().parent() {
+                    Some(parent) if events.contains_key(&parent) => parent,
+                    _ => root.id,
+                };
+                (e.id, parent)
+            })
+            .collect();
+        for (id, parent) in parents.into_iter() {
+            if let Some(event) = events.remove(&id) {
+                children.entry(parent).or_default().push(event);
+            }
+        }
+
+        Self::build(root, &mut children)
+    }
+
+    fn build(event: Event, children: &mut BTreeMap<EventId, Vec<Event>>) -> Self {
+        let mut replies: Vec<Event> = children.remove(&event.id).unwrap_or_default();
+        replies.sort_by_key(|e| e.created_at);
+        Self {
+            replies: replies
+                .into_iter()
+                .map(|e| Self::build(e, children))
+                .collect(),
+            event,
+        }
+    }
+
+    /// All the events of the tree (depth-first)
+    pub fn events(&self) -> Vec<&Event> {
+        let mut events: Vec<&Event> = vec![&self.event];
+        for reply in self.replies.iter() {
+            events.extend(reply.events());
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::event::tag::UncheckedUrl;
+    use crate::{EventBuilder, Keys};
+
+    #[test]
+    fn test_positional_scheme() {
+        let root = EventId::from_slice(&[1; 32]).unwrap();
+        let mention = EventId::from_slice(&[2; 32]).unwrap();
+        let reply = EventId::from_slice(&[3; 32]).unwrap();
+
+        let refs = EventThreadRefs::from_tags(&[Tag::Event(root, None, None)]);
+        assert_eq!(refs.root, Some(root));
+        assert_eq!(refs.reply, None);
+        assert_eq!(refs.parent(), Some(root));
+
+        let refs = EventThreadRefs::from_tags(&[
+            Tag::Event(root, None, None),
+            Tag::Event(mention, None, None),
+            Tag::Event(reply, None, None),
+        ]);
+        assert_eq!(refs.root, Some(root));
+        assert_eq!(refs.reply, Some(reply));
+        assert_eq!(refs.mentions, vec![mention]);
+
+        assert!(!EventThreadRefs::from_tags(&[]).is_reply());
+    }
+
+    #[test]
+    fn test_mention_only() {
+        let mention = EventId::from_slice(&[2; 32]).unwrap();
+        let refs = EventThreadRefs::from_tags(&[Tag::Event(
+            mention,
+            None,
+            Some(Marker::Custom(String::from("mention"))),
+        )]);
+        assert!(!refs.is_reply());
+        assert_eq!(refs.parent(), None);
+        assert_eq!(refs.mentions, vec![mention]);
+    }
+
+    #[test]
+    fn test_reply_to() {
+        let alice = Keys::generate();
+        let bob = Keys::generate();
+        let carol = Keys::generate();
+        let relay_url = UncheckedUrl::from_str("wss://relay.damus.io").unwrap();
+
+        let root = EventBuilder::new_text_note("root", &[])
+            .to_event(&alice)
+            .unwrap();
+
+        // Direct reply to the root
+        let reply = EventBuilder::reply_to("reply", &root, None, Some(relay_url.clone()))
+            .to_event(&bob)
+            .unwrap();
+        assert!(reply.tags.contains(&Tag::Event(
+            root.id,
+            Some(relay_url.clone()),
+            Some(Marker::Root)
+        )));
+        let refs = reply.thread_refs();
+        assert_eq!(refs.root, Some(root.id));
+        assert_eq!(refs.reply, None);
+        assert_eq!(refs.public_keys, vec![alice.public_key()]);
+
+        // Nested reply: root inherited from the parent, p-tags inherited
+        let nested = EventBuilder::reply_to("nested", &reply, None, None)
+            .to_event(&carol)
+            .unwrap();
+        let refs = nested.thread_refs();
+        assert_eq!(refs.root, Some(root.id));
+        assert_eq!(refs.reply, Some(reply.id));
+        assert_eq!(refs.parent(), Some(reply.id));
+        assert_eq!(refs.public_keys, vec![alice.public_key(), bob.public_key()]);
+
+        // Mentions are kept apart
+        let mention = EventId::from_slice(&[2; 32]).unwrap();
+        let tags = [
+            nested.tags.clone(),
+            vec![Tag::Event(
+                mention,
+                None,
+                Some(Marker::Custom(String::from("mention"))),
+            )],
+        ]
+        .concat();
+        let refs = EventThreadRefs::from_tags(&tags);
+        assert_eq!(refs.reply, Some(reply.id));
+        assert_eq!(refs.mentions, vec![mention]);
+    }
+
+    #[test]
+    fn test_thread_tree() {
+        let keys = Keys::generate();
+        let root = EventBuilder::new_text_note("root", &[])
+            .to_event(&keys)
+            .unwrap();
+        let first = EventBuilder::reply_to("first", &root, None, None)
+            .custom_created_at(root.created_at + 10_u64)
+            .to_event(&keys)
+            .unwrap();
+        let second = EventBuilder::reply_to("second", &root, None, None)
+            .custom_created_at(root.created_at + 5_u64)
+            .to_event(&keys)
+            .unwrap();
+        let nested = EventBuilder::reply_to("nested", &first, None, None)
+            .custom_created_at(root.created_at + 20_u64)
+            .to_event(&keys)
+            .unwrap();
+        let missing_parent = EventBuilder::new_text_note(
+            "orphan",
+            &[Tag::Event(
+                EventId::from_slice(&[9; 32]).unwrap(),
+                None,
+                None,
+            )],
+        )
+        .custom_created_at(root.created_at + 30_u64)
+        .to_event(&keys)
+        .unwrap();
+
+        let tree = ThreadNode::from_events(
+            root.clone(),
+            vec![
+                nested.clone(),
+                first.clone(),
+                second.clone(),
+                first.clone(),
+                missing_parent.clone(),
+            ],
+        );
+        assert_eq!(tree.event, root);
+        assert_eq!(tree.replies.len(), 3);
+        assert_eq!(tree.replies[0].event, second);
+        assert_eq!(tree.replies[1].event, first);
+        assert_eq!(tree.replies[1].replies[0].event, nested);
+        assert_eq!(tree.replies[2].event, missing_parent);
+        assert_eq!(tree.events().len(), 5);
+    }
+}
@@ -48,6 +48,33 @@ pub struct RelayInformationDocument {
     pub software: Option<String>,
     /// Software version
     pub version: Option<String>,
+    /// Server limitations
+    pub limitation: Option<Limitation>,
+}
+
+/// Server limitations imposed by the relay
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Limitation {
+    /// Maximum number of bytes for incoming JSON
+    pub max_message_length: Option<i32>,
+    /// Maximum number of active subscriptions on a single connection
+    pub max_subscriptions: Option<i32>,
+    /// Maximum number of filter values in each subscription
+    pub max_filters: Option<i32>,
+    /// Maximum `limit` value in filters
+    pub max_limit: Option<i32>,
+    /// Maximum length of subscription id
+    pub max_subid_length: Option<i32>,
+    /// Maximum number of elements in the tags list
+    pub max_event_tags: Option<i32>,
+    /// Maximum number of characters in the content field
+    pub max_content_length: Option<i32>,
+    /// Minimum PoW difficulty for new events
+    pub min_pow_difficulty: Option<i32>,
+    /// Relay requires NIP-42 authentication
+    pub auth_required: Option<bool>,
+    /// Relay requires payment
+    pub payment_required: Option<bool>,
 }
 
 impl RelayInformationDocument {
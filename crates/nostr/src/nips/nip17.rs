@@ -0,0 +1,230 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! NIP17
+//!
+//! <https://github.com/nostr-protocol/nips/blob/master/17.md>
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use secp256k1::XOnlyPublicKey;
+use url::Url;
+
+use super::nip59::UnwrappedGift;
+use crate::{Event, EventId, Kind, Tag, Timestamp};
+
+/// `NIP17` error
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Not a private direct message
+    #[error("rumor is not a private direct message (kind 14)")]
+    NotPrivateDirectMessage,
+}
+
+/// Extract the relay urls from an inbox relays event (kind 10050)
+pub fn extract_relay_list(event: &Event) -> Vec<Url> {
+    if event.kind != Kind::InboxRelays {
+        return Vec::new();
+    }
+
+    event
+        .tags
+        .iter()
+        .filter_map(|t| match t {
+            Tag::Relay(url) => Some(url.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Private direct message, extracted from a gift wrap
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrivateDirectMessage {
+    /// Rumor id
+    pub id: EventId,
+    /// Sender
+    pub sender: XOnlyPublicKey,
+    /// Receivers
+    pub receivers: Vec<XOnlyPublicKey>,
+    /// Timestamp of the rumor
+    pub created_at: Timestamp,
+    /// Conversation title
+    pub subject: Option<String>,
+    /// Message replied to
+    pub reply_to: Option<EventId>,
+    /// Message
+    pub content: String,
+}
+
+impl TryFrom<UnwrappedGift> for PrivateDirectMessage {
+    type Error = Error;
+
+    fn try_from(gift: UnwrappedGift) -> Result<Self, Self::Error> {
+        let rumor = gift.rumor;
+        if rumor.kind != Kind::PrivateDirectMessage {
+            return Err(Error::NotPrivateDirectMessage);
+        }
+
+        let mut receivers: Vec<XOnlyPublicKey> = Vec::new();
+        let mut subject: Option<String> = None;
+        let mut reply_to: Option<EventId> = None;
+        for tag in rumor.tags.into_iter() {
+            match tag {
+                Tag::PubKey(public_key, ..) => receivers.push(public_key),
+                Tag::Subject(s) => subject = Some(s),
+                Tag::Event(event_id, ..) => reply_to = Some(event_id),
+                _ => (),
+            }
+        }
+
+        Ok(Self {
+            id: rumor.id,
+            sender: gift.sender,
+            receivers,
+            created_at: rumor.created_at,
+            subject,
+            reply_to,
+            content: rumor.content,
+        })
+    }
+}
+
+impl PrivateDirectMessage {
+    /// Sender and receivers of the message
+    pub fn participants(&self) -> BTreeSet<XOnlyPublicKey> {
+        let mut participants: BTreeSet<XOnlyPublicKey> = self.receivers.iter().copied().collect();
+        participants.insert(self.sender);
+        participants
+    }
+}
+
+/// Conversation between a set of participants
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conversation {
+    /// Participants (sender and receivers)
+    pub participants: BTreeSet<XOnlyPublicKey>,
+    /// Messages, ordered by timestamp
+    pub messages: Vec<PrivateDirectMessage>,
+}
+
+impl Conversation {
+    /// Get the current conversation title (the subject of the latest message that set one)
+    pub fn subject(&self) -> Option<&str> {
+        self.messages
+            .iter()
+            .rev()
+            .find_map(|msg| msg.subject.as_deref())
+    }
+
+    /// Timestamp of the last message
+    pub fn last_activity(&self) -> Option<Timestamp> {
+        self.messages.last().map(|msg| msg.created_at)
+    }
+}
+
+/// Group private direct messages into conversations
+///
+/// Duplicated messages are removed. Conversations are sorted by last activity (newest first).
+pub fn group_conversations<I>(messages: I) -> Vec<Conversation>
+where
+    I: IntoIterator<Item = PrivateDirectMessage>,
+{
+    let mut seen: HashSet<EventId> = HashSet::new();
+    let mut map: BTreeMap<BTreeSet<XOnlyPublicKey>, Vec<PrivateDirectMessage>> = BTreeMap::new();
+    for msg in messages.into_iter() {
+        if seen.insert(msg.id) {
+            map.entry(msg.participants()).or_default().push(msg);
+        }
+    }
+
+    let mut conversations: Vec<Conversation> = map
+        .into_iter()
+        .map(|(participants, mut messages)| {
+            messages.sort_by_key(|msg| msg.created_at);
+            Conversation {
+                participants,
+                messages,
+            }
+        })
+        .collect();
+    conversations.sort_by_key(|c| Reverse(c.last_activity()));
+    conversations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::tag::Marker;
+    use crate::nips::nip59;
+    use crate::{EventBuilder, Keys};
+
+    #[test]
+    fn test_private_msg_conversations() {
+        let alice = Keys::generate();
+        let bob = Keys::generate();
+        let carol = Keys::generate();
+
+        let rumor = EventBuilder::private_msg_rumor(&[bob.public_key()], "hello bob", None)
+            .to_unsigned_event(alice.public_key());
+        let wrap_bob =
+            EventBuilder::gift_wrap(&alice, &bob.public_key(), rumor.clone(), None).unwrap();
+        let wrap_alice =
+            EventBuilder::gift_wrap(&alice, &alice.public_key(), rumor.clone(), None).unwrap();
+
+        // Both copies carry the same rumor
+        let msg_bob =
+            PrivateDirectMessage::try_from(nip59::extract_rumor(&bob, &wrap_bob).unwrap()).unwrap();
+        let msg_alice =
+            PrivateDirectMessage::try_from(nip59::extract_rumor(&alice, &wrap_alice).unwrap())
+                .unwrap();
+        assert_eq!(msg_bob, msg_alice);
+        assert_eq!(msg_bob.sender, alice.public_key());
+        assert_eq!(msg_bob.receivers, vec![bob.public_key()]);
+        assert_eq!(msg_bob.reply_to, None);
+
+        let reply =
+            EventBuilder::private_msg_rumor(&[alice.public_key()], "hi alice", Some(msg_bob.id))
+                .to_unsigned_event(bob.public_key());
+        assert!(reply
+            .tags
+            .contains(&Tag::Event(msg_bob.id, None, Some(Marker::Reply))));
+        let wrap = EventBuilder::gift_wrap(&bob, &alice.public_key(), reply, None).unwrap();
+        let reply =
+            PrivateDirectMessage::try_from(nip59::extract_rumor(&alice, &wrap).unwrap()).unwrap();
+        assert_eq!(reply.reply_to, Some(msg_bob.id));
+
+        let group = EventBuilder::private_msg_rumor(
+            &[bob.public_key(), carol.public_key()],
+            "hi all",
+            None,
+        )
+        .to_unsigned_event(alice.public_key());
+        let wrap = EventBuilder::gift_wrap(&alice, &carol.public_key(), group, None).unwrap();
+        let group =
+            PrivateDirectMessage::try_from(nip59::extract_rumor(&carol, &wrap).unwrap()).unwrap();
+
+        let conversations = group_conversations(vec![msg_bob, msg_alice, reply, group]);
+        assert_eq!(conversations.len(), 2);
+        let direct = conversations
+            .iter()
+            .find(|c| c.participants.len() == 2)
+            .unwrap();
+        assert_eq!(direct.messages.len(), 2);
+        assert!(direct.participants.contains(&alice.public_key()));
+        assert!(direct.participants.contains(&bob.public_key()));
+    }
+
+    #[test]
+    fn test_extract_relay_list() {
+        let keys = Keys::generate();
+        let relays = vec![
+            Url::parse("wss://inbox.nostr.wine").unwrap(),
+            Url::parse("wss://relay.damus.io").unwrap(),
+        ];
+        let event = EventBuilder::inbox_relays(relays.clone())
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(extract_relay_list(&event), relays);
+    }
+}
@@ -13,9 +13,12 @@ use secp256k1::{SecretKey, XOnlyPublicKey};
 use serde::{Deserialize, Serialize};
 
 use crate::event::id::{self, EventId};
-use crate::Kind;
+#[cfg(feature = "nip49")]
+use crate::nips::nip49::{self, EncryptedSecretKey};
+use crate::{Kind, Profile};
 
 pub const PREFIX_BECH32_SECRET_KEY: &str = "nsec";
+pub const PREFIX_BECH32_ENCRYPTED_SECRET_KEY: &str = "ncryptsec";
 pub const PREFIX_BECH32_PUBLIC_KEY: &str = "npub";
 pub const PREFIX_BECH32_NOTE_ID: &str = "note";
 pub const PREFIX_BECH32_CHANNEL: &str = "nchannel";
@@ -85,6 +88,25 @@ impl FromBech32 for SecretKey {
     }
 }
 
+#[cfg(feature = "nip49")]
+impl FromBech32 for EncryptedSecretKey {
+    type Err = nip49::Error;
+    fn from_bech32<S>(encrypted_secret_key: S) -> Result<Self, Self::Err>
+    where
+        S: Into<String>,
+    {
+        let (hrp, data, checksum) =
+            bech32::decode(&encrypted_secret_key.into()).map_err(Error::Bech32)?;
+
+        if hrp != PREFIX_BECH32_ENCRYPTED_SECRET_KEY || checksum != Variant::Bech32 {
+            return Err(Error::WrongPrefixOrVariant.into());
+        }
+
+        let data = Vec::<u8>::from_base32(&data).map_err(Error::Bech32)?;
+        Self::from_slice(data.as_slice())
+    }
+}
+
 impl FromBech32 for XOnlyPublicKey {
     type Err = Error;
     fn from_bech32<S>(public_key: S) -> Result<Self, Self::Err>
@@ -150,6 +172,20 @@ impl ToBech32 for SecretKey {
     }
 }
 
+#[cfg(feature = "nip49")]
+impl ToBech32 for EncryptedSecretKey {
+    type Err = Error;
+
+    fn to_bech32(&self) -> Result<String, Self::Err> {
+        let data = self.as_vec().to_base32();
+        Ok(bech32::encode(
+            PREFIX_BECH32_ENCRYPTED_SECRET_KEY,
+            data,
+            Variant::Bech32,
+        )?)
+    }
+}
+
 // Note ID
 impl ToBech32 for EventId {
     type Err = Error;
@@ -348,6 +384,64 @@ impl ToBech32 for ParameterizedReplaceableEvent {
     }
 }
 
+/// Decoded bech32 entity
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Nip19 {
+    /// Secret key (`nsec`)
+    Secret(SecretKey),
+    /// Public key (`npub`)
+    Pubkey(XOnlyPublicKey),
+    /// Profile (`nprofile`)
+    Profile(Profile),
+    /// Event ID (`note`)
+    EventId(EventId),
+    /// Event (`nevent`)
+    Event(Nip19Event),
+    /// Parameterized replaceable event coordinate (`naddr`)
+    Coordinate(ParameterizedReplaceableEvent),
+}
+
+impl FromBech32 for Nip19 {
+    type Err = Error;
+    fn from_bech32<S>(s: S) -> Result<Self, Self::Err>
+    where
+        S: Into<String>,
+    {
+        let s: String = s.into();
+        let prefix: &str = match s.rfind('1') {
+            Some(pos) => &s[..pos],
+            None => return Err(Error::WrongPrefixOrVariant),
+        };
+
+        match prefix.to_lowercase().as_str() {
+            PREFIX_BECH32_SECRET_KEY => Ok(Self::Secret(SecretKey::from_bech32(s)?)),
+            PREFIX_BECH32_PUBLIC_KEY => Ok(Self::Pubkey(XOnlyPublicKey::from_bech32(s)?)),
+            PREFIX_BECH32_PROFILE => Ok(Self::Profile(Profile::from_bech32(s)?)),
+            PREFIX_BECH32_NOTE_ID => Ok(Self::EventId(EventId::from_bech32(s)?)),
+            PREFIX_BECH32_EVENT => Ok(Self::Event(Nip19Event::from_bech32(s)?)),
+            PREFIX_BECH32_PARAMETERIZED_REPLACEABLE_EVENT => Ok(Self::Coordinate(
+                ParameterizedReplaceableEvent::from_bech32(s)?,
+            )),
+            _ => Err(Error::WrongPrefixOrVariant),
+        }
+    }
+}
+
+impl ToBech32 for Nip19 {
+    type Err = Error;
+
+    fn to_bech32(&self) -> Result<String, Self::Err> {
+        match self {
+            Self::Secret(secret_key) => secret_key.to_bech32(),
+            Self::Pubkey(public_key) => public_key.to_bech32(),
+            Self::Profile(profile) => profile.to_bech32(),
+            Self::EventId(event_id) => event_id.to_bech32(),
+            Self::Event(event) => event.to_bech32(),
+            Self::Coordinate(coordinate) => coordinate.to_bech32(),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::str::FromStr;
@@ -0,0 +1,106 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! NIP21
+//!
+//! <https://github.com/nostr-protocol/nips/blob/master/21.md>
+
+use std::fmt;
+use std::str::FromStr;
+
+use super::nip19::{self, FromBech32, Nip19, ToBech32};
+
+/// NIP21 URI Scheme
+pub const NOSTR_URI_SCHEME: &str = "nostr";
+
+/// `NIP21` error
+#[derive(Debug, Eq, PartialEq, thiserror::Error)]
+pub enum Error {
+    /// Invalid URI scheme
+    #[error("invalid uri scheme")]
+    InvalidURIScheme,
+    /// Secret keys can't be shared
+    #[error("secret keys are not allowed in nostr URIs")]
+    SecretKeyNotAllowed,
+    /// NIP19 error
+    #[error(transparent)]
+    NIP19(#[from] nip19::Error),
+}
+
+/// `nostr:` URI
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct NostrURI {
+    entity: Nip19,
+}
+
+impl NostrURI {
+    /// New `nostr:` URI from [`Nip19`] entity
+    pub fn new(entity: Nip19) -> Result<Self, Error> {
+        if let Nip19::Secret(..) = entity {
+            return Err(Error::SecretKeyNotAllowed);
+        }
+        Ok(Self { entity })
+    }
+
+    /// Get [`Nip19`] entity
+    pub fn entity(&self) -> &Nip19 {
+        &self.entity
+    }
+
+    /// Consume the URI and get the [`Nip19`] entity
+    pub fn into_entity(self) -> Nip19 {
+        self.entity
+    }
+}
+
+impl FromStr for NostrURI {
+    type Err = Error;
+    fn from_str(uri: &str) -> Result<Self, Self::Err> {
+        let bech32: &str = uri
+            .strip_prefix(NOSTR_URI_SCHEME)
+            .and_then(|s| s.strip_prefix(':'))
+            .ok_or(Error::InvalidURIScheme)?;
+        Self::new(Nip19::from_bech32(bech32)?)
+    }
+}
+
+impl fmt::Display for NostrURI {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bech32: String = self.entity.to_bech32().map_err(|_| fmt::Error)?;
+        write!(f, "{NOSTR_URI_SCHEME}:{bech32}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use secp256k1::XOnlyPublicKey;
+
+    use super::*;
+    use crate::Keys;
+
+    #[test]
+    fn test_nostr_uri() {
+        let uri = "nostr:npub14f8usejl26twx0dhuxjh9cas7keav9vr0v8nvtwtrjqx3vycc76qqh9nsy";
+        let nostr_uri = NostrURI::from_str(uri).unwrap();
+        assert_eq!(
+            nostr_uri.entity(),
+            &Nip19::Pubkey(
+                XOnlyPublicKey::from_str(
+                    "aa4fc8665f5696e33db7e1a572e3b0f5b3d615837b0f362dcb1c8068b098c7b4"
+                )
+                .unwrap()
+            )
+        );
+        assert_eq!(nostr_uri.to_string(), uri);
+
+        let nsec = Keys::generate().secret_key().unwrap().to_bech32().unwrap();
+        assert_eq!(
+            NostrURI::from_str(&format!("nostr:{nsec}")),
+            Err(Error::SecretKeyNotAllowed)
+        );
+        assert_eq!(
+            NostrURI::from_str("npub14f8usejl26twx0dhuxjh9cas7keav9vr0v8nvtwtrjqx3vycc76qqh9nsy"),
+            Err(Error::InvalidURIScheme)
+        );
+    }
+}
@@ -0,0 +1,156 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! NIP27
+//!
+//! <https://github.com/nostr-protocol/nips/blob/master/27.md>
+
+use std::str::FromStr;
+
+use bech32::Variant;
+use url::Url;
+
+use super::nip19::Nip19;
+use super::nip21::{NostrURI, NOSTR_URI_SCHEME};
+
+const LEADING_PUNCTUATION: &[char] = &['(', '[', '"', '\''];
+const TRAILING_PUNCTUATION: &[char] = &['.', ',', ';', ':', '!', '?', ')', ']', '"', '\''];
+
+/// Segment of a text note content
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Token {
+    /// Plain text
+    Text(String),
+    /// `nostr:` URI reference
+    Nostr(Nip19),
+    /// Hashtag (without `#`)
+    Hashtag(String),
+    /// URL
+    Url(Url),
+    /// Lightning invoice (bolt11, with a valid bech32 checksum)
+    Bolt11(String),
+}
+
+/// Split content into [`Token`]s
+///
+/// Trailing punctuation and enclosing brackets are left in the surrounding text.
+/// Concatenating the segments gives back the content (except for the `lightning:` prefix of the invoices).
+pub fn tokenize<S>(content: S) -> Vec<Token>
+where
+    S: AsRef<str>,
+{
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut text = String::new();
+    let mut rest: &str = content.as_ref();
+
+    while !rest.is_empty() {
+        let ws_end: usize = rest
+            .find(|c: char| !c.is_whitespace())
+            .unwrap_or(rest.len());
+        text.push_str(&rest[..ws_end]);
+        rest = &rest[ws_end..];
+
+        let word_end: usize = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        let word: &str = &rest[..word_end];
+        rest = &rest[word_end..];
+
+        let start: usize = word.len() - word.trim_start_matches(LEADING_PUNCTUATION).len();
+        let end: usize = word.trim_end_matches(TRAILING_PUNCTUATION).len();
+        let token: Option<Token> = if start < end {
+            parse_token(&word[start..end])
+        } else {
+            None
+        };
+        match token {
+            Some(token) => {
+                text.push_str(&word[..start]);
+                if !text.is_empty() {
+                    tokens.push(Token::Text(std::mem::take(&mut text)));
+                }
+                tokens.push(token);
+                text.push_str(&word[end..]);
+            }
+            None => text.push_str(word),
+        }
+    }
+
+    if !text.is_empty() {
+        tokens.push(Token::Text(text));
+    }
+
+    tokens
+}
+
+fn parse_token(word: &str) -> Option<Token> {
+    if word.starts_with(NOSTR_URI_SCHEME) {
+        return NostrURI::from_str(word)
+            .ok()
+            .map(|uri| Token::Nostr(uri.into_entity()));
+    }
+
+    if let Some(hashtag) = word.strip_prefix('#') {
+        if !hashtag.is_empty()
+            && hashtag
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+        {
+            return Some(Token::Hashtag(hashtag.to_string()));
+        }
+        return None;
+    }
+
+    if word.starts_with("https://") || word.starts_with("http://") {
+        return Url::parse(word).ok().map(Token::Url);
+    }
+
+    let invoice: &str = word
+        .strip_prefix("lightning:")
+        .or_else(|| word.strip_prefix("LIGHTNING:"))
+        .unwrap_or(word);
+    let lowercase: String = invoice.to_lowercase();
+    if lowercase.starts_with("lnbc") || lowercase.starts_with("lntb") {
+        if let Ok((_, _, Variant::Bech32)) = bech32::decode(invoice) {
+            return Some(Token::Bolt11(invoice.to_string()));
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use secp256k1::XOnlyPublicKey;
+
+    use super::*;
+
+    #[test]
+    fn test_tokenize() {
+        let invoice = "lnbc1pvjluezsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygspp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdpl2pkx2ctnv5sxxmmwwd5kgetjypeh2ursdae8g6twvus8g6rfwvs8qun0dfjkxaq9qrsgq357wnc5r2ueh7ck6q93dj32dlqnls087fxdwk8qakdyafkq3yap9us6v52vjjsrvywa6rt52cm9r9zqt8r2t7mlcwspyetp5h2tztugp9lfyql";
+        let content = format!("Hello nostr:npub14f8usejl26twx0dhuxjh9cas7keav9vr0v8nvtwtrjqx3vycc76qqh9nsy, check #nostr (https://example.com/path?q=1).\nZap me: lightning:{invoice} and nostr:nsec1invalid");
+        let public_key = XOnlyPublicKey::from_str(
+            "aa4fc8665f5696e33db7e1a572e3b0f5b3d615837b0f362dcb1c8068b098c7b4",
+        )
+        .unwrap();
+        assert_eq!(
+            tokenize(content),
+            vec![
+                Token::Text(String::from("Hello ")),
+                Token::Nostr(Nip19::Pubkey(public_key)),
+                Token::Text(String::from(", check ")),
+                Token::Hashtag(String::from("nostr")),
+                Token::Text(String::from(" (")),
+                Token::Url(Url::parse("https://example.com/path?q=1").unwrap()),
+                Token::Text(String::from(").\nZap me: ")),
+                Token::Bolt11(String::from(invoice)),
+                Token::Text(String::from(" and nostr:nsec1invalid")),
+            ]
+        );
+
+        assert_eq!(tokenize(""), Vec::new());
+        assert_eq!(
+            tokenize("lnbc10u1pjw5tyc"),
+            vec![Token::Text(String::from("lnbc10u1pjw5tyc"))]
+        );
+        assert_eq!(tokenize("a#b #"), vec![Token::Text(String::from("a#b #"))]);
+    }
+}
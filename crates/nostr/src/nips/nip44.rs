@@ -0,0 +1,674 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! NIP44
+//!
+//! <https://github.com/nostr-protocol/nips/blob/master/44.md>
+
+use std::fmt;
+
+use base64::engine::{general_purpose, Engine};
+use bitcoin_hashes::hmac::{Hmac, HmacEngine};
+use bitcoin_hashes::{sha256, Hash, HashEngine};
+use chacha20::cipher::{KeyIvInit, StreamCipher};
+use chacha20::ChaCha20;
+use secp256k1::{ecdh, Parity, PublicKey, SecretKey, XOnlyPublicKey};
+
+const SALT: &[u8] = b"nip44-v2";
+const MIN_PLAINTEXT_SIZE: usize = 1;
+const MAX_PLAINTEXT_SIZE: usize = 65535;
+const MIN_PAYLOAD_SIZE: usize = 132;
+const MAX_PAYLOAD_SIZE: usize = 87472;
+const MIN_DECODED_PAYLOAD_SIZE: usize = 99;
+const MAX_DECODED_PAYLOAD_SIZE: usize = 65603;
+
+/// `NIP44` error
+#[derive(Debug, Eq, PartialEq, thiserror::Error)]
+pub enum Error {
+    /// Unknown encryption version
+    #[error("unknown version: {0}")]
+    UnknownVersion(u8),
+    /// Version not supported (payload starting with `#`)
+    #[error("encryption version not supported")]
+    VersionNotSupported,
+    /// Invalid plaintext length
+    #[error("invalid plaintext length: {0}")]
+    InvalidPlaintextLength(usize),
+    /// Invalid payload length
+    #[error("invalid payload length: {0}")]
+    InvalidPayloadLength(usize),
+    /// Invalid padding
+    #[error("invalid padding")]
+    InvalidPadding,
+    /// Invalid HMAC
+    #[error("invalid MAC")]
+    InvalidMac,
+    /// Error while decoding from base64
+    #[error("error while decoding from base64")]
+    Base64Decode,
+    /// Error while encoding to UTF-8
+    #[error("error while encoding to UTF-8")]
+    Utf8Encode,
+    /// Invalid conversation key length
+    #[error("invalid conversation key length")]
+    InvalidConversationKey,
+}
+
+/// Encryption version
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Version {
+    /// Version 2 (ChaCha20 + HMAC-SHA256)
+    #[default]
+    V2 = 0x02,
+}
+
+impl Version {
+    /// Get version as byte
+    pub fn as_u8(&self) -> u8 {
+        *self as u8
+    }
+}
+
+impl TryFrom<u8> for Version {
+    type Error = Error;
+
+    fn try_from(version: u8) -> Result<Self, Self::Error> {
+        match version {
+            0x02 => Ok(Self::V2),
+            v => Err(Error::UnknownVersion(v)),
+        }
+    }
+}
+
+/// Conversation key
+///
+/// Derived from the ECDH shared point between two keys: it's the same for both the parties.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct ConversationKey([u8; 32]);
+
+impl fmt::Debug for ConversationKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ConversationKey(...)")
+    }
+}
+
+impl ConversationKey {
+    /// Derive conversation key (HKDF-extract of the shared x coordinate, salted with `nip44-v2`)
+    pub fn derive(secret_key: &SecretKey, public_key: &XOnlyPublicKey) -> Self {
+        let public_key: PublicKey = public_key.public_key(Parity::Even);
+        let shared_point: [u8; 64] = ecdh::shared_secret_point(&public_key, secret_key);
+        Self(hmac_sha256(SALT, &[&shared_point[..32]]))
+    }
+
+    /// Construct from bytes
+    pub fn from_slice(slice: &[u8]) -> Result<Self, Error> {
+        let key: [u8; 32] = slice
+            .try_into()
+            .map_err(|_| Error::InvalidConversationKey)?;
+        Ok(Self(key))
+    }
+
+    /// Get as bytes
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+struct MessageKeys {
+    chacha_key: [u8; 32],
+    chacha_nonce: [u8; 12],
+    hmac_key: [u8; 32],
+}
+
+impl MessageKeys {
+    /// HKDF-expand of the conversation key, with the nonce as info
+    fn new(conversation_key: &ConversationKey, nonce: &[u8; 32]) -> Self {
+        let mut okm: Vec<u8> = Vec::with_capacity(96);
+        let mut previous: Vec<u8> = Vec::new();
+        for counter in 1u8..=3 {
+            let block: [u8; 32] =
+                hmac_sha256(conversation_key.as_bytes(), &[&previous, nonce, &[counter]]);
+            okm.extend_from_slice(&block);
+            previous = block.to_vec();
+        }
+
+        let mut chacha_key = [0u8; 32];
+        let mut chacha_nonce = [0u8; 12];
+        let mut hmac_key = [0u8; 32];
+        chacha_key.copy_from_slice(&okm[0..32]);
+        chacha_nonce.copy_from_slice(&okm[32..44]);
+        hmac_key.copy_from_slice(&okm[44..76]);
+
+        Self {
+            chacha_key,
+            chacha_nonce,
+            hmac_key,
+        }
+    }
+}
+
+/// Encrypt
+pub fn encrypt<T>(
+    secret_key: &SecretKey,
+    public_key: &XOnlyPublicKey,
+    content: T,
+    version: Version,
+) -> Result<String, Error>
+where
+    T: AsRef<[u8]>,
+{
+    match version {
+        Version::V2 => {
+            let conversation_key = ConversationKey::derive(secret_key, public_key);
+            let nonce: [u8; 32] = secp256k1::rand::random();
+            encrypt_with_nonce(&conversation_key, content.as_ref(), &nonce)
+        }
+    }
+}
+
+/// Decrypt
+pub fn decrypt<S>(
+    secret_key: &SecretKey,
+    public_key: &XOnlyPublicKey,
+    payload: S,
+) -> Result<String, Error>
+where
+    S: AsRef<str>,
+{
+    let conversation_key = ConversationKey::derive(secret_key, public_key);
+    decrypt_with_conversation_key(&conversation_key, payload)
+}
+
+/// Encrypt with [`ConversationKey`] (version 2)
+pub fn encrypt_with_conversation_key<T>(
+    conversation_key: &ConversationKey,
+    content: T,
+) -> Result<String, Error>
+where
+    T: AsRef<[u8]>,
+{
+    let nonce: [u8; 32] = secp256k1::rand::random();
+    encrypt_with_nonce(conversation_key, content.as_ref(), &nonce)
+}
+
+/// Decrypt with [`ConversationKey`]
+pub fn decrypt_with_conversation_key<S>(
+    conversation_key: &ConversationKey,
+    payload: S,
+) -> Result<String, Error>
+where
+    S: AsRef<str>,
+{
+    let payload: &str = payload.as_ref();
+
+    if payload.starts_with('#') {
+        return Err(Error::VersionNotSupported);
+    }
+
+    if !(MIN_PAYLOAD_SIZE..=MAX_PAYLOAD_SIZE).contains(&payload.len()) {
+        return Err(Error::InvalidPayloadLength(payload.len()));
+    }
+
+    let data: Vec<u8> = general_purpose::STANDARD
+        .decode(payload)
+        .map_err(|_| Error::Base64Decode)?;
+
+    if !(MIN_DECODED_PAYLOAD_SIZE..=MAX_DECODED_PAYLOAD_SIZE).contains(&data.len()) {
+        return Err(Error::InvalidPayloadLength(data.len()));
+    }
+
+    match Version::try_from(data[0])? {
+        Version::V2 => {
+            let len: usize = data.len();
+            let mut nonce = [0u8; 32];
+            nonce.copy_from_slice(&data[1..33]);
+            let ciphertext: &[u8] = &data[33..len - 32];
+            let mac: &[u8] = &data[len - 32..];
+
+            let keys = MessageKeys::new(conversation_key, &nonce);
+
+            let expected: [u8; 32] = hmac_sha256(&keys.hmac_key, &[&nonce, ciphertext]);
+            if !constant_time_eq(&expected, mac) {
+                return Err(Error::InvalidMac);
+            }
+
+            let mut padded: Vec<u8> = ciphertext.to_vec();
+            let mut cipher = ChaCha20::new(&keys.chacha_key.into(), &keys.chacha_nonce.into());
+            cipher.apply_keystream(&mut padded);
+
+            let plaintext: Vec<u8> = unpad(&padded)?;
+            String::from_utf8(plaintext).map_err(|_| Error::Utf8Encode)
+        }
+    }
+}
+
+fn encrypt_with_nonce(
+    conversation_key: &ConversationKey,
+    plaintext: &[u8],
+    nonce: &[u8; 32],
+) -> Result<String, Error> {
+    let keys = MessageKeys::new(conversation_key, nonce);
+
+    let mut buffer: Vec<u8> = pad(plaintext)?;
+    let mut cipher = ChaCha20::new(&keys.chacha_key.into(), &keys.chacha_nonce.into());
+    cipher.apply_keystream(&mut buffer);
+
+    let mac: [u8; 32] = hmac_sha256(&keys.hmac_key, &[nonce, &buffer]);
+
+    let mut payload: Vec<u8> = Vec::with_capacity(1 + 32 + buffer.len() + 32);
+    payload.push(Version::V2.as_u8());
+    payload.extend_from_slice(nonce);
+    payload.extend_from_slice(&buffer);
+    payload.extend_from_slice(&mac);
+
+    Ok(general_purpose::STANDARD.encode(payload))
+}
+
+/// Calculate the padded length of a plaintext
+fn calc_padded_len(len: usize) -> usize {
+    if len <= 32 {
+        return 32;
+    }
+    let next_power: usize = 1 << (usize::BITS - (len - 1).leading_zeros());
+    let chunk: usize = if next_power <= 256 {
+        32
+    } else {
+        next_power / 8
+    };
+    chunk * ((len - 1) / chunk + 1)
+}
+
+/// Prefix the plaintext with its length (u16, big-endian) and pad with zeros
+fn pad(plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+    let len: usize = plaintext.len();
+    if !(MIN_PLAINTEXT_SIZE..=MAX_PLAINTEXT_SIZE).contains(&len) {
+        return Err(Error::InvalidPlaintextLength(len));
+    }
+
+    let mut padded: Vec<u8> = Vec::with_capacity(2 + calc_padded_len(len));
+    padded.extend_from_slice(&(len as u16).to_be_bytes());
+    padded.extend_from_slice(plaintext);
+    padded.resize(2 + calc_padded_len(len), 0);
+    Ok(padded)
+}
+
+fn unpad(padded: &[u8]) -> Result<Vec<u8>, Error> {
+    if padded.len() < 2 {
+        return Err(Error::InvalidPadding);
+    }
+
+    let len: usize = u16::from_be_bytes([padded[0], padded[1]]) as usize;
+    if len < MIN_PLAINTEXT_SIZE || padded.len() != 2 + calc_padded_len(len) {
+        return Err(Error::InvalidPadding);
+    }
+
+    Ok(padded[2..2 + len].to_vec())
+}
+
+fn hmac_sha256(key: &[u8], parts: &[&[u8]]) -> [u8; 32] {
+    let mut engine: HmacEngine<sha256::Hash> = HmacEngine::new(key);
+    for part in parts.iter() {
+        engine.input(part);
+    }
+    Hmac::<sha256::Hash>::from_engine(engine).to_byte_array()
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter()
+        .zip(b.iter())
+        .fold(0u8, |acc, (x, y)| acc | (x ^ y))
+        == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use bitcoin_hashes::hex::FromHex;
+
+    use super::*;
+    use crate::Keys;
+
+    fn secret_key(hex: &str) -> SecretKey {
+        SecretKey::from_str(hex).unwrap()
+    }
+
+    #[test]
+    fn test_calc_padded_len() {
+        let vectors: [(usize, usize); 24] = [
+            (16, 32),
+            (32, 32),
+            (33, 64),
+            (37, 64),
+            (45, 64),
+            (49, 64),
+            (64, 64),
+            (65, 96),
+            (100, 128),
+            (111, 128),
+            (200, 224),
+            (250, 256),
+            (320, 320),
+            (383, 384),
+            (384, 384),
+            (400, 448),
+            (500, 512),
+            (512, 512),
+            (515, 640),
+            (700, 768),
+            (800, 896),
+            (900, 1024),
+            (1020, 1024),
+            (65536, 65536),
+        ];
+        for (len, padded) in vectors.into_iter() {
+            assert_eq!(calc_padded_len(len), padded, "len: {len}");
+        }
+    }
+
+    fn payload_with_padded(conversation_key: &ConversationKey, padded: &[u8]) -> String {
+        let nonce = [1u8; 32];
+        let keys = MessageKeys::new(conversation_key, &nonce);
+        let mut buffer: Vec<u8> = padded.to_vec();
+        let mut cipher = ChaCha20::new(&keys.chacha_key.into(), &keys.chacha_nonce.into());
+        cipher.apply_keystream(&mut buffer);
+        let mac: [u8; 32] = hmac_sha256(&keys.hmac_key, &[&nonce, &buffer]);
+        general_purpose::STANDARD
+            .encode([&[Version::V2.as_u8()], &nonce[..], &buffer, &mac].concat())
+    }
+
+    #[test]
+    fn test_conversation_key_vectors() {
+        let vectors = [
+            (
+                "315e59ff51cb9209768cf7da80791ddcaae56ac9775eb25b6dee1234bc5d2268",
+                "c2f9d9948dc8c7c38321e4b85c8558872eafa0641cd269db76848a6073e69133",
+                "3dfef0ce2a4d80a25e7a328accf73448ef67096f65f79588e358d9a0eb9013f1",
+            ),
+            (
+                "a1e37752c9fdc1273be53f68c5f74be7c8905728e8de75800b94262f9497c86e",
+                "03bb7947065dde12ba991ea045132581d0954f042c84e06d8c00066e23c1a800",
+                "4d14f36e81b8452128da64fe6f1eae873baae2f444b02c950b90e43553f2178b",
+            ),
+            (
+                "98a5902fd67518a0c900f0fb62158f278f94a21d6f9d33d30cd3091195500311",
+                "aae65c15f98e5e677b5050de82e3aba47a6fe49b3dab7863cf35d9478ba9f7d1",
+                "9c00b769d5f54d02bf175b7284a1cbd28b6911b06cda6666b2243561ac96bad7",
+            ),
+            (
+                "86ae5ac8034eb2542ce23ec2f84375655dab7f836836bbd3c54cefe9fdc9c19f",
+                "59f90272378089d73f1339710c02e2be6db584e9cdbe86eed3578f0c67c23585",
+                "19f934aafd3324e8415299b64df42049afaa051c71c98d0aa10e1081f2e3e2ba",
+            ),
+            (
+                "49808637b2d21129478041813aceb6f2c9d4929cd1303cdaf4fbdbd690905ff2",
+                "74d2aab13e97827ea21baf253ad7e39b974bb2498cc747cdb168582a11847b65",
+                "4bf304d3c8c4608864c0fe03890b90279328cd24a018ffa9eb8f8ccec06b505d",
+            ),
+            (
+                "af67c382106242c5baabf856efdc0629cc1c5b4061f85b8ceaba52aa7e4b4082",
+                "bdaf0001d63e7ec994fad736eab178ee3c2d7cfc925ae29f37d19224486db57b",
+                "a3a575dd66d45e9379904047ebfb9a7873c471687d0535db00ef2daa24b391db",
+            ),
+            (
+                "0e44e2d1db3c1717b05ffa0f08d102a09c554a1cbbf678ab158b259a44e682f1",
+                "1ffa76c5cc7a836af6914b840483726207cb750889753d7499fb8b76aa8fe0de",
+                "a39970a667b7f861f100e3827f4adbf6f464e2697686fe1a81aeda817d6b8bdf",
+            ),
+            // sec1 = n - 2
+            (
+                "fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364139",
+                "0000000000000000000000000000000000000000000000000000000000000002",
+                "8b6392dbf2ec6a2b2d5b1477fc2be84d63ef254b667cadd31bd3f444c44ae6ba",
+            ),
+            // pub2 = G
+            (
+                "0000000000000000000000000000000000000000000000000000000000000001",
+                "79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+                "3b4610cb7189beb9cc29eb3716ecc6102f1247e8f3101a03a1787d8908aeb54e",
+            ),
+        ];
+
+        for (sec1, pub2, conversation_key) in vectors.into_iter() {
+            let key = ConversationKey::derive(
+                &secret_key(sec1),
+                &XOnlyPublicKey::from_str(pub2).unwrap(),
+            );
+            assert_eq!(
+                key.as_bytes().to_vec(),
+                Vec::<u8>::from_hex(conversation_key).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid_conversation_keys() {
+        // sec1 higher than curve.n, equal to curve.n or zero
+        for sec1 in [
+            "ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+            "fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141",
+            "0000000000000000000000000000000000000000000000000000000000000000",
+        ] {
+            assert!(SecretKey::from_str(sec1).is_err());
+        }
+
+        // pub2 higher than curve.p, or not on the curve
+        for pub2 in [
+            "fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff0",
+            "0000000000000000000000000000000000000000000000000000000000000000",
+        ] {
+            assert!(XOnlyPublicKey::from_str(pub2).is_err());
+        }
+    }
+
+    #[test]
+    fn test_message_keys_vectors() {
+        let conversation_key = ConversationKey::from_slice(
+            &Vec::<u8>::from_hex(
+                "a1a3d60f3470a8612633924e91febf96dc5366ce130f658b1f0fc652c20b3b54",
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        let nonce: [u8; 32] =
+            Vec::<u8>::from_hex("e1e6f880560d6d149ed83dcc7e5861ee62a5ee051f7fde9975fe5d25d2a02d72")
+                .unwrap()
+                .try_into()
+                .unwrap();
+        let keys = MessageKeys::new(&conversation_key, &nonce);
+        assert_eq!(
+            keys.chacha_key.to_vec(),
+            Vec::<u8>::from_hex("f145f3bed47cb70dbeaac07f3a3fe683e822b3715edb7c4fe310829014ce7d76")
+                .unwrap()
+        );
+        assert_eq!(
+            keys.chacha_nonce.to_vec(),
+            Vec::<u8>::from_hex("c4ad129bb01180c0933a160c").unwrap()
+        );
+        assert_eq!(
+            keys.hmac_key.to_vec(),
+            Vec::<u8>::from_hex("027c1db445f05e2eee864a0975b0ddef5b7110583c8c192de3732571ca5838c4")
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_encrypt_decrypt_vectors() {
+        let vectors = [
+            (
+                "0000000000000000000000000000000000000000000000000000000000000001",
+                "0000000000000000000000000000000000000000000000000000000000000002",
+                "c41c775356fd92eadc63ff5a0dc1da211b268cbea22316767095b2871ea1412d",
+                "0000000000000000000000000000000000000000000000000000000000000001",
+                "a",
+                "AgAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAABee0G5VSK0/9YypIObAtDKfYEAjD35uVkHyB0F4DwrcNaCXlCWZKaArsGrY6M9wnuTMxWfp1RTN9Xga8no+kF5Vsb",
+            ),
+            (
+                "0000000000000000000000000000000000000000000000000000000000000002",
+                "0000000000000000000000000000000000000000000000000000000000000001",
+                "c41c775356fd92eadc63ff5a0dc1da211b268cbea22316767095b2871ea1412d",
+                "f00000000000000000000000000000f00000000000000000000000000000000f",
+                "🍕🫃",
+                "AvAAAAAAAAAAAAAAAAAAAPAAAAAAAAAAAAAAAAAAAAAPSKSK6is9ngkX2+cSq85Th16oRTISAOfhStnixqZziKMDvB0QQzgFZdjLTPicCJaV8nDITO+QfaQ61+KbWQIOO2Yj",
+            ),
+            (
+                "5c0c523f52a5b6fad39ed2403092df8cebc36318b39383bca6c00808626fab3a",
+                "4b22aa260e4acb7021e32f38a6cdf4b673c6a277755bfce287e370c924dc936d",
+                "3e2b52a63be47d34fe0a80e34e73d436d6963bc8f39827f327057a9986c20a45",
+                "b635236c42db20f021bb8d1cdff5ca75dd1a0cc72ea742ad750f33010b24f73b",
+                "表ポあA鷗ŒéＢ逍Üßªąñ丂㐀𠀀",
+                "ArY1I2xC2yDwIbuNHN/1ynXdGgzHLqdCrXUPMwELJPc7s7JqlCMJBAIIjfkpHReBPXeoMCyuClwgbT419jUWU1PwaNl4FEQYKCDKVJz+97Mp3K+Q2YGa77B6gpxB/lr1QgoqpDf7wDVrDmOqGoiPjWDqy8KzLueKDcm9BVP8xeTJIxs=",
+            ),
+            (
+                "8f40e50a84a7462e2b8d24c28898ef1f23359fff50d8c509e6fb7ce06e142f9c",
+                "b9b0a1e9cc20100c5faa3bbe2777303d25950616c4c6a3fa2e3e046f936ec2ba",
+                "d5a2f879123145a4b291d767428870f5a8d9e5007193321795b40183d4ab8c2b",
+                "b20989adc3ddc41cd2c435952c0d59a91315d8c5218d5040573fc3749543acaf",
+                "ability🤝的 ȺȾ",
+                "ArIJia3D3cQc0sQ1lSwNWakTFdjFIY1QQFc/w3SVQ6yvbG2S0x4Yu86QGwPTy7mP3961I1XqB6SFFTzqDZZavhxoWMj7mEVGMQIsh2RLWI5EYQaQDIePSnXPlzf7CIt+voTD",
+            ),
+            (
+                "875adb475056aec0b4809bd2db9aa00cff53a649e7b59d8edcbf4e6330b0995c",
+                "9c05781112d5b0a2a7148a222e50e0bd891d6b60c5483f03456e982185944aae",
+                "3b15c977e20bfe4b8482991274635edd94f366595b1a3d2993515705ca3cedb8",
+                "8d4442713eb9d4791175cb040d98d6fc5be8864d6ec2f89cf0895a2b2b72d1b1",
+                "pepper👀їжак",
+                "Ao1EQnE+udR5EXXLBA2Y1vxb6IZNbsL4nPCJWisrctGxY3AduCS+jTUgAAnfvKafkmpy15+i9YMwCdccisRa8SvzW671T2JO4LFSPX31K4kYUKelSAdSPwe9NwO6LhOsnoJ+",
+            ),
+        ];
+
+        for (sec1, sec2, conversation_key, nonce, plaintext, payload) in vectors.into_iter() {
+            let sk1 = secret_key(sec1);
+            let sk2 = secret_key(sec2);
+            let pk2 = Keys::new(sk2).public_key();
+            let pk1 = Keys::new(sk1).public_key();
+
+            let key = ConversationKey::derive(&sk1, &pk2);
+            assert_eq!(
+                key.as_bytes().to_vec(),
+                Vec::<u8>::from_hex(conversation_key).unwrap()
+            );
+            assert_eq!(ConversationKey::derive(&sk2, &pk1), key);
+
+            let nonce: [u8; 32] = Vec::<u8>::from_hex(nonce).unwrap().try_into().unwrap();
+            assert_eq!(
+                encrypt_with_nonce(&key, plaintext.as_bytes(), &nonce).unwrap(),
+                payload
+            );
+            assert_eq!(decrypt(&sk2, &pk1, payload).unwrap(), plaintext);
+        }
+    }
+
+    #[test]
+    fn test_encryption_decryption() {
+        let alice = Keys::generate();
+        let bob = Keys::generate();
+        let sk_alice = alice.secret_key().unwrap();
+        let sk_bob = bob.secret_key().unwrap();
+
+        for content in ["hello", "🍕🫃", &"x".repeat(MAX_PLAINTEXT_SIZE)] {
+            let payload = encrypt(&sk_alice, &bob.public_key(), content, Version::V2).unwrap();
+            assert_eq!(
+                decrypt(&sk_bob, &alice.public_key(), &payload).unwrap(),
+                content
+            );
+        }
+
+        for len in [0, 65536, 100000, 10000000] {
+            assert_eq!(
+                encrypt(&sk_alice, &bob.public_key(), vec![b'a'; len], Version::V2),
+                Err(Error::InvalidPlaintextLength(len))
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid_payloads() {
+        let alice = Keys::generate();
+        let bob = Keys::generate();
+        let sk_alice = alice.secret_key().unwrap();
+        let sk_bob = bob.secret_key().unwrap();
+
+        let payload = encrypt(&sk_alice, &bob.public_key(), "hello", Version::V2).unwrap();
+
+        // Unsupported version
+        let unsupported = format!("#{}", &payload[1..]);
+        assert_eq!(
+            decrypt(&sk_bob, &alice.public_key(), unsupported),
+            Err(Error::VersionNotSupported)
+        );
+
+        // Tampered MAC
+        let mut data = general_purpose::STANDARD.decode(&payload).unwrap();
+        let last = data.len() - 1;
+        data[last] ^= 0x01;
+        let tampered = general_purpose::STANDARD.encode(&data);
+        assert_eq!(
+            decrypt(&sk_bob, &alice.public_key(), tampered),
+            Err(Error::InvalidMac)
+        );
+
+        // Unknown version
+        data[last] ^= 0x01;
+        data[0] = 0x01;
+        let unknown = general_purpose::STANDARD.encode(&data);
+        assert_eq!(
+            decrypt(&sk_bob, &alice.public_key(), unknown),
+            Err(Error::UnknownVersion(0x01))
+        );
+
+        // Wrong key
+        let eve = Keys::generate();
+        assert_eq!(
+            decrypt(&eve.secret_key().unwrap(), &alice.public_key(), &payload),
+            Err(Error::InvalidMac)
+        );
+
+        // Too short or too long
+        assert_eq!(
+            decrypt(&sk_bob, &alice.public_key(), "AgAA"),
+            Err(Error::InvalidPayloadLength(4))
+        );
+        let too_long = "A".repeat(MAX_PAYLOAD_SIZE + 1);
+        assert_eq!(
+            decrypt(&sk_bob, &alice.public_key(), too_long),
+            Err(Error::InvalidPayloadLength(MAX_PAYLOAD_SIZE + 1))
+        );
+
+        // Invalid base64
+        let invalid_base64 = format!("{}!", &payload[..payload.len() - 1]);
+        assert_eq!(
+            decrypt(&sk_bob, &alice.public_key(), invalid_base64),
+            Err(Error::Base64Decode)
+        );
+
+        // Invalid padding, with a valid MAC
+        let conversation_key = ConversationKey::derive(&sk_bob, &alice.public_key());
+        let zero_len = [&[0u8, 0u8][..], &[0u8; 32]].concat();
+        let too_much_padding = [&[0u8, 5u8][..], b"hello", &[0u8; 59]].concat();
+        let too_little_padding = [&[0u8, 40u8][..], &[b'a'; 32]].concat();
+        for padded in [zero_len, too_much_padding, too_little_padding] {
+            assert_eq!(
+                decrypt_with_conversation_key(
+                    &conversation_key,
+                    payload_with_padded(&conversation_key, &padded)
+                ),
+                Err(Error::InvalidPadding)
+            );
+        }
+        assert_eq!(
+            decrypt_with_conversation_key(
+                &conversation_key,
+                payload_with_padded(
+                    &conversation_key,
+                    &[&[0u8, 5u8][..], b"hello", &[0u8; 27]].concat()
+                )
+            )
+            .unwrap(),
+            "hello"
+        );
+    }
+}
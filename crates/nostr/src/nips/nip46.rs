@@ -6,6 +6,7 @@
 //! <https://github.com/nostr-protocol/nips/blob/master/46.md>
 
 use std::borrow::Cow;
+use std::collections::HashMap;
 use std::fmt;
 use std::str::FromStr;
 
@@ -18,8 +19,8 @@ use serde_json::{json, Value};
 use url::form_urlencoded::byte_serialize;
 use url::Url;
 
-use super::nip04;
 use super::nip26::{self, sign_delegation, Conditions};
+use super::{nip04, nip44};
 use crate::key::{self, Keys};
 use crate::UnsignedEvent;
 
@@ -41,6 +42,9 @@ pub enum Error {
     /// NIP04 error
     #[error(transparent)]
     NIP04(#[from] nip04::Error),
+    /// NIP44 error
+    #[error(transparent)]
+    NIP44(#[from] nip44::Error),
     /// NIP26 error
     #[error(transparent)]
     NIP26(#[from] nip26::Error),
@@ -50,6 +54,9 @@ pub enum Error {
     /// Invalid request
     #[error("invalid request")]
     InvalidRequest,
+    /// Invalid response
+    #[error("invalid response")]
+    InvalidResponse,
     /// Too many/few params
     #[error("too many/few params")]
     InvalidParamsLength,
@@ -74,7 +81,14 @@ pub enum Request {
     /// Sign [`UnsignedEvent`]
     SignEvent(UnsignedEvent),
     /// Connect
-    Connect(XOnlyPublicKey),
+    Connect {
+        /// Pubkey
+        public_key: XOnlyPublicKey,
+        /// Optional secret
+        secret: Option<String>,
+        /// Optional requested permissions (comma separated, ex. `nip44_encrypt,sign_event:4`)
+        permissions: Option<String>,
+    },
     /// Disconnect
     Disconnect,
     /// Delegate
@@ -100,6 +114,24 @@ pub enum Request {
     },
     /// Sign Schnorr
     SignSchnorr(String),
+    /// Get relays
+    GetRelays,
+    /// Ping
+    Ping,
+    /// Encrypt text (NIP44)
+    Nip44Encrypt {
+        /// Pubkey
+        public_key: XOnlyPublicKey,
+        /// Plain text
+        text: String,
+    },
+    /// Decrypt (NIP44)
+    Nip44Decrypt {
+        /// Pubkey
+        public_key: XOnlyPublicKey,
+        /// Ciphertext
+        text: String,
+    },
 }
 
 impl Request {
@@ -109,12 +141,16 @@ impl Request {
             Self::Describe => "describe".to_string(),
             Self::GetPublicKey => "get_public_key".to_string(),
             Self::SignEvent(_) => "sign_event".to_string(),
-            Self::Connect(_) => "connect".to_string(),
+            Self::Connect { .. } => "connect".to_string(),
             Self::Disconnect => "disconnect".to_string(),
             Self::Delegate { .. } => "delegate".to_string(),
             Self::Nip04Encrypt { .. } => "nip04_encrypt".to_string(),
             Self::Nip04Decrypt { .. } => "nip04_decrypt".to_string(),
             Self::SignSchnorr(_) => "sign_schnorr".to_string(),
+            Self::GetRelays => "get_relays".to_string(),
+            Self::Ping => "ping".to_string(),
+            Self::Nip44Encrypt { .. } => "nip44_encrypt".to_string(),
+            Self::Nip44Decrypt { .. } => "nip44_decrypt".to_string(),
         }
     }
 
@@ -124,7 +160,20 @@ impl Request {
             Self::Describe => Vec::new(),
             Self::GetPublicKey => Vec::new(),
             Self::SignEvent(event) => vec![json!(event)],
-            Self::Connect(pubkey) => vec![json!(pubkey)],
+            Self::Connect {
+                public_key,
+                secret,
+                permissions,
+            } => {
+                let mut params = vec![json!(public_key)];
+                if secret.is_some() || permissions.is_some() {
+                    params.push(json!(secret.clone().unwrap_or_default()));
+                }
+                if let Some(permissions) = permissions {
+                    params.push(json!(permissions));
+                }
+                params
+            }
             Self::Disconnect => Vec::new(),
             Self::Delegate {
                 public_key,
@@ -133,10 +182,17 @@ impl Request {
             Self::Nip04Encrypt { public_key, text } => vec![json!(public_key), json!(text)],
             Self::Nip04Decrypt { public_key, text } => vec![json!(public_key), json!(text)],
             Self::SignSchnorr(value) => vec![json!(value)],
+            Self::GetRelays => Vec::new(),
+            Self::Ping => Vec::new(),
+            Self::Nip44Encrypt { public_key, text } => vec![json!(public_key), json!(text)],
+            Self::Nip44Decrypt { public_key, text } => vec![json!(public_key), json!(text)],
         }
     }
 
     /// Generate [`Response`] message for [`Request`]
+    ///
+    /// `disconnect` doesn't have a response, while `get_relays` is not supported (the relays are
+    /// known only by the signer). The `connect` secret is not checked: it's up to the signer.
     pub fn generate_response(self, keys: &Keys) -> Result<Option<Response>, Error> {
         let res: Option<Response> = match self {
             Self::Describe => Some(Response::Describe(vec![
@@ -149,13 +205,17 @@ impl Request {
                 String::from("nip04_encrypt"),
                 String::from("nip04_decrypt"),
                 String::from("sign_schnorr"),
+                String::from("get_relays"),
+                String::from("ping"),
+                String::from("nip44_encrypt"),
+                String::from("nip44_decrypt"),
             ])),
             Self::GetPublicKey => Some(Response::GetPublicKey(keys.public_key())),
             Self::SignEvent(unsigned_event) => {
                 let signed_event = unsigned_event.sign(keys)?;
                 Some(Response::SignEvent(signed_event.sig))
             }
-            Self::Connect(_) => None,
+            Self::Connect { .. } => Some(Response::Connect(String::from("ack"))),
             Self::Disconnect => None,
             Self::Delegate {
                 public_key,
@@ -185,6 +245,21 @@ impl Request {
                 let sig: Signature = keys.sign_schnorr(&message)?;
                 Some(Response::SignSchnorr(sig))
             }
+            Self::GetRelays => return Err(Error::UnsupportedMethod(String::from("get_relays"))),
+            Self::Ping => Some(Response::Pong),
+            Self::Nip44Encrypt { public_key, text } => {
+                let encrypted_content = nip44::encrypt(
+                    &keys.secret_key()?,
+                    &public_key,
+                    text,
+                    nip44::Version::default(),
+                )?;
+                Some(Response::Nip44Encrypt(encrypted_content))
+            }
+            Self::Nip44Decrypt { public_key, text } => {
+                let decrypted_content = nip44::decrypt(&keys.secret_key()?, &public_key, text)?;
+                Some(Response::Nip44Decrypt(decrypted_content))
+            }
         };
         Ok(res)
     }
@@ -203,6 +278,15 @@ pub struct DelegationResult {
     pub sig: Signature,
 }
 
+/// Relay read/write permissions (`get_relays` response)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RelayPermissions {
+    /// Read
+    pub read: bool,
+    /// Write
+    pub write: bool,
+}
+
 /// Response
 #[derive(Debug, Clone)]
 pub enum Response {
@@ -220,6 +304,16 @@ pub enum Response {
     Nip04Decrypt(String),
     /// Sign Schnorr
     SignSchnorr(Signature),
+    /// Connect (`ack` or the secret)
+    Connect(String),
+    /// Relays
+    GetRelays(HashMap<Url, RelayPermissions>),
+    /// Pong
+    Pong,
+    /// Encrypted content (NIP44)
+    Nip44Encrypt(String),
+    /// Decrypted content (NIP44)
+    Nip44Decrypt(String),
 }
 
 /// Message
@@ -268,11 +362,39 @@ impl Message {
                 Response::Nip04Encrypt(encrypted_content) => json!(encrypted_content),
                 Response::Nip04Decrypt(decrypted_content) => json!(decrypted_content),
                 Response::SignSchnorr(sig) => json!(sig),
+                Response::Connect(value) => json!(value),
+                Response::GetRelays(relays) => json!(relays),
+                Response::Pong => json!("pong"),
+                Response::Nip44Encrypt(encrypted_content) => json!(encrypted_content),
+                Response::Nip44Decrypt(decrypted_content) => json!(decrypted_content),
             }),
             error: None,
         }
     }
 
+    /// Compose `auth_url` challenge response
+    ///
+    /// The app must open the [`Url`] to authenticate: the real response is sent after that.
+    pub fn auth_url(req_id: String, url: Url) -> Self {
+        Self::Response {
+            id: req_id,
+            result: Some(json!(AUTH_URL)),
+            error: Some(url.to_string()),
+        }
+    }
+
+    /// Get the [`Url`] of an `auth_url` challenge response
+    pub fn to_auth_url(&self) -> Option<Url> {
+        match self {
+            Self::Response {
+                result: Some(Value::String(result)),
+                error: Some(error),
+                ..
+            } if result == AUTH_URL => Url::parse(error).ok(),
+            _ => None,
+        }
+    }
+
     /// check if current [`Message`] is a request
     pub fn is_request(&self) -> bool {
         match self {
@@ -322,12 +444,26 @@ impl Message {
                     }
                 }
                 "connect" => {
-                    if params.len() != 1 {
+                    if params.is_empty() || params.len() > 3 {
                         return Err(Error::InvalidParamsLength);
                     }
 
-                    let pubkey: XOnlyPublicKey = serde_json::from_value(params[0].to_owned())?;
-                    Ok(Request::Connect(pubkey))
+                    let public_key: XOnlyPublicKey = serde_json::from_value(params[0].to_owned())?;
+                    let secret: Option<String> = match params.get(1) {
+                        Some(value) => Some(serde_json::from_value::<String>(value.to_owned())?)
+                            .filter(|s| !s.is_empty()),
+                        None => None,
+                    };
+                    let permissions: Option<String> = match params.get(2) {
+                        Some(value) => Some(serde_json::from_value::<String>(value.to_owned())?)
+                            .filter(|s| !s.is_empty()),
+                        None => None,
+                    };
+                    Ok(Request::Connect {
+                        public_key,
+                        secret,
+                        permissions,
+                    })
                 }
                 "disconnect" => Ok(Request::Disconnect),
                 "delegate" => {
@@ -368,6 +504,28 @@ impl Message {
                     let value: String = serde_json::from_value(params[0].clone())?;
                     Ok(Request::SignSchnorr(value))
                 }
+                "get_relays" => Ok(Request::GetRelays),
+                "ping" => Ok(Request::Ping),
+                "nip44_encrypt" => {
+                    if params.len() != 2 {
+                        return Err(Error::InvalidParamsLength);
+                    }
+
+                    Ok(Request::Nip44Encrypt {
+                        public_key: serde_json::from_value(params[0].clone())?,
+                        text: serde_json::from_value(params[1].clone())?,
+                    })
+                }
+                "nip44_decrypt" => {
+                    if params.len() != 2 {
+                        return Err(Error::InvalidParamsLength);
+                    }
+
+                    Ok(Request::Nip44Decrypt {
+                        public_key: serde_json::from_value(params[0].clone())?,
+                        text: serde_json::from_value(params[1].clone())?,
+                    })
+                }
                 other => Err(Error::UnsupportedMethod(other.to_string())),
             }
         } else {
@@ -375,13 +533,60 @@ impl Message {
         }
     }
 
+    /// Convert [`Message`] to the [`Response`] of a [`Request`]
+    pub fn to_response(&self, req: &Request) -> Result<Response, Error> {
+        if let Message::Response {
+            result: Some(result),
+            ..
+        } = self
+        {
+            let result: Value = result.to_owned();
+            Ok(match req {
+                Request::Describe => Response::Describe(serde_json::from_value(result)?),
+                Request::GetPublicKey => Response::GetPublicKey(serde_json::from_value(result)?),
+                Request::SignEvent(_) => Response::SignEvent(serde_json::from_value(result)?),
+                Request::Connect { .. } => Response::Connect(serde_json::from_value(result)?),
+                Request::Disconnect => return Err(Error::InvalidResponse),
+                Request::Delegate { .. } => Response::Delegate(serde_json::from_value(result)?),
+                Request::Nip04Encrypt { .. } => {
+                    Response::Nip04Encrypt(serde_json::from_value(result)?)
+                }
+                Request::Nip04Decrypt { .. } => {
+                    Response::Nip04Decrypt(serde_json::from_value(result)?)
+                }
+                Request::SignSchnorr(_) => Response::SignSchnorr(serde_json::from_value(result)?),
+                Request::GetRelays => Response::GetRelays(serde_json::from_value(result)?),
+                Request::Ping => {
+                    if result != json!("pong") {
+                        return Err(Error::InvalidResponse);
+                    }
+                    Response::Pong
+                }
+                Request::Nip44Encrypt { .. } => {
+                    Response::Nip44Encrypt(serde_json::from_value(result)?)
+                }
+                Request::Nip44Decrypt { .. } => {
+                    Response::Nip44Decrypt(serde_json::from_value(result)?)
+                }
+            })
+        } else {
+            Err(Error::InvalidResponse)
+        }
+    }
+
     /// Generate [`Response`] message for [`Request`]
+    ///
+    /// If the response can't be generated (ex. `get_relays`), an error response is returned.
     pub fn generate_response(&self, keys: &Keys) -> Result<Option<Self>, Error> {
         let req = self.to_request()?;
-        if let Some(res) = req.generate_response(keys)? {
-            Ok(Some(Self::response(self.id(), res)))
-        } else {
-            Ok(None)
+        match req.generate_response(keys) {
+            Ok(Some(res)) => Ok(Some(Self::response(self.id(), res))),
+            Ok(None) => Ok(None),
+            Err(e) => Ok(Some(Self::Response {
+                id: self.id(),
+                result: None,
+                error: Some(e.to_string()),
+            })),
         }
     }
 }
@@ -395,6 +600,10 @@ where
 
 /// NIP46 URI Scheme
 pub const NOSTR_CONNECT_URI_SCHEME: &str = "nostrconnect";
+/// NIP46 bunker URI Scheme
+pub const NOSTR_CONNECT_BUNKER_URI_SCHEME: &str = "bunker";
+/// `auth_url` challenge response result
+const AUTH_URL: &str = "auth_url";
 
 /// Nostr Connect Metadata
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
@@ -572,6 +781,101 @@ impl fmt::Display for NostrConnectURI {
     }
 }
 
+/// Nostr Connect bunker URI
+///
+/// Generated by the `Signer` and used by the `App` to connect.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct BunkerURI {
+    /// Signer Pubkey
+    pub signer_public_key: XOnlyPublicKey,
+    /// Relays where the `Signer` listens for messages
+    pub relays: Vec<Url>,
+    /// Optional secret (single-use)
+    pub secret: Option<String>,
+}
+
+impl BunkerURI {
+    /// Create new [`BunkerURI`]
+    pub fn new(signer_public_key: XOnlyPublicKey, relays: Vec<Url>) -> Self {
+        Self {
+            signer_public_key,
+            relays,
+            secret: None,
+        }
+    }
+
+    /// Set secret
+    pub fn secret<S>(self, secret: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            secret: Some(secret.into()),
+            ..self
+        }
+    }
+}
+
+impl FromStr for BunkerURI {
+    type Err = Error;
+    fn from_str(uri: &str) -> Result<Self, Self::Err> {
+        let url = Url::parse(uri)?;
+
+        if url.scheme() != NOSTR_CONNECT_BUNKER_URI_SCHEME {
+            return Err(Error::InvalidURIScheme);
+        }
+
+        if let Some(pubkey) = url.domain() {
+            let signer_public_key = XOnlyPublicKey::from_str(pubkey)?;
+
+            let mut relays: Vec<Url> = Vec::new();
+            let mut secret: Option<String> = None;
+
+            for (key, value) in url.query_pairs() {
+                match key {
+                    Cow::Borrowed("relay") => {
+                        let value = value.to_string();
+                        relays.push(Url::parse(&value)?);
+                    }
+                    Cow::Borrowed("secret") => {
+                        secret = Some(value.to_string());
+                    }
+                    _ => (),
+                }
+            }
+
+            if !relays.is_empty() {
+                return Ok(Self {
+                    signer_public_key,
+                    relays,
+                    secret,
+                });
+            }
+        }
+
+        Err(Error::InvalidURI)
+    }
+}
+
+impl fmt::Display for BunkerURI {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut params: Vec<String> = self
+            .relays
+            .iter()
+            .map(|url| format!("relay={}", url_encode(url.to_string())))
+            .collect();
+        if let Some(secret) = &self.secret {
+            params.push(format!("secret={}", url_encode(secret)));
+        }
+        write!(
+            f,
+            "{NOSTR_CONNECT_BUNKER_URI_SCHEME}://{}?{}",
+            self.signer_public_key,
+            params.join("&")
+        )
+    }
+}
+
 #[cfg(test)]
 mod test {
     use std::str::FromStr;
@@ -607,4 +911,136 @@ mod test {
         assert_eq!(uri, NostrConnectURI::new(pubkey, relay_url, app_name));
         Ok(())
     }
+
+    #[test]
+    fn test_bunker_uri() -> Result<()> {
+        let pubkey = XOnlyPublicKey::from_str(
+            "b889ff5b1513b641e2a139f661a661364979c5beee91842f8f0ef42ab558e9d4",
+        )?;
+        let relays = vec![
+            Url::parse("wss://relay.damus.io")?,
+            Url::parse("wss://nos.lol")?,
+        ];
+        let uri = BunkerURI::new(pubkey, relays).secret("abcd");
+        let expected = "bunker://b889ff5b1513b641e2a139f661a661364979c5beee91842f8f0ef42ab558e9d4?relay=wss%3A%2F%2Frelay.damus.io%2F&relay=wss%3A%2F%2Fnos.lol%2F&secret=abcd";
+        assert_eq!(uri.to_string(), expected);
+        assert_eq!(BunkerURI::from_str(expected)?, uri);
+
+        // Missing relays
+        assert!(BunkerURI::from_str(
+            "bunker://b889ff5b1513b641e2a139f661a661364979c5beee91842f8f0ef42ab558e9d4"
+        )
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_request_roundtrip() -> Result<()> {
+        let keys = Keys::generate();
+        let public_key = keys.public_key();
+
+        let requests = vec![
+            Request::Connect {
+                public_key,
+                secret: Some(String::from("abcd")),
+                permissions: Some(String::from("nip44_encrypt,sign_event:4")),
+            },
+            Request::Connect {
+                public_key,
+                secret: None,
+                permissions: None,
+            },
+            Request::GetRelays,
+            Request::Ping,
+            Request::Nip44Encrypt {
+                public_key,
+                text: String::from("hello"),
+            },
+        ];
+        for req in requests.into_iter() {
+            let msg = Message::from_json(Message::request(req.clone()).as_json())?;
+            let parsed = msg.to_request()?;
+            assert_eq!(parsed.method(), req.method());
+            assert_eq!(parsed.params(), req.params());
+        }
+
+        // Permissions without secret
+        let msg = Message::from_json(format!(
+            r#"{{"id":"1","method":"connect","params":["{public_key}","","sign_event"]}}"#
+        ))?;
+        match msg.to_request()? {
+            Request::Connect {
+                secret,
+                permissions,
+                ..
+            } => {
+                assert_eq!(secret, None);
+                assert_eq!(permissions, Some(String::from("sign_event")));
+            }
+            req => panic!("unexpected request: {req:?}"),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_response_roundtrip() -> Result<()> {
+        let keys = Keys::generate();
+        let receiver = Keys::generate();
+
+        let req = Request::Nip44Encrypt {
+            public_key: receiver.public_key(),
+            text: String::from("hello"),
+        };
+        let msg = Message::request(req.clone());
+        let res = Message::from_json(msg.generate_response(&keys)?.unwrap().as_json())?;
+        assert_eq!(res.id(), msg.id());
+        let encrypted = match res.to_response(&req)? {
+            Response::Nip44Encrypt(encrypted) => encrypted,
+            res => panic!("unexpected response: {res:?}"),
+        };
+        assert_eq!(
+            nip44::decrypt(&receiver.secret_key()?, &keys.public_key(), encrypted)?,
+            "hello"
+        );
+
+        let msg = Message::request(Request::Ping);
+        let res = Message::from_json(msg.generate_response(&keys)?.unwrap().as_json())?;
+        assert!(matches!(res.to_response(&Request::Ping)?, Response::Pong));
+
+        // Relays are known only by the signer
+        let msg = Message::request(Request::GetRelays);
+        let res = msg.generate_response(&keys)?.unwrap();
+        assert!(matches!(
+            res,
+            Message::Response {
+                result: None,
+                error: Some(_),
+                ..
+            }
+        ));
+
+        let relay_url = Url::parse("wss://relay.damus.io")?;
+        let mut relays = HashMap::new();
+        relays.insert(
+            relay_url.clone(),
+            RelayPermissions {
+                read: true,
+                write: false,
+            },
+        );
+        let res = Message::from_json(
+            Message::response(String::from("1"), Response::GetRelays(relays)).as_json(),
+        )?;
+        match res.to_response(&Request::GetRelays)? {
+            Response::GetRelays(relays) => assert!(!relays[&relay_url].write),
+            res => panic!("unexpected response: {res:?}"),
+        }
+
+        // Auth challenge
+        let auth_url = Url::parse("https://bunker.example.com/auth/1")?;
+        let res = Message::from_json(Message::auth_url(msg.id(), auth_url.clone()).as_json())?;
+        assert_eq!(res.to_auth_url(), Some(auth_url));
+        assert_eq!(Message::request(Request::Ping).to_auth_url(), None);
+        Ok(())
+    }
 }
@@ -0,0 +1,333 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! NIP49
+//!
+//! <https://github.com/nostr-protocol/nips/blob/master/49.md>
+
+use std::fmt;
+
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use secp256k1::rand::rngs::OsRng;
+use secp256k1::rand::RngCore;
+use secp256k1::SecretKey;
+use unicode_normalization::UnicodeNormalization;
+
+use super::nip19;
+
+const SALT_SIZE: usize = 16;
+const NONCE_SIZE: usize = 24;
+const CIPHERTEXT_SIZE: usize = 48;
+const ENCRYPTED_SECRET_KEY_SIZE: usize = 1 + 1 + SALT_SIZE + NONCE_SIZE + 1 + CIPHERTEXT_SIZE;
+/// Max scrypt work factor (4 GiB of memory)
+const MAX_LOG_N: u8 = 22;
+
+/// `NIP49` error
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum Error {
+    /// Invalid length
+    #[error("invalid length: expected {expected}, found {found}")]
+    InvalidLength {
+        /// Expected length
+        expected: usize,
+        /// Found length
+        found: usize,
+    },
+    /// Unknown version
+    #[error("unknown version: {0}")]
+    UnknownVersion(u8),
+    /// Unknown key security
+    #[error("unknown key security: {0}")]
+    UnknownKeySecurity(u8),
+    /// Scrypt work factor too high
+    #[error("log_n too high: {0} (max {MAX_LOG_N})")]
+    LogNTooHigh(u8),
+    /// Invalid scrypt params
+    #[error("invalid scrypt params")]
+    InvalidScryptParams,
+    /// Encryption failed
+    #[error("encryption failed")]
+    EncryptionFailed,
+    /// Decryption failed (wrong password or corrupted data)
+    #[error("decryption failed")]
+    DecryptionFailed,
+    /// Secp256k1 error
+    #[error(transparent)]
+    Secp256k1(#[from] secp256k1::Error),
+    /// NIP19 error
+    #[error(transparent)]
+    NIP19(#[from] nip19::Error),
+}
+
+/// Encrypted secret key version
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Version {
+    /// V2 (scrypt + XChaCha20-Poly1305)
+    #[default]
+    V2 = 0x02,
+}
+
+impl TryFrom<u8> for Version {
+    type Error = Error;
+
+    fn try_from(version: u8) -> Result<Self, Self::Error> {
+        match version {
+            0x02 => Ok(Self::V2),
+            v => Err(Error::UnknownVersion(v)),
+        }
+    }
+}
+
+/// How the secret key has been handled before the encryption
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum KeySecurity {
+    /// The key has been known to have been handled insecurely (stored unencrypted, cut and paste unencrypted, etc)
+    Weak = 0x00,
+    /// The key has NOT been known to have been handled insecurely
+    Medium = 0x01,
+    /// The client does not track this data
+    #[default]
+    Unknown = 0x02,
+}
+
+impl TryFrom<u8> for KeySecurity {
+    type Error = Error;
+
+    fn try_from(key_security: u8) -> Result<Self, Self::Error> {
+        match key_security {
+            0x00 => Ok(Self::Weak),
+            0x01 => Ok(Self::Medium),
+            0x02 => Ok(Self::Unknown),
+            v => Err(Error::UnknownKeySecurity(v)),
+        }
+    }
+}
+
+/// Password-encrypted secret key
+#[derive(Clone, PartialEq, Eq)]
+pub struct EncryptedSecretKey {
+    version: Version,
+    log_n: u8,
+    salt: [u8; SALT_SIZE],
+    nonce: [u8; NONCE_SIZE],
+    key_security: KeySecurity,
+    encrypted_key: [u8; CIPHERTEXT_SIZE],
+}
+
+impl fmt::Debug for EncryptedSecretKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EncryptedSecretKey")
+            .field("version", &self.version)
+            .field("log_n", &self.log_n)
+            .field("key_security", &self.key_security)
+            .finish()
+    }
+}
+
+impl EncryptedSecretKey {
+    /// Encrypt [`SecretKey`] with password
+    ///
+    /// `log_n` is the scrypt work factor: memory and time double at each increment
+    /// (16 takes 64 MiB, 20 takes 1 GiB). Max is 22.
+    pub fn new<S>(
+        secret_key: &SecretKey,
+        password: S,
+        log_n: u8,
+        key_security: KeySecurity,
+    ) -> Result<Self, Error>
+    where
+        S: AsRef<str>,
+    {
+        if log_n > MAX_LOG_N {
+            return Err(Error::LogNTooHigh(log_n));
+        }
+
+        let mut rng = OsRng;
+        let mut salt = [0u8; SALT_SIZE];
+        rng.fill_bytes(&mut salt);
+        let mut nonce = [0u8; NONCE_SIZE];
+        rng.fill_bytes(&mut nonce);
+
+        let key: [u8; 32] = derive_key(password, &salt, log_n)?;
+        let cipher = XChaCha20Poly1305::new(&key.into());
+        let payload = Payload {
+            msg: &secret_key.secret_bytes(),
+            aad: &[key_security as u8],
+        };
+        let ciphertext: Vec<u8> = cipher
+            .encrypt(XNonce::from_slice(&nonce), payload)
+            .map_err(|_| Error::EncryptionFailed)?;
+
+        let mut encrypted_key = [0u8; CIPHERTEXT_SIZE];
+        encrypted_key.copy_from_slice(&ciphertext);
+
+        Ok(Self {
+            version: Version::default(),
+            log_n,
+            salt,
+            nonce,
+            key_security,
+            encrypted_key,
+        })
+    }
+
+    /// Parse from bytes
+    ///
+    /// Keys with a `log_n` above 22 are rejected, since deriving them would take too much memory.
+    pub fn from_slice(slice: &[u8]) -> Result<Self, Error> {
+        if slice.len() != ENCRYPTED_SECRET_KEY_SIZE {
+            return Err(Error::InvalidLength {
+                expected: ENCRYPTED_SECRET_KEY_SIZE,
+                found: slice.len(),
+            });
+        }
+
+        let version = Version::try_from(slice[0])?;
+        let log_n: u8 = slice[1];
+        if log_n > MAX_LOG_N {
+            return Err(Error::LogNTooHigh(log_n));
+        }
+
+        let mut salt = [0u8; SALT_SIZE];
+        salt.copy_from_slice(&slice[2..2 + SALT_SIZE]);
+
+        let mut nonce = [0u8; NONCE_SIZE];
+        nonce.copy_from_slice(&slice[2 + SALT_SIZE..2 + SALT_SIZE + NONCE_SIZE]);
+
+        let key_security = KeySecurity::try_from(slice[2 + SALT_SIZE + NONCE_SIZE])?;
+
+        let mut encrypted_key = [0u8; CIPHERTEXT_SIZE];
+        encrypted_key.copy_from_slice(&slice[3 + SALT_SIZE + NONCE_SIZE..]);
+
+        Ok(Self {
+            version,
+            log_n,
+            salt,
+            nonce,
+            key_security,
+            encrypted_key,
+        })
+    }
+
+    /// Serialize to bytes
+    pub fn as_vec(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Vec::with_capacity(ENCRYPTED_SECRET_KEY_SIZE);
+        bytes.push(self.version as u8);
+        bytes.push(self.log_n);
+        bytes.extend_from_slice(&self.salt);
+        bytes.extend_from_slice(&self.nonce);
+        bytes.push(self.key_security as u8);
+        bytes.extend_from_slice(&self.encrypted_key);
+        bytes
+    }
+
+    /// Get [`Version`]
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    /// Get scrypt work factor
+    pub fn log_n(&self) -> u8 {
+        self.log_n
+    }
+
+    /// Get [`KeySecurity`]
+    pub fn key_security(&self) -> KeySecurity {
+        self.key_security
+    }
+
+    /// Decrypt [`SecretKey`] with password
+    pub fn to_secret_key<S>(&self, password: S) -> Result<SecretKey, Error>
+    where
+        S: AsRef<str>,
+    {
+        let key: [u8; 32] = derive_key(password, &self.salt, self.log_n)?;
+        let cipher = XChaCha20Poly1305::new(&key.into());
+        let payload = Payload {
+            msg: &self.encrypted_key,
+            aad: &[self.key_security as u8],
+        };
+        let secret_key: Vec<u8> = cipher
+            .decrypt(XNonce::from_slice(&self.nonce), payload)
+            .map_err(|_| Error::DecryptionFailed)?;
+        Ok(SecretKey::from_slice(&secret_key)?)
+    }
+}
+
+/// Derive the symmetric key from the NFKC normalized password
+fn derive_key<S>(password: S, salt: &[u8], log_n: u8) -> Result<[u8; 32], Error>
+where
+    S: AsRef<str>,
+{
+    let password: String = password.as_ref().nfkc().collect();
+    let params = scrypt::Params::new(log_n, 8, 1, 32).map_err(|_| Error::InvalidScryptParams)?;
+    let mut key = [0u8; 32];
+    scrypt::scrypt(password.as_bytes(), salt, &params, &mut key)
+        .map_err(|_| Error::InvalidScryptParams)?;
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::nips::nip19::{FromBech32, ToBech32};
+    use crate::Keys;
+
+    #[test]
+    fn test_decrypt_nip49_vector() {
+        let encrypted = EncryptedSecretKey::from_bech32("ncryptsec1qgg9947rlpvqu76pj5ecreduf9jxhselq2nae2kghhvd5g7dgjtcxfqtd67p9m0w57lspw8gsq6yphnm8623nsl8xn9j4jdzz84zm3frztj3z7s35vpzmqf6ksu8r89qk5z2zxfmu5gv8th8wclt0h4p").unwrap();
+        assert_eq!(encrypted.version(), Version::V2);
+        assert_eq!(encrypted.log_n(), 16);
+        assert_eq!(encrypted.key_security(), KeySecurity::Weak);
+
+        let keys = Keys::from_encrypted(&encrypted, "nostr").unwrap();
+        assert_eq!(
+            keys.secret_key().unwrap(),
+            SecretKey::from_str("3501454135014541350145413501453fefb02227e449e57cf4d3a3ce05378683")
+                .unwrap()
+        );
+
+        assert_eq!(
+            encrypted.to_secret_key("wrong"),
+            Err(Error::DecryptionFailed)
+        );
+    }
+
+    #[test]
+    fn test_encrypt_roundtrip() {
+        let keys = Keys::generate();
+        let secret_key = keys.secret_key().unwrap();
+
+        // Password is NFKC normalized
+        let encrypted =
+            EncryptedSecretKey::new(&secret_key, "ÅΩẛ̣", 4, KeySecurity::Medium).unwrap();
+        let bech32 = encrypted.to_bech32().unwrap();
+        assert!(bech32.starts_with("ncryptsec1"));
+
+        let decoded = EncryptedSecretKey::from_bech32(bech32).unwrap();
+        assert_eq!(decoded, encrypted);
+        assert_eq!(decoded.key_security(), KeySecurity::Medium);
+        assert_eq!(decoded.to_secret_key("ÅΩṩ").unwrap(), secret_key);
+
+        // Key security byte is authenticated
+        let mut bytes = encrypted.as_vec();
+        bytes[2 + SALT_SIZE + NONCE_SIZE] = KeySecurity::Weak as u8;
+        let tampered = EncryptedSecretKey::from_slice(&bytes).unwrap();
+        assert_eq!(tampered.to_secret_key("ÅΩṩ"), Err(Error::DecryptionFailed));
+
+        // Work factor is capped
+        let mut bytes = encrypted.as_vec();
+        bytes[1] = 64;
+        assert_eq!(
+            EncryptedSecretKey::from_slice(&bytes),
+            Err(Error::LogNTooHigh(64))
+        );
+        assert_eq!(
+            EncryptedSecretKey::new(&secret_key, "ÅΩẛ̣", 23, KeySecurity::Medium),
+            Err(Error::LogNTooHigh(23))
+        );
+    }
+}
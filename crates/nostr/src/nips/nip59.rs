@@ -0,0 +1,215 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! NIP59
+//!
+//! <https://github.com/nostr-protocol/nips/blob/master/59.md>
+
+use secp256k1::rand::{self, Rng};
+use secp256k1::XOnlyPublicKey;
+
+use super::nip44;
+use crate::event::unsigned::{self, UnsignedEvent};
+use crate::signer::{NostrSigner, SignerError};
+use crate::{key, Event, EventId, Keys, Kind, Timestamp};
+
+/// Range of the random tweak applied to `created_at` (2 days)
+pub const RANGE_RANDOM_TIMESTAMP_TWEAK: u64 = 2 * 24 * 60 * 60;
+
+/// `NIP59` error
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Key error
+    #[error(transparent)]
+    Key(#[from] key::Error),
+    /// Event error
+    #[error(transparent)]
+    Event(#[from] crate::event::Error),
+    /// Unsigned event error
+    #[error(transparent)]
+    Unsigned(#[from] unsigned::Error),
+    /// NIP44 error
+    #[error(transparent)]
+    NIP44(#[from] nip44::Error),
+    /// Signer error
+    #[error(transparent)]
+    Signer(#[from] SignerError),
+    /// Not a gift wrap
+    #[error("not a gift wrap (kind 1059)")]
+    NotGiftWrap,
+    /// Not a seal
+    #[error("not a seal (kind 13)")]
+    NotSeal,
+    /// Seal author not match the rumor author
+    #[error("seal author not match the rumor author")]
+    SenderMismatch,
+    /// Invalid rumor id
+    #[error("invalid rumor id")]
+    InvalidRumorId,
+}
+
+/// Random timestamp in the past, up to [`RANGE_RANDOM_TIMESTAMP_TWEAK`]
+pub fn random_timestamp() -> Timestamp {
+    let tweak: u64 = rand::thread_rng().gen_range(0..RANGE_RANDOM_TIMESTAMP_TWEAK);
+    Timestamp::now() - tweak
+}
+
+/// Unwrapped gift wrap
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnwrappedGift {
+    /// Sender public key (author of the seal)
+    pub sender: XOnlyPublicKey,
+    /// Rumor
+    pub rumor: UnsignedEvent,
+}
+
+impl UnwrappedGift {
+    /// Unwrap a gift wrap, verifying the seal signature and the rumor author
+    pub fn from_gift_wrap(receiver_keys: &Keys, gift_wrap: &Event) -> Result<Self, Error> {
+        if gift_wrap.kind != Kind::GiftWrap {
+            return Err(Error::NotGiftWrap);
+        }
+
+        let secret_key = receiver_keys.secret_key()?;
+
+        // Open the gift wrap
+        let seal: String = nip44::decrypt(&secret_key, &gift_wrap.pubkey, &gift_wrap.content)?;
+        let seal: Event = Self::parse_seal(seal)?;
+
+        // Open the seal
+        let rumor: String = nip44::decrypt(&secret_key, &seal.pubkey, &seal.content)?;
+        Self::from_seal_and_rumor(seal, rumor)
+    }
+
+    /// Unwrap a gift wrap with a [`NostrSigner`], verifying the seal signature and the rumor author
+    pub async fn from_gift_wrap_with_signer<T>(signer: &T, gift_wrap: &Event) -> Result<Self, Error>
+    where
+        T: NostrSigner + ?Sized,
+    {
+        if gift_wrap.kind != Kind::GiftWrap {
+            return Err(Error::NotGiftWrap);
+        }
+
+        // Open the gift wrap
+        let seal: String = signer
+            .nip44_decrypt(gift_wrap.pubkey, gift_wrap.content.clone())
+            .await?;
+        let seal: Event = Self::parse_seal(seal)?;
+
+        // Open the seal
+        let rumor: String = signer
+            .nip44_decrypt(seal.pubkey, seal.content.clone())
+            .await?;
+        Self::from_seal_and_rumor(seal, rumor)
+    }
+
+    fn parse_seal(json: String) -> Result<Event, Error> {
+        let seal: Event = Event::from_json(json)?;
+        if seal.kind != Kind::Seal {
+            return Err(Error::NotSeal);
+        }
+        seal.verify()?;
+        Ok(seal)
+    }
+
+    fn from_seal_and_rumor(seal: Event, rumor: String) -> Result<Self, Error> {
+        let rumor: UnsignedEvent = UnsignedEvent::from_json(rumor)?;
+        if rumor.pubkey != seal.pubkey {
+            return Err(Error::SenderMismatch);
+        }
+
+        let id = EventId::new(
+            &rumor.pubkey,
+            rumor.created_at,
+            &rumor.kind,
+            &rumor.tags,
+            &rumor.content,
+        );
+        if id != rumor.id {
+            return Err(Error::InvalidRumorId);
+        }
+
+        Ok(Self {
+            sender: seal.pubkey,
+            rumor,
+        })
+    }
+}
+
+/// Extract the rumor from a gift wrap
+pub fn extract_rumor(receiver_keys: &Keys, gift_wrap: &Event) -> Result<UnwrappedGift, Error> {
+    UnwrappedGift::from_gift_wrap(receiver_keys, gift_wrap)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EventBuilder, Tag};
+
+    #[test]
+    fn test_gift_wrap_roundtrip() {
+        let sender_keys = Keys::generate();
+        let receiver_keys = Keys::generate();
+
+        let rumor: UnsignedEvent =
+            EventBuilder::new_text_note("Are you going to the party tonight?", &[])
+                .to_unsigned_event(sender_keys.public_key());
+
+        let gift_wrap: Event = EventBuilder::gift_wrap(
+            &sender_keys,
+            &receiver_keys.public_key(),
+            rumor.clone(),
+            None,
+        )
+        .unwrap();
+        gift_wrap.verify().unwrap();
+
+        // Metadata hidden
+        assert_eq!(gift_wrap.kind, Kind::GiftWrap);
+        assert_ne!(gift_wrap.pubkey, sender_keys.public_key());
+        assert!(gift_wrap.created_at <= Timestamp::now());
+        assert!(gift_wrap
+            .tags
+            .contains(&Tag::PubKey(receiver_keys.public_key(), None)));
+
+        let unwrapped = extract_rumor(&receiver_keys, &gift_wrap).unwrap();
+        assert_eq!(unwrapped.sender, sender_keys.public_key());
+        assert_eq!(unwrapped.rumor, rumor);
+
+        // Only the receiver can open it
+        let other_keys = Keys::generate();
+        assert!(extract_rumor(&other_keys, &gift_wrap).is_err());
+    }
+
+    #[test]
+    fn test_seal_sender_mismatch() {
+        let sender_keys = Keys::generate();
+        let receiver_keys = Keys::generate();
+        let impersonated = Keys::generate();
+
+        // Rumor of another author
+        let rumor: UnsignedEvent =
+            EventBuilder::new_text_note("hello", &[]).to_unsigned_event(impersonated.public_key());
+        assert!(
+            EventBuilder::seal(&sender_keys, &receiver_keys.public_key(), rumor.clone()).is_err()
+        );
+
+        // Forged seal
+        let content = nip44::encrypt(
+            &sender_keys.secret_key().unwrap(),
+            &receiver_keys.public_key(),
+            rumor.as_json(),
+            nip44::Version::V2,
+        )
+        .unwrap();
+        let seal: Event = EventBuilder::new(Kind::Seal, content, &[])
+            .to_event(&sender_keys)
+            .unwrap();
+        let gift_wrap: Event =
+            EventBuilder::gift_wrap_from_seal(&receiver_keys.public_key(), &seal, None).unwrap();
+        assert!(matches!(
+            extract_rumor(&receiver_keys, &gift_wrap),
+            Err(Error::SenderMismatch)
+        ));
+    }
+}
@@ -24,22 +24,42 @@ pub use crate::event::unsigned::*;
 pub use crate::event::*;
 pub use crate::key::*;
 pub use crate::message::*;
+pub use crate::signer::*;
 pub use crate::types::*;
 pub use crate::{Result, SECP256K1};
 
 // NIPs
 #[cfg(feature = "nip04")]
 pub use crate::nips::nip04::*;
+// NIP04 `encrypt` and `decrypt` take precedence over the NIP44 ones
+#[cfg(feature = "nip04")]
+pub use crate::nips::nip04::{decrypt, encrypt};
 #[cfg(feature = "nip05")]
 pub use crate::nips::nip05::*;
 #[cfg(feature = "nip06")]
 pub use crate::nips::nip06::*;
+pub use crate::nips::nip10::*;
 #[cfg(feature = "nip11")]
 pub use crate::nips::nip11::*;
 pub use crate::nips::nip13::*;
+#[cfg(feature = "nip17")]
+pub use crate::nips::nip17::*;
 #[cfg(feature = "nip19")]
 pub use crate::nips::nip19::*;
+#[cfg(feature = "nip19")]
+pub use crate::nips::nip21::*;
 pub use crate::nips::nip26::*;
+#[cfg(feature = "nip19")]
+pub use crate::nips::nip27::*;
+#[cfg(feature = "nip44")]
+pub use crate::nips::nip44::*;
+// NIP44 `Version` takes precedence over the NIP49 one
+#[cfg(feature = "nip44")]
+pub use crate::nips::nip44::Version;
 #[cfg(feature = "nip46")]
 pub use crate::nips::nip46::*;
+#[cfg(feature = "nip49")]
+pub use crate::nips::nip49::*;
+#[cfg(feature = "nip59")]
+pub use crate::nips::nip59::*;
 pub use crate::nips::nip65::*;
@@ -0,0 +1,174 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Distributed under the MIT software license
+
+//! Signer
+//!
+//! Abstraction over the way events are signed and direct messages are encrypted
+//! (local [`Keys`], remote signers, hardware or enclave based signers, ...).
+
+use std::fmt;
+
+use async_trait::async_trait;
+use secp256k1::XOnlyPublicKey;
+
+#[cfg(feature = "nip04")]
+use crate::nips::nip04;
+#[cfg(feature = "nip44")]
+use crate::nips::nip44;
+use crate::{Event, EventId, Keys, UnsignedEvent};
+
+/// Signer error
+#[derive(Debug, thiserror::Error)]
+pub enum SignerError {
+    /// Backend error
+    #[error(transparent)]
+    Backend(Box<dyn std::error::Error + Send + Sync>),
+    /// Operation not supported by the signer
+    #[error("operation not supported by the signer")]
+    Unsupported,
+    /// The author of the event is not the signer
+    #[error("public key mismatch")]
+    PublicKeyMismatch,
+    /// Event ID doesn't match the event content
+    #[error("invalid event id")]
+    InvalidEventId,
+}
+
+impl SignerError {
+    /// Create a new [`SignerError::Backend`] from any backend error
+    pub fn backend<E>(error: E) -> Self
+    where
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        Self::Backend(Box::new(error))
+    }
+}
+
+/// Nostr signer
+///
+/// Implement this trait to sign events and encrypt messages with a custom backend.
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub trait NostrSigner: fmt::Debug + Send + Sync {
+    /// Get signer [`XOnlyPublicKey`]
+    async fn public_key(&self) -> Result<XOnlyPublicKey, SignerError>;
+
+    /// Sign an [`UnsignedEvent`]
+    ///
+    /// The author of the [`UnsignedEvent`] must be the signer public key.
+    async fn sign_event(&self, unsigned: UnsignedEvent) -> Result<Event, SignerError>;
+
+    /// NIP04 encrypt
+    ///
+    /// Return [`SignerError::Unsupported`] by default.
+    #[cfg(feature = "nip04")]
+    async fn nip04_encrypt(
+        &self,
+        _public_key: XOnlyPublicKey,
+        _content: String,
+    ) -> Result<String, SignerError> {
+        Err(SignerError::Unsupported)
+    }
+
+    /// NIP04 decrypt
+    ///
+    /// Return [`SignerError::Unsupported`] by default.
+    #[cfg(feature = "nip04")]
+    async fn nip04_decrypt(
+        &self,
+        _public_key: XOnlyPublicKey,
+        _payload: String,
+    ) -> Result<String, SignerError> {
+        Err(SignerError::Unsupported)
+    }
+
+    /// NIP44 encrypt
+    ///
+    /// Return [`SignerError::Unsupported`] by default.
+    #[cfg(feature = "nip44")]
+    async fn nip44_encrypt(
+        &self,
+        _public_key: XOnlyPublicKey,
+        _content: String,
+    ) -> Result<String, SignerError> {
+        Err(SignerError::Unsupported)
+    }
+
+    /// NIP44 decrypt
+    ///
+    /// Return [`SignerError::Unsupported`] by default.
+    #[cfg(feature = "nip44")]
+    async fn nip44_decrypt(
+        &self,
+        _public_key: XOnlyPublicKey,
+        _payload: String,
+    ) -> Result<String, SignerError> {
+        Err(SignerError::Unsupported)
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl NostrSigner for Keys {
+    async fn public_key(&self) -> Result<XOnlyPublicKey, SignerError> {
+        Ok(Keys::public_key(self))
+    }
+
+    async fn sign_event(&self, unsigned: UnsignedEvent) -> Result<Event, SignerError> {
+        if unsigned.pubkey != Keys::public_key(self) {
+            return Err(SignerError::PublicKeyMismatch);
+        }
+        let id = EventId::new(
+            &unsigned.pubkey,
+            unsigned.created_at,
+            &unsigned.kind,
+            &unsigned.tags,
+            &unsigned.content,
+        );
+        if id != unsigned.id {
+            return Err(SignerError::InvalidEventId);
+        }
+        unsigned.sign(self).map_err(SignerError::backend)
+    }
+
+    #[cfg(feature = "nip04")]
+    async fn nip04_encrypt(
+        &self,
+        public_key: XOnlyPublicKey,
+        content: String,
+    ) -> Result<String, SignerError> {
+        let secret_key = self.secret_key().map_err(SignerError::backend)?;
+        nip04::encrypt(&secret_key, &public_key, content).map_err(SignerError::backend)
+    }
+
+    #[cfg(feature = "nip04")]
+    async fn nip04_decrypt(
+        &self,
+        public_key: XOnlyPublicKey,
+        payload: String,
+    ) -> Result<String, SignerError> {
+        let secret_key = self.secret_key().map_err(SignerError::backend)?;
+        nip04::decrypt(&secret_key, &public_key, payload).map_err(SignerError::backend)
+    }
+
+    #[cfg(feature = "nip44")]
+    async fn nip44_encrypt(
+        &self,
+        public_key: XOnlyPublicKey,
+        content: String,
+    ) -> Result<String, SignerError> {
+        let secret_key = self.secret_key().map_err(SignerError::backend)?;
+        nip44::encrypt(&secret_key, &public_key, content, nip44::Version::default())
+            .map_err(SignerError::backend)
+    }
+
+    #[cfg(feature = "nip44")]
+    async fn nip44_decrypt(
+        &self,
+        public_key: XOnlyPublicKey,
+        payload: String,
+    ) -> Result<String, SignerError> {
+        let secret_key = self.secret_key().map_err(SignerError::backend)?;
+        nip44::decrypt(&secret_key, &public_key, payload).map_err(SignerError::backend)
+    }
+}